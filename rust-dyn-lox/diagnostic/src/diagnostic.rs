@@ -1,4 +1,7 @@
-use crate::diagnostic_code::{DiagnosticCode, Severity};
+use crate::{
+  diagnostic_code::{DiagnosticCode, Severity},
+  json,
+};
 
 /// Represents a source code location
 #[derive(Debug, Clone, PartialEq)]
@@ -9,6 +12,55 @@ pub struct Span {
   pub length: usize,
 }
 
+impl Span {
+  /// Function that converts the byte range `start..end` over `src` of `file` into a span, the line
+  /// and column are 1-based like the formatter reads them while the column and length count
+  /// characters so multi-byte characters take a single column, offsets that are out of bounds or
+  /// inside a character are moved back to the closest character boundary.
+  pub fn from_byte_range(file: &str, src: &str, start: usize, end: usize) -> Span {
+    let floor = |mut offset: usize| {
+      offset = offset.min(src.len());
+      while !src.is_char_boundary(offset) {
+        offset -= 1;
+      }
+      offset
+    };
+    let start = floor(start);
+    let end = floor(end).max(start);
+
+    let before = &src[..start];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    Span {
+      file: file.to_string(),
+      line,
+      column: src[line_start..start].chars().count() + 1,
+      length: src[start..end].chars().count(),
+    }
+  }
+
+  /// Function that returns the smallest span covering both spans. A span can't cross lines so
+  /// when they're on different lines the one that starts first is kept as it is.
+  pub fn merge(&self, other: &Span) -> Span {
+    let (first, second) = if (other.line, other.column) < (self.line, self.column) {
+      (other, self)
+    } else {
+      (self, other)
+    };
+
+    if first.line != second.line {
+      return first.clone();
+    }
+
+    let end = (first.column + first.length).max(second.column + second.length);
+    Span {
+      length: end - first.column,
+      ..first.clone()
+    }
+  }
+}
+
 /// Label for underlining specific parts of code
 #[derive(Debug, Clone)]
 pub struct Label {
@@ -41,6 +93,25 @@ impl Label {
   }
 }
 
+/// Fix-it that replaces the code under `span` with `replacement`, an empty span inserts it
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+  pub span: Span,
+  pub replacement: String,
+}
+
+impl Suggestion {
+  /// Function that applies the suggestion to the source line it points into, the column counts
+  /// characters from 1 like the labels do
+  pub fn apply(&self, line: &str) -> String {
+    let start = self.span.column.saturating_sub(1);
+    let mut fixed = line.chars().take(start).collect::<String>();
+    fixed.push_str(&self.replacement);
+    fixed.extend(line.chars().skip(start + self.span.length));
+    fixed
+  }
+}
+
 /// A single diagnostic message
 #[derive(Debug, Clone)]
 pub struct Diagnostic {
@@ -50,6 +121,7 @@ pub struct Diagnostic {
   pub labels: Vec<Label>,
   pub notes: Vec<String>,
   pub help: Option<String>,
+  pub suggestions: Vec<Suggestion>,
 }
 
 impl Diagnostic {
@@ -61,6 +133,25 @@ impl Diagnostic {
       labels: Vec::new(),
       notes: Vec::new(),
       help: None,
+      suggestions: Vec::new(),
+    }
+  }
+
+  /// Function that creates a standalone informational diagnostic, unlike `with_note` it's a
+  /// message of its own and never counts as an error or a warning
+  pub fn note(code: DiagnosticCode, message: String) -> Self {
+    Self {
+      severity: Severity::Note,
+      ..Self::new(code, message)
+    }
+  }
+
+  /// Function that creates a standalone suggestion diagnostic, unlike `with_help` it's a message of
+  /// its own and never counts as an error or a warning
+  pub fn help_diag(code: DiagnosticCode, message: String) -> Self {
+    Self {
+      severity: Severity::Help,
+      ..Self::new(code, message)
     }
   }
 
@@ -78,4 +169,72 @@ impl Diagnostic {
     self.help = Some(help);
     self
   }
+
+  /// Function that attaches a fix-it, the formatter shows the source line with it applied
+  pub fn with_suggestion(mut self, span: Span, replacement: String) -> Self {
+    self.suggestions.push(Suggestion { span, replacement });
+    self
+  }
+
+  /// Function that returns the first primary label, falling back to the first label
+  pub fn primary_label(&self) -> Option<&Label> {
+    self
+      .labels
+      .iter()
+      .find(|label| label.style == LabelStyle::Primary)
+      .or(self.labels.first())
+  }
+}
+
+impl Label {
+  /// Function that renders the label as a JSON object
+  pub fn to_json(&self) -> String {
+    format!(
+      "{{\"style\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"length\": {}, \"message\": {}}}",
+      json::quote(match self.style {
+        LabelStyle::Primary => "primary",
+        LabelStyle::Secondary => "secondary",
+      }),
+      json::quote(&self.span.file),
+      self.span.line,
+      self.span.column,
+      self.span.length,
+      self.message.as_deref().map_or("null".to_string(), json::quote)
+    )
+  }
+}
+
+impl Diagnostic {
+  /// Function that renders the diagnostic as a JSON object, see `DiagnosticEngine::format_all_json`
+  /// for the shape
+  pub fn to_json(&self) -> String {
+    let labels = self
+      .labels
+      .iter()
+      .map(|label| label.to_json())
+      .collect::<Vec<_>>()
+      .join(", ");
+    let notes = self
+      .notes
+      .iter()
+      .map(|note| json::quote(note))
+      .collect::<Vec<_>>()
+      .join(", ");
+
+    format!(
+      "{{\"code\": {}, \"name\": {}, \"severity\": {}, \"message\": {}, \"labels\": [{}], \"notes\": [{}], \"help\": {}}}",
+      json::quote(&self.code.code()),
+      json::quote(&format!("{:?}", self.code)),
+      json::quote(match self.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help",
+      }),
+      json::quote(&self.message),
+      labels,
+      notes,
+      self.help.as_deref().map_or("null".to_string(), json::quote)
+    )
+  }
 }
@@ -12,6 +12,7 @@ pub enum Severity {
 pub enum DiagnosticCode {
   // Errors
   UnterminatedString,
+  UnterminatedInterpolation,
   InvalidCharacter,
   InvalidSuperclass,
   InvalidNumber,
@@ -27,6 +28,7 @@ pub enum DiagnosticCode {
   VariableAlreadyDeclared,
   ContinueOutsideLoop,
   BreakOutsideLoop,
+  UnknownLabel,
   UndeclaredVariable,
   TypeMismatch,
   DuplicateDeclaration,
@@ -38,6 +40,8 @@ pub enum DiagnosticCode {
   EccededNumberOfArguments,
   CannotInferType,
   RecursiveType,
+  MaxRecursionDepth,
+  NonExhaustiveMatch,
   FileNotFound,
   InvalidArguments,
   IoError,
@@ -45,14 +49,75 @@ pub enum DiagnosticCode {
   TypeError,
   DivisionByZero,
   ExpectedToken,
+  DuplicateDefault,
 
   // Warning
   UnusedVariable,
   UnreachableCode,
   ImplicitConversion,
+  MissingTrailingSemicolon,
+  UninitializedVariable,
+  MixedIndentation,
+  TrailingComma,
+
+  // Notes
+  TooManyErrors,
+  Info,
 }
 
 impl DiagnosticCode {
+  /// Every diagnostic code, in declaration order
+  pub const ALL: &'static [DiagnosticCode] = &[
+    Self::UnterminatedString,
+    Self::UnterminatedInterpolation,
+    Self::InvalidCharacter,
+    Self::InvalidSuperclass,
+    Self::InvalidNumber,
+    Self::UnexpectedEof,
+    Self::UnexpectedToken,
+    Self::ExpectedExpression,
+    Self::InvalidThis,
+    Self::MissingClosingBrace,
+    Self::MissingClosingParen,
+    Self::MissingSemicolon,
+    Self::InvalidAssignmentTarget,
+    Self::ExpectedIdentifier,
+    Self::VariableAlreadyDeclared,
+    Self::ContinueOutsideLoop,
+    Self::BreakOutsideLoop,
+    Self::UnknownLabel,
+    Self::UndeclaredVariable,
+    Self::TypeMismatch,
+    Self::DuplicateDeclaration,
+    Self::InvalidAssignment,
+    Self::InvalidOperator,
+    Self::ReturnNotInFunction,
+    Self::InvalidFunctionCall,
+    Self::WrongNumberOfArguments,
+    Self::EccededNumberOfArguments,
+    Self::CannotInferType,
+    Self::RecursiveType,
+    Self::MaxRecursionDepth,
+    Self::NonExhaustiveMatch,
+    Self::FileNotFound,
+    Self::InvalidArguments,
+    Self::IoError,
+    Self::InvalidUnaryOperator,
+    Self::TypeError,
+    Self::DivisionByZero,
+    Self::ExpectedToken,
+    Self::DuplicateDefault,
+    Self::UnusedVariable,
+    Self::UnreachableCode,
+    Self::ImplicitConversion,
+    Self::MissingTrailingSemicolon,
+    Self::UninitializedVariable,
+    Self::MixedIndentation,
+    Self::TrailingComma,
+    Self::TooManyErrors,
+    Self::Info,
+  ];
+
   pub fn code(&self) -> String {
     match self {
       // Errors
@@ -61,25 +126,30 @@ impl DiagnosticCode {
       Self::InvalidNumber => "E0003".to_string(),
       Self::UnexpectedEof => "E0004".to_string(),
       Self::VariableAlreadyDeclared => "E0005".to_string(),
+      Self::UnterminatedInterpolation => "E0006".to_string(),
       Self::UnexpectedToken => "E0100".to_string(),
       Self::ExpectedExpression => "E0101".to_string(),
       Self::MissingClosingBrace => "E0102".to_string(),
-      Self::InvalidSuperclass => "E0103".to_string(),
+      Self::InvalidSuperclass => "E0108".to_string(),
       Self::MissingClosingParen => "E0103".to_string(),
       Self::MissingSemicolon => "E0104".to_string(),
       Self::InvalidAssignmentTarget => "E0105".to_string(),
       Self::ExpectedIdentifier => "E0106".to_string(),
-      Self::ContinueOutsideLoop => "E0200".to_string(),
-      Self::BreakOutsideLoop => "E0201".to_string(),
+      Self::DuplicateDefault => "E0107".to_string(),
+      Self::ContinueOutsideLoop => "E0209".to_string(),
+      Self::BreakOutsideLoop => "E0210".to_string(),
       Self::UndeclaredVariable => "E0200".to_string(),
       Self::TypeMismatch => "E0201".to_string(),
       Self::DuplicateDeclaration => "E0202".to_string(),
       Self::InvalidAssignment => "E0203".to_string(),
       Self::InvalidOperator => "E0204".to_string(),
       Self::ReturnNotInFunction => "E0205".to_string(),
-      Self::InvalidFunctionCall => "E0205".to_string(),
+      Self::InvalidFunctionCall => "E0211".to_string(),
       Self::WrongNumberOfArguments => "E0206".to_string(),
-      Self::EccededNumberOfArguments => "E0206".to_string(),
+      Self::EccededNumberOfArguments => "E0212".to_string(),
+      Self::UnknownLabel => "E0213".to_string(),
+      Self::MaxRecursionDepth => "E0214".to_string(),
+      Self::NonExhaustiveMatch => "E0215".to_string(),
       Self::TypeError => "E0207".to_string(),
       Self::DivisionByZero => "E0208".to_string(),
       Self::CannotInferType => "E0300".to_string(),
@@ -89,18 +159,33 @@ impl DiagnosticCode {
       Self::IoError => "E0402".to_string(),
       Self::InvalidUnaryOperator => "E0403".to_string(),
       Self::InvalidThis => "E0404".to_string(),
-      Self::ExpectedToken => "E0105".to_string(), // assign a unique code or reuse MissingSemicolon code if appropriate
+      Self::ExpectedToken => "E0109".to_string(),
 
       // Warnings
       Self::UnusedVariable => "W0001".to_string(),
       Self::UnreachableCode => "W0002".to_string(),
       Self::ImplicitConversion => "W0003".to_string(),
+      Self::MissingTrailingSemicolon => "W0004".to_string(),
+      Self::UninitializedVariable => "W0005".to_string(),
+      Self::MixedIndentation => "W0006".to_string(),
+      Self::TrailingComma => "W0007".to_string(),
+
+      // Notes
+      Self::TooManyErrors => "N0001".to_string(),
+      Self::Info => "N0002".to_string(),
     }
   }
 
   pub fn severity(&self) -> Severity {
     match self {
-      Self::UnusedVariable | Self::UnreachableCode | Self::ImplicitConversion => Severity::Warning,
+      Self::UnusedVariable
+      | Self::UnreachableCode
+      | Self::ImplicitConversion
+      | Self::MissingTrailingSemicolon
+      | Self::UninitializedVariable
+      | Self::MixedIndentation
+      | Self::TrailingComma => Severity::Warning,
+      Self::TooManyErrors | Self::Info => Severity::Note,
       _ => Severity::Error,
     }
   }
@@ -1,14 +1,31 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
 use colored::*;
 
 use crate::{
-  diagnostic::{Diagnostic, LabelStyle},
+  diagnostic::{Diagnostic, Label, LabelStyle},
   diagnostic_code::Severity,
+  source_map::SourceMap,
 };
 
+/// Default number of columns a `\t` advances to when rendering source lines
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// Default number of labels rendered per diagnostic before the rest are summarized
+pub const DEFAULT_MAX_LABELS: usize = 8;
+
+/// Default number of source lines rendered before and after every labelled line
+pub const DEFAULT_CONTEXT_LINES: usize = 1;
+
 /// Formats diagnostics like rustc with colored crate
 pub struct DiagnosticFormatter<'a> {
   diagnostic: &'a Diagnostic,
   source_lines: Vec<String>,
+  /// Lines of the files set with `with_sources`, keyed by the name the spans report
+  files: HashMap<String, Vec<String>>,
+  tab_width: usize,
+  max_labels: usize,
+  context_lines: usize,
 }
 
 impl<'a> DiagnosticFormatter<'a> {
@@ -17,7 +34,231 @@ impl<'a> DiagnosticFormatter<'a> {
     Self {
       diagnostic,
       source_lines,
+      files: HashMap::new(),
+      tab_width: DEFAULT_TAB_WIDTH,
+      max_labels: DEFAULT_MAX_LABELS,
+      context_lines: DEFAULT_CONTEXT_LINES,
+    }
+  }
+
+  /// Function that reads the labels and suggestions pointing into a file of `sources` from that
+  /// file, the others keep reading the source the formatter was made with
+  pub fn with_sources(mut self, sources: &SourceMap) -> Self {
+    let spans = self
+      .diagnostic
+      .labels
+      .iter()
+      .map(|label| &label.span)
+      .chain(
+        self
+          .diagnostic
+          .suggestions
+          .iter()
+          .map(|suggestion| &suggestion.span),
+      );
+
+    for span in spans {
+      if let Some(file) = sources.get(&span.file) {
+        self
+          .files
+          .entry(span.file.clone())
+          .or_insert_with(|| file.src.lines().map(|s| s.to_string()).collect());
+      }
+    }
+    self
+  }
+
+  /// Function that sets how many source lines are rendered before and after a labelled line
+  pub fn with_context_lines(mut self, context_lines: usize) -> Self {
+    self.context_lines = context_lines;
+    self
+  }
+
+  /// Function that returns the lines shown around `line_num`, clamped to the lines of the file
+  fn context_around(&self, file: &str, line_num: usize) -> RangeInclusive<usize> {
+    let first = line_num.saturating_sub(self.context_lines).max(1);
+    let last = (line_num + self.context_lines).min(self.lines_of(file).len());
+    first..=last
+  }
+
+  /// Function that sets how many labels are rendered before the rest are summarized
+  pub fn with_max_labels(mut self, max_labels: usize) -> Self {
+    self.max_labels = max_labels;
+    self
+  }
+
+  /// Function that returns the "... and M more" line for the labels past `max_labels`
+  fn hidden_labels_text(&self) -> Option<String> {
+    let hidden = self.diagnostic.labels.len().saturating_sub(self.max_labels);
+    match hidden {
+      0 => None,
+      1 => Some("... and 1 more related location".to_string()),
+      _ => Some(format!("... and {} more related locations", hidden)),
+    }
+  }
+
+  /// Function that sets how many columns a tab expands to
+  pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+    self.tab_width = tab_width.max(1);
+    self
+  }
+
+  /// Function that replaces every tab with spaces up to the next tab stop
+  fn expand_tabs(&self, line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+
+    for char in line.chars() {
+      if char == '\t' {
+        let width = self.tab_width - column % self.tab_width;
+        expanded.push_str(&" ".repeat(width));
+        column += width;
+      } else {
+        expanded.push(char);
+        column += 1;
+      }
+    }
+
+    expanded
+  }
+
+  /// Function that maps a character column of the line to its column once tabs are expanded
+  fn visual_column(&self, line: &str, column: usize) -> usize {
+    let mut visual = 0;
+    let mut count = 0;
+
+    for char in line.chars().take(column) {
+      if char == '\t' {
+        visual += self.tab_width - visual % self.tab_width;
+      } else {
+        visual += 1;
+      }
+      count += 1;
+    }
+
+    // Columns past the end of the line (like EOF errors) keep their one-column width
+    visual + (column - count)
+  }
+
+  /// Function that renders the labels of one source line, their underlines share the first row
+  /// with the message of the rightmost label and every other message gets a row of its own
+  /// under the start of its underline. Columns are 1-based.
+  fn underline_rows(&self, line_content: &str, labels: &[&Label], colored: bool) -> Vec<String> {
+    let mut segments = labels
+      .iter()
+      .map(|label| {
+        let column = label.span.column.saturating_sub(1);
+        let start = self.visual_column(line_content, column);
+        let length = self
+          .visual_column(line_content, column + label.span.length)
+          .saturating_sub(start)
+          .max(1);
+        (start, length, *label)
+      })
+      .collect::<Vec<_>>();
+    // `sort_by_key` is stable so labels starting together keep their order
+    segments.sort_by_key(|(start, _, _)| *start);
+
+    let mut row = String::new();
+    let mut end = 0;
+    for (start, length, label) in &segments {
+      // An underline overlapping the previous one only draws its remaining part
+      let from = (*start).max(end);
+      let to = start + length;
+      if to <= from {
+        continue;
+      }
+
+      row.push_str(&" ".repeat(from - end));
+      let underline = self
+        .underline_char(label.style)
+        .to_string()
+        .repeat(to - from);
+      row.push_str(&self.paint(&underline, label.style, colored));
+      end = to;
+    }
+
+    let mut messages = segments
+      .iter()
+      .filter_map(|(start, _, label)| label.message.as_ref().map(|msg| (*start, label, msg)))
+      .collect::<Vec<_>>();
+    if let Some((_, label, msg)) = messages.pop() {
+      row.push(' ');
+      row.push_str(&self.paint(msg, label.style, colored));
+    }
+
+    let mut rows = vec![row];
+    for (start, label, msg) in messages.into_iter().rev() {
+      rows.push(format!(
+        "{}{}",
+        " ".repeat(start),
+        self.paint(msg, label.style, colored)
+      ));
+    }
+    rows
+  }
+
+  /// Function that colors an underline or a label message by the severity and the label style
+  fn paint(&self, text: &str, style: LabelStyle, colored: bool) -> String {
+    if !colored {
+      return text.to_string();
+    }
+
+    match (self.diagnostic.severity, style) {
+      (Severity::Error, LabelStyle::Primary) => text.red().bold(),
+      (Severity::Warning, LabelStyle::Primary) => text.yellow().bold(),
+      (Severity::Help, LabelStyle::Primary) => text.green().bold(),
+      (_, LabelStyle::Secondary) => text.cyan().bold(),
+      _ => text.cyan().bold(),
     }
+    .to_string()
+  }
+
+  /// Function that returns how wide the gutter must be for the largest line number of any label
+  fn gutter_width(&self) -> usize {
+    self
+      .diagnostic
+      .labels
+      .iter()
+      .take(self.max_labels)
+      .filter(|label| {
+        self
+          .get_line_content(&label.span.file, label.span.line)
+          .is_some()
+      })
+      .map(|label| {
+        *self
+          .context_around(&label.span.file, label.span.line.max(1))
+          .end()
+      })
+      .max()
+      .map(|line| line.to_string().len())
+      .unwrap_or(1)
+  }
+
+  /// Function that renders a source line shown only for context, with a dimmed line number
+  fn format_context_line(&self, file: &str, line_num: usize, width: usize) -> String {
+    let line_content = self.get_line_content(file, line_num).unwrap_or_default();
+    format!(
+      " {} {} {}\n",
+      format!("{:>width$}", line_num, width = width).dimmed(),
+      "|".blue().bold(),
+      self.expand_tabs(line_content)
+    )
+  }
+
+  /// Function that renders every suggestion as the source line with the fix applied
+  fn suggestion_texts(&self) -> Vec<String> {
+    self
+      .diagnostic
+      .suggestions
+      .iter()
+      .filter_map(|suggestion| {
+        let line_content = self.get_line_content(&suggestion.span.file, suggestion.span.line)?;
+        let fixed = self.expand_tabs(&suggestion.apply(line_content));
+        Some(format!("try this: `{}`", fixed.trim()))
+      })
+      .collect()
   }
 
   fn severity_text(&self) -> &'static str {
@@ -36,12 +277,54 @@ impl<'a> DiagnosticFormatter<'a> {
     }
   }
 
-  fn get_line_content(&self, line_num: usize) -> Option<&str> {
-    if line_num == 0 && self.source_lines.is_empty() {
+  /// Function that groups the labelled lines by file, in the order the files first appear in the
+  /// labels, each with the sorted labelled lines and every line shown around them
+  fn shown_lines(&self, labels: &[&Label]) -> Vec<(String, Vec<usize>, Vec<usize>)> {
+    let mut files: Vec<(String, Vec<usize>, Vec<usize>)> = Vec::new();
+
+    for label in labels {
+      let file = &label.span.file;
+      if self.get_line_content(file, label.span.line).is_none() {
+        continue;
+      }
+
+      let index = match files.iter().position(|(name, _, _)| name == file) {
+        Some(index) => index,
+        None => {
+          files.push((file.clone(), Vec::new(), Vec::new()));
+          files.len() - 1
+        },
+      };
+      files[index].1.push(label.span.line.max(1));
+    }
+
+    for (file, line_nums, shown) in &mut files {
+      line_nums.sort_unstable();
+      line_nums.dedup();
+      *shown = line_nums
+        .iter()
+        .flat_map(|line| self.context_around(file, *line))
+        .collect();
+      shown.sort_unstable();
+      shown.dedup();
+    }
+
+    files
+  }
+
+  /// Function that returns the lines of `file`, the source the formatter was made with for a
+  /// file missing from `with_sources`
+  fn lines_of(&self, file: &str) -> &[String] {
+    self.files.get(file).unwrap_or(&self.source_lines)
+  }
+
+  fn get_line_content(&self, file: &str, line_num: usize) -> Option<&str> {
+    let lines = self.lines_of(file);
+    if line_num == 0 && lines.is_empty() {
       return None;
     }
     let index = if line_num == 0 { 0 } else { line_num - 1 };
-    self.source_lines.get(index).map(|s| s.as_str())
+    lines.get(index).map(|s| s.as_str())
   }
 
   pub fn format(&self) -> String {
@@ -65,7 +348,7 @@ impl<'a> DiagnosticFormatter<'a> {
           self.diagnostic.message
         )
       },
-      _ => {
+      Severity::Note => {
         format!(
           "{}: [{}]: {}",
           self.severity_text().cyan().bold(),
@@ -73,9 +356,21 @@ impl<'a> DiagnosticFormatter<'a> {
           self.diagnostic.message
         )
       },
+      Severity::Help => {
+        format!(
+          "{}: [{}]: {}",
+          self.severity_text().green().bold(),
+          self.diagnostic.code.code().green().bold(),
+          self.diagnostic.message
+        )
+      },
     };
     output.push_str(&header);
-    output.push_str("\n");
+    output.push('\n');
+
+    // The gutter is as wide as the largest line number shown so every `|` lines up
+    let width = self.gutter_width();
+    let gutter = " ".repeat(width + 1);
 
     // Get the primary label (first one, usually)
     if let Some(primary_label) = self.diagnostic.labels.first() {
@@ -89,74 +384,84 @@ impl<'a> DiagnosticFormatter<'a> {
       ));
 
       // Empty line with just the gutter
-      output.push_str(&format!("   {}\n", "|".blue().bold()));
+      output.push_str(&format!("{} {}\n", gutter, "|".blue().bold()));
+
+      let labels = self
+        .diagnostic
+        .labels
+        .iter()
+        .take(self.max_labels)
+        .collect::<Vec<_>>();
+      for (index, (file, line_nums, shown)) in self.shown_lines(&labels).iter().enumerate() {
+        // Every other file gets its own location line, pointing at its first label
+        if index > 0 {
+          if let Some(first) = labels.iter().find(|label| &label.span.file == file) {
+            output.push_str(&format!(
+              "  {} {}:{}:{}\n",
+              ":::".blue().bold(),
+              file.white().bold(),
+              first.span.line.to_string().white().bold(),
+              first.span.column.to_string().white().bold()
+            ));
+            output.push_str(&format!("{} {}\n", gutter, "|".blue().bold()));
+          }
+        }
 
-      // Get the source line
-      if let Some(line_content) = self.get_line_content(primary_label.span.line) {
-        let line_num = primary_label.span.line;
+        let mut previous = None;
+        for &line_num in shown {
+          let Some(line_content) = self.get_line_content(file, line_num) else {
+            continue;
+          };
 
-        // Line number and content
-        output.push_str(&format!(
-          " {} {} {}\n",
-          format!("{}", line_num).blue().bold(),
-          "|".blue().bold(),
-          line_content
-        ));
+          // Lines skipped between two labelled regions are elided like rustc does
+          if previous.is_some_and(|previous| line_num > previous + 1) {
+            output.push_str(&format!("{}{}\n", gutter, "...".blue().bold()));
+          }
+          previous = Some(line_num);
+
+          if !line_nums.contains(&line_num) {
+            output.push_str(&self.format_context_line(file, line_num, width));
+            continue;
+          }
 
-        // Now render ALL labels for this line
-        for label in &self.diagnostic.labels {
-          // Only show labels that are on the same line
-          if label.span.line == line_num {
-            let underline_char = self.underline_char(label.style);
-            let start_col = label.span.column;
-            let length = label.span.length;
-
-            let padding = " ".repeat(start_col);
-            let underline = underline_char.to_string().repeat(length);
-
-            let colored_underline = match (self.diagnostic.severity, label.style) {
-              (Severity::Error, LabelStyle::Primary) => underline.red().bold(),
-              (Severity::Warning, LabelStyle::Primary) => underline.yellow().bold(),
-              (_, LabelStyle::Secondary) => underline.cyan().bold(),
-              _ => underline.cyan().bold(),
-            };
-
-            // Combine underline and message on the same line
-            if let Some(msg) = &label.message {
-              let colored_msg = match (self.diagnostic.severity, label.style) {
-                (Severity::Error, LabelStyle::Primary) => msg.red().bold(),
-                (Severity::Warning, LabelStyle::Primary) => msg.yellow().bold(),
-                (_, LabelStyle::Secondary) => msg.cyan().bold(),
-                _ => msg.cyan().bold(),
-              };
-
-              output.push_str(&format!(
-                "   {} {}{} {}\n",
-                "|".blue().bold(),
-                padding,
-                colored_underline,
-                colored_msg
-              ));
-            } else {
-              output.push_str(&format!(
-                "   {} {}{}\n",
-                "|".blue().bold(),
-                padding,
-                colored_underline
-              ));
-            }
+          // Line number and content
+          output.push_str(&format!(
+            " {} {} {}\n",
+            format!("{:>width$}", line_num, width = width).blue().bold(),
+            "|".blue().bold(),
+            self.expand_tabs(line_content)
+          ));
+
+          // Every label of the line shares one underline row
+          let line_labels = labels
+            .iter()
+            .copied()
+            .filter(|label| &label.span.file == file && label.span.line.max(1) == line_num)
+            .collect::<Vec<_>>();
+          for row in self.underline_rows(line_content, &line_labels, true) {
+            output.push_str(&format!("{} {} {}\n", gutter, "|".blue().bold(), row));
           }
         }
       }
 
+      if let Some(hidden) = self.hidden_labels_text() {
+        output.push_str(&format!(
+          "{} {} {}\n",
+          gutter,
+          "|".blue().bold(),
+          hidden.cyan()
+        ));
+      }
+
       // Empty line after all labels
-      output.push_str(&format!("   {}\n", "|".blue().bold()));
+      output.push_str(&format!("{} {}\n", gutter, "|".blue().bold()));
     }
 
     // Notes
     for note in &self.diagnostic.notes {
       output.push_str(&format!(
-        "   {} {}: {}\n",
+        "{} {} {}: {}\n",
+        gutter,
         "=".blue().bold(),
         "note".cyan().bold(),
         note
@@ -166,13 +471,24 @@ impl<'a> DiagnosticFormatter<'a> {
     // Help
     if let Some(help) = &self.diagnostic.help {
       output.push_str(&format!(
-        "   {} {}: {}\n",
+        "{} {} {}: {}\n",
+        gutter,
         "=".blue().bold(),
         "help".cyan().bold(),
         help
       ));
     }
 
+    for suggestion in self.suggestion_texts() {
+      output.push_str(&format!(
+        "{} {} {}: {}\n",
+        gutter,
+        "=".blue().bold(),
+        "help".cyan().bold(),
+        suggestion
+      ));
+    }
+
     output
   }
 
@@ -188,6 +504,22 @@ impl<'a> DiagnosticFormatter<'a> {
       self.diagnostic.message
     ));
 
+    // The gutter is as wide as the largest line number shown so every `|` lines up
+    let labels = self
+      .diagnostic
+      .labels
+      .iter()
+      .take(self.max_labels)
+      .collect::<Vec<_>>();
+    let files = self.shown_lines(&labels);
+    let width = files
+      .iter()
+      .filter_map(|(_, _, shown)| shown.last())
+      .max()
+      .map(|line| line.to_string().len())
+      .unwrap_or(1);
+    let gutter = " ".repeat(width + 1);
+
     // Get the primary label
     if let Some(primary_label) = self.diagnostic.labels.first() {
       output.push_str(&format!(
@@ -195,44 +527,74 @@ impl<'a> DiagnosticFormatter<'a> {
         primary_label.span.file, primary_label.span.line, primary_label.span.column
       ));
 
-      output.push_str("   |\n");
+      output.push_str(&format!("{} |\n", gutter));
+
+      for (index, (file, line_nums, shown)) in files.iter().enumerate() {
+        // Every other file gets its own location line, pointing at its first label
+        if index > 0 {
+          if let Some(first) = labels.iter().find(|label| &label.span.file == file) {
+            output.push_str(&format!(
+              "  ::: {}:{}:{}\n",
+              file, first.span.line, first.span.column
+            ));
+            output.push_str(&format!("{} |\n", gutter));
+          }
+        }
 
-      if let Some(line_content) = self.get_line_content(primary_label.span.line) {
-        let line_num = primary_label.span.line;
+        let mut previous = None;
+        for &line_num in shown {
+          let Some(line_content) = self.get_line_content(file, line_num) else {
+            continue;
+          };
 
-        output.push_str(&format!(" {:>3} | {}\n", line_num, line_content));
+          // Lines skipped between two labelled regions are elided like rustc does
+          if previous.is_some_and(|previous| line_num > previous + 1) {
+            output.push_str(&format!("{}...\n", " ".repeat(width + 1)));
+          }
+          previous = Some(line_num);
 
-        // Render all labels for this line
-        for label in &self.diagnostic.labels {
-          if label.span.line == line_num {
-            let underline_char = self.underline_char(label.style);
-            let start_col = label.span.column.saturating_sub(1);
-            let length = label.span.length.max(1);
+          output.push_str(&format!(
+            " {:>width$} | {}\n",
+            line_num,
+            self.expand_tabs(line_content),
+            width = width
+          ));
 
-            let padding = " ".repeat(start_col);
-            let underline = underline_char.to_string().repeat(length);
+          if !line_nums.contains(&line_num) {
+            continue;
+          }
 
-            // Combine underline and message on the same line
-            if let Some(msg) = &label.message {
-              output.push_str(&format!("   | {}{} {}\n", padding, underline, msg));
-            } else {
-              output.push_str(&format!("   | {}{}\n", padding, underline));
-            }
+          // Every label of the line shares one underline row
+          let line_labels = labels
+            .iter()
+            .copied()
+            .filter(|label| &label.span.file == file && label.span.line.max(1) == line_num)
+            .collect::<Vec<_>>();
+          for row in self.underline_rows(line_content, &line_labels, false) {
+            output.push_str(&format!("{} | {}\n", gutter, row));
           }
         }
       }
 
-      output.push_str("   |\n");
+      if let Some(hidden) = self.hidden_labels_text() {
+        output.push_str(&format!("{} | {}\n", gutter, hidden));
+      }
+
+      output.push_str(&format!("{} |\n", gutter));
     }
 
     // Notes
     for note in &self.diagnostic.notes {
-      output.push_str(&format!("   = note: {}\n", note));
+      output.push_str(&format!("{} = note: {}\n", gutter, note));
     }
 
     // Help
     if let Some(help) = &self.diagnostic.help {
-      output.push_str(&format!("   = help: {}\n", help));
+      output.push_str(&format!("{} = help: {}\n", gutter, help));
+    }
+
+    for suggestion in self.suggestion_texts() {
+      output.push_str(&format!("{} = help: {}\n", gutter, suggestion));
     }
 
     output
@@ -1,30 +1,124 @@
 pub mod diagnostic;
 pub mod diagnostic_code;
 pub mod formatter;
+pub mod json;
+pub mod source_map;
+
+use std::{ffi::OsStr, io::IsTerminal};
 
 use colored::*;
 
-use crate::{diagnostic::Diagnostic, diagnostic_code::Severity, formatter::DiagnosticFormatter};
+use crate::{
+  diagnostic::Diagnostic,
+  diagnostic_code::{DiagnosticCode, Severity},
+  formatter::DiagnosticFormatter,
+  source_map::SourceMap,
+};
 
 /// Collector for all diagnostics during compilation
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct DiagnosticEngine {
   diagnostics: Vec<Diagnostic>,
   error_count: usize,
   warning_count: usize,
+  /// Whether `print_all` styles the output with ANSI colors
+  use_color: bool,
+  /// Number of errors after which new errors are dropped, `None` keeps all of them
+  max_errors: Option<usize>,
+  /// Set once `max_errors` was reached
+  aborted: bool,
+  /// Whether `print_all` renders every error before the warnings instead of mixing them by position
+  errors_first: bool,
+}
+
+impl Default for DiagnosticEngine {
+  fn default() -> Self {
+    Self {
+      diagnostics: Vec::new(),
+      error_count: 0,
+      warning_count: 0,
+      use_color: color_enabled(
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::io::stdout().is_terminal(),
+      ),
+      max_errors: None,
+      aborted: false,
+      errors_first: false,
+    }
+  }
+}
+
+/// Function that decides color support from the `NO_COLOR` value and whether stdout is a
+/// terminal, colors are off when `NO_COLOR` is set to a non empty value or stdout is not a terminal.
+pub fn color_enabled(no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+  let no_color = no_color.is_some_and(|value| !value.is_empty());
+  !no_color && is_terminal
 }
 
 impl DiagnosticEngine {
   pub fn new() -> Self {
     Self::default()
   }
+
+  /// Function that overrides the detected color support
+  pub fn with_color(mut self, use_color: bool) -> Self {
+    self.use_color = use_color;
+    self
+  }
+
+  /// Function that groups the printed diagnostics by severity, errors then warnings then the rest,
+  /// each group still ordered by position
+  pub fn with_errors_first(mut self, errors_first: bool) -> Self {
+    self.errors_first = errors_first;
+    self
+  }
+
+  /// Function that caps the number of errors kept before compilation aborts
+  pub fn set_max_errors(&mut self, max_errors: Option<usize>) {
+    self.max_errors = max_errors;
+  }
+
+  /// Function that tells the phases to stop once the error cap was reached
+  pub fn should_abort(&self) -> bool {
+    self.aborted
+  }
+
   pub fn clear(&mut self) {
     self.diagnostics = vec![];
     self.error_count = 0;
     self.warning_count = 0;
+    self.aborted = false;
   }
 
   pub fn emit(&mut self, diagnostic: Diagnostic) {
+    // Error recovery can report the same mistake more than once, keep only the first one
+    if self.diagnostics.iter().any(|emitted| {
+      emitted.code == diagnostic.code
+        && emitted.message == diagnostic.message
+        && emitted.primary_label().map(|label| &label.span)
+          == diagnostic.primary_label().map(|label| &label.span)
+    }) {
+      return;
+    }
+
+    if diagnostic.severity == Severity::Error {
+      if self.aborted {
+        return;
+      }
+
+      if self
+        .max_errors
+        .is_some_and(|max_errors| self.error_count >= max_errors)
+      {
+        self.aborted = true;
+        self.diagnostics.push(Diagnostic::new(
+          DiagnosticCode::TooManyErrors,
+          "too many errors; aborting".to_string(),
+        ));
+        return;
+      }
+    }
+
     match diagnostic.severity {
       Severity::Error => self.error_count += 1,
       Severity::Warning => self.warning_count += 1,
@@ -45,37 +139,153 @@ impl DiagnosticEngine {
     self.warning_count
   }
 
-  /// Print all diagnostics with colors to stdout
+  /// Function that returns the error-level diagnostics, in the order they were emitted
+  pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+    self.with_severity(Severity::Error)
+  }
+
+  /// Function that returns the warnings, in the order they were emitted
+  pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+    self.with_severity(Severity::Warning)
+  }
+
+  fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+    self
+      .diagnostics
+      .iter()
+      .filter(move |diagnostic| diagnostic.severity == severity)
+  }
+
+  /// Print all diagnostics to stdout, colored unless colors are disabled
   pub fn print_all(&self, source_code: &str) {
-    for diagnostic in &self.diagnostics {
-      let formatter = DiagnosticFormatter::new(diagnostic, source_code);
-      print!("{}", formatter.format());
+    print!("{}", self.format_all(source_code));
+  }
+
+  /// Get all diagnostics as they are printed by `print_all`
+  pub fn format_all(&self, source_code: &str) -> String {
+    self.format_each(|diagnostic| DiagnosticFormatter::new(diagnostic, source_code))
+  }
+
+  /// Print all diagnostics to stdout, every label against the file it points into
+  pub fn print_all_sources(&self, sources: &SourceMap) {
+    print!("{}", self.format_all_sources(sources));
+  }
+
+  /// Get all diagnostics as they are printed by `print_all_sources`
+  pub fn format_all_sources(&self, sources: &SourceMap) -> String {
+    self.format_each(|diagnostic| DiagnosticFormatter::new(diagnostic, "").with_sources(sources))
+  }
+
+  /// Function that formats every diagnostic with the formatter returned for it, colored unless
+  /// colors are disabled
+  fn format_each<'a>(
+    &'a self,
+    formatter_for: impl Fn(&'a Diagnostic) -> DiagnosticFormatter<'a>,
+  ) -> String {
+    if !self.use_color {
+      let mut output = self.format_each_plain(formatter_for);
+      if self.error_count > 0 || self.warning_count > 0 {
+        output.push('\n');
+      }
+      return output;
+    }
+
+    let mut output = String::new();
+    for diagnostic in self.sorted_diagnostics() {
+      output.push_str(&formatter_for(diagnostic).format());
     }
 
-    self.print_summary();
+    output.push_str(&self.format_summary());
+    output
+  }
+
+  /// Function that returns the diagnostics ordered by the `(file, line, column)` of their primary
+  /// label, diagnostics without labels go last and emission order breaks ties. With `errors_first` the
+  /// diagnostics are grouped by severity before that.
+  fn sorted_diagnostics(&self) -> Vec<&Diagnostic> {
+    let mut diagnostics = self.diagnostics.iter().collect::<Vec<_>>();
+
+    // `sort_by_key` is stable so equal positions keep their emission order
+    diagnostics.sort_by_key(|diagnostic| {
+      let group = if self.errors_first {
+        match diagnostic.severity {
+          Severity::Error => 0,
+          Severity::Warning => 1,
+          Severity::Note | Severity::Help => 2,
+        }
+      } else {
+        0
+      };
+
+      match diagnostic.primary_label() {
+        Some(label) => (
+          group,
+          false,
+          label.span.file.as_str(),
+          label.span.line,
+          label.span.column,
+        ),
+        None => (group, true, "", 0, 0),
+      }
+    });
+
+    diagnostics
   }
 
   /// Get all diagnostics as plain text (for file logging)
   pub fn format_all_plain(&self, source_code: &str) -> String {
+    self.format_each_plain(|diagnostic| DiagnosticFormatter::new(diagnostic, source_code))
+  }
+
+  fn format_each_plain<'a>(
+    &'a self,
+    formatter_for: impl Fn(&'a Diagnostic) -> DiagnosticFormatter<'a>,
+  ) -> String {
     let mut output = String::new();
 
-    for diagnostic in &self.diagnostics {
-      let formatter = DiagnosticFormatter::new(diagnostic, source_code);
-      output.push_str(&formatter.format_plain());
-      output.push_str("\n");
+    for diagnostic in self.sorted_diagnostics() {
+      output.push_str(&formatter_for(diagnostic).format_plain());
+      output.push('\n');
     }
 
     output.push_str(&self.format_summary_plain());
     output
   }
 
-  fn print_summary(&self) {
-    if self.error_count > 0 || self.warning_count > 0 {
-      println!();
+  /// Get all diagnostics as a JSON array for editors, every entry has the shape
+  ///
+  /// ```text
+  /// {
+  ///   "code": "E0200",                 // the code printed in the header
+  ///   "name": "UndeclaredVariable",    // the `DiagnosticCode` variant
+  ///   "severity": "error",             // "error" | "warning" | "note" | "help"
+  ///   "message": "...",
+  ///   "labels": [{ "style": "primary" | "secondary", "file": "...", "line": 0, "column": 0,
+  ///                "length": 0, "message": "..." | null }],
+  ///   "notes": ["..."],
+  ///   "help": "..." | null
+  /// }
+  /// ```
+  pub fn format_all_json(&self) -> String {
+    if self.diagnostics.is_empty() {
+      return "[]".to_string();
+    }
+
+    let diagnostics = self
+      .diagnostics
+      .iter()
+      .map(|diagnostic| format!("  {}", diagnostic.to_json()))
+      .collect::<Vec<_>>()
+      .join(",\n");
+
+    format!("[\n{}\n]", diagnostics)
+  }
 
+  fn format_summary(&self) -> String {
+    if self.error_count > 0 || self.warning_count > 0 {
       if self.has_errors() {
-        println!(
-          "{}: could not compile due to {} previous {}{}",
+        format!(
+          "\n{}: could not compile due to {} previous {}{}\n",
           "error".red().bold(),
           self.error_count.to_string().red().bold(),
           if self.error_count == 1 {
@@ -96,10 +306,10 @@ impl DiagnosticEngine {
           } else {
             String::new()
           }
-        );
-      } else if self.warning_count > 0 {
-        println!(
-          "{}: {} {} emitted",
+        )
+      } else {
+        format!(
+          "\n{}: {} {} emitted\n",
           "warning".yellow().bold(),
           self.warning_count.to_string().yellow().bold(),
           if self.warning_count == 1 {
@@ -107,8 +317,10 @@ impl DiagnosticEngine {
           } else {
             "warnings"
           }
-        );
+        )
       }
+    } else {
+      String::new()
     }
   }
 
@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+/// A source file the diagnostics can point into
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+  pub path: String,
+  pub src: String,
+}
+
+impl SourceFile {
+  pub fn new(path: String, src: String) -> Self {
+    Self { path, src }
+  }
+}
+
+/// Every file of a compilation, keyed by the name the spans report in `Span::file`
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+  pub files: BTreeMap<String, SourceFile>,
+}
+
+impl SourceMap {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_file(&mut self, path: &str, src: &str) {
+    self.files.insert(
+      path.to_string(),
+      SourceFile::new(path.to_string(), src.to_string()),
+    );
+  }
+
+  pub fn get(&self, path: &str) -> Option<&SourceFile> {
+    self.files.get(path)
+  }
+
+  pub fn has_file(&self, path: &str) -> bool {
+    self.files.contains_key(path)
+  }
+
+  /// Function that returns the contents of the file, empty when the map doesn't know it
+  pub fn source(&self, path: &str) -> &str {
+    self.get(path).map_or("", |file| file.src.as_str())
+  }
+}
@@ -1,12 +1,41 @@
 #[cfg(test)]
 mod tests {
-
-  use super::*;
   use diagnostic::{
+    color_enabled,
     diagnostic::{Diagnostic, Label, Span},
     diagnostic_code::DiagnosticCode,
+    formatter::DiagnosticFormatter,
     DiagnosticEngine,
   };
+  use std::ffi::OsStr;
+
+  /// Function that returns the column of the first caret and the column of `target` in the printed
+  /// source line of a colorless diagnostic
+  fn caret_and_target(output: &str, target: char) -> (usize, usize) {
+    let source_line = output.lines().find(|line| line.contains(" 1 | ")).unwrap();
+    let caret_line = output.lines().find(|line| line.contains('^')).unwrap();
+
+    (
+      caret_line.find('^').unwrap(),
+      source_line.find(target).unwrap(),
+    )
+  }
+
+  fn tab_diagnostic(column: usize) -> Diagnostic {
+    Diagnostic::new(
+      DiagnosticCode::ExpectedExpression,
+      "Expected expr".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: "input".to_string(),
+        line: 1,
+        column,
+        length: 1,
+      },
+      None,
+    ))
+  }
 
   #[test]
   fn test_diagnostic_formatting() {
@@ -32,4 +61,814 @@ mod tests {
     engine.emit(error);
     println!("{}", engine.format_all_plain(source));
   }
+
+  #[test]
+  fn test_no_color_disables_colors() {
+    let set = Some(OsStr::new("1"));
+    let empty = Some(OsStr::new(""));
+
+    assert!(color_enabled(None, true));
+    assert!(color_enabled(empty, true));
+    assert!(!color_enabled(set, true));
+    assert!(!color_enabled(None, false));
+  }
+
+  #[test]
+  fn test_disabled_color_has_no_ansi_escapes() {
+    let source = "var a = ;";
+    let mut engine = DiagnosticEngine::new().with_color(false);
+
+    engine.emit(
+      Diagnostic::new(
+        DiagnosticCode::ExpectedExpression,
+        "Expected expr".to_string(),
+      )
+      .with_label(Label::primary(
+        Span {
+          file: "input".to_string(),
+          line: 0,
+          column: 8,
+          length: 1,
+        },
+        Some("unexpected token ';'".to_string()),
+      )),
+    );
+
+    let output = engine.format_all(source);
+
+    assert!(output.contains("Expected expr"));
+    assert!(
+      !output.contains('\x1b'),
+      "found ANSI escapes in\n{}",
+      output
+    );
+  }
+
+  #[test]
+  fn test_caret_alignment_with_tabs() {
+    colored::control::set_override(false);
+
+    // `;` is the 10th character, after two tabs
+    let source = "\t\tfoo(1, ;";
+    let diagnostic = tab_diagnostic(10);
+
+    let output = DiagnosticFormatter::new(&diagnostic, source).format();
+    let (caret, target) = caret_and_target(&output, ';');
+
+    assert!(!output.contains('\t'));
+    assert_eq!(caret, target, "caret is not under ';' in\n{}", output);
+  }
+
+  #[test]
+  fn test_colored_underline_starts_at_the_1_based_column() {
+    colored::control::set_override(false);
+
+    let source = "var b = a - 1;";
+    let diagnostic = Diagnostic::new(DiagnosticCode::TypeMismatch, "Bad operands".to_string())
+      .with_label(Label::primary(
+        Span {
+          file: "input".to_string(),
+          line: 1,
+          column: 9,
+          length: 5,
+        },
+        Some("these operands".to_string()),
+      ));
+
+    let colored = DiagnosticFormatter::new(&diagnostic, source).format();
+    let plain = DiagnosticFormatter::new(&diagnostic, source).format_plain();
+
+    assert!(
+      colored.contains("\n   |         ^^^^^ these operands\n"),
+      "{}",
+      colored
+    );
+    assert!(
+      plain.contains("\n   |         ^^^^^ these operands\n"),
+      "{}",
+      plain
+    );
+  }
+
+  #[test]
+  fn test_caret_alignment_with_custom_tab_width() {
+    colored::control::set_override(false);
+
+    // A tab between the tokens only advances to the next tab stop
+    let source = "ab\tx";
+    let diagnostic = tab_diagnostic(4);
+
+    let output = DiagnosticFormatter::new(&diagnostic, source)
+      .with_tab_width(8)
+      .format();
+    let (caret, target) = caret_and_target(&output, 'x');
+
+    assert!(output.contains("ab      x"));
+    assert_eq!(caret, target, "caret is not under 'x' in\n{}", output);
+  }
+
+  #[test]
+  fn test_format_all_json() {
+    let mut engine = DiagnosticEngine::new();
+
+    engine.emit(
+      Diagnostic::new(
+        DiagnosticCode::UndeclaredVariable,
+        "Undefined variable \"a\"".to_string(),
+      )
+      .with_label(Label::primary(
+        Span {
+          file: "input.duck".to_string(),
+          line: 2,
+          column: 4,
+          length: 1,
+        },
+        Some("not found in this scope".to_string()),
+      ))
+      .with_note("variables must be declared with `var`".to_string()),
+    );
+
+    let json = engine.format_all_json();
+
+    assert!(json.starts_with('[') && json.ends_with(']'));
+    for key in [
+      "\"code\": \"E0200\"",
+      "\"name\": \"UndeclaredVariable\"",
+      "\"severity\": \"error\"",
+      "\"message\": \"Undefined variable \\\"a\\\"\"",
+      "\"labels\": [{\"style\": \"primary\", \"file\": \"input.duck\", \"line\": 2, \"column\": 4, \"length\": 1, \"message\": \"not found in this scope\"}]",
+      "\"notes\": [\"variables must be declared with `var`\"]",
+      "\"help\": null",
+    ] {
+      assert!(json.contains(key), "missing {} in\n{}", key, json);
+    }
+  }
+
+  #[test]
+  fn test_span_from_byte_range() {
+    let source = "var a = 1;\nvar é = \"ü\";\n  print(é);";
+
+    let triple = |start: usize, end: usize| {
+      let span = Span::from_byte_range("main.duck", source, start, end);
+      assert_eq!(span.file, "main.duck");
+      (span.line, span.column, span.length)
+    };
+
+    // `var` on the first line
+    assert_eq!(triple(0, 3), (1, 1, 3));
+    // `é` is two bytes but one column
+    let e = source.find('é').unwrap();
+    assert_eq!(triple(e, e + 'é'.len_utf8()), (2, 5, 1));
+    // `"ü"` sits after the multi-byte `é`
+    let string = source.find('"').unwrap();
+    assert_eq!(triple(string, string + 4), (2, 9, 3));
+    // `print` on the last line after the indentation
+    let print = source.find("print").unwrap();
+    assert_eq!(triple(print, print + 5), (3, 3, 5));
+    // an offset inside `é` moves back to the start of the character
+    assert_eq!(triple(e + 1, e + 2), (2, 5, 1));
+    // out of bounds offsets are clamped to the end of the source
+    assert_eq!(triple(source.len(), source.len() + 10), (3, 12, 0));
+  }
+
+  #[test]
+  fn test_diagnostics_sorted_by_position() {
+    let mut engine = DiagnosticEngine::new();
+
+    let at = |message: &str, line: usize, column: usize| {
+      Diagnostic::new(DiagnosticCode::UnexpectedToken, message.to_string()).with_label(
+        Label::primary(
+          Span {
+            file: "input".to_string(),
+            line,
+            column,
+            length: 1,
+          },
+          None,
+        ),
+      )
+    };
+
+    engine.emit(Diagnostic::new(
+      DiagnosticCode::InvalidArguments,
+      "no position".to_string(),
+    ));
+    engine.emit(at("third", 3, 1));
+    engine.emit(at("second", 1, 5));
+    engine.emit(at("first", 1, 2));
+    engine.emit(at("second again", 1, 5));
+
+    let output = engine.format_all_plain("a\nb\nc\n");
+    let positions = ["first", "second", "second again", "third", "no position"]
+      .iter()
+      .map(|message| output.find(&format!("]: {}\n", message)).unwrap())
+      .collect::<Vec<_>>();
+
+    assert!(
+      positions.windows(2).all(|pair| pair[0] < pair[1]),
+      "diagnostics out of order in\n{}",
+      output
+    );
+  }
+
+  #[test]
+  fn test_identical_diagnostics_are_deduplicated() {
+    let mut engine = DiagnosticEngine::new();
+
+    let at = |column: usize| {
+      Diagnostic::new(
+        DiagnosticCode::UnexpectedToken,
+        "Unexpected token ')'".to_string(),
+      )
+      .with_label(Label::primary(
+        Span {
+          file: "input".to_string(),
+          line: 1,
+          column,
+          length: 1,
+        },
+        None,
+      ))
+    };
+
+    engine.emit(at(4));
+    engine.emit(at(4));
+    assert_eq!(engine.error_count(), 1);
+    assert_eq!(engine.get_diagnostics().len(), 1);
+
+    // Same mistake somewhere else is a different diagnostic
+    engine.emit(at(7));
+    assert_eq!(engine.error_count(), 2);
+  }
+
+  fn error_at(column: usize) -> Diagnostic {
+    Diagnostic::new(
+      DiagnosticCode::UnexpectedToken,
+      "Unexpected token".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: "input".to_string(),
+        line: 1,
+        column,
+        length: 1,
+      },
+      None,
+    ))
+  }
+
+  #[test]
+  fn test_max_errors_truncates() {
+    let mut engine = DiagnosticEngine::new();
+    engine.set_max_errors(Some(2));
+
+    for column in 0..5 {
+      engine.emit(error_at(column));
+    }
+
+    assert!(engine.should_abort());
+    assert_eq!(engine.error_count(), 2);
+    assert_eq!(engine.get_diagnostics().len(), 3);
+    assert!(engine
+      .format_all_plain("a b c d e")
+      .contains("too many errors; aborting"));
+  }
+
+  #[test]
+  fn test_max_errors_unlimited() {
+    let mut engine = DiagnosticEngine::new();
+    engine.set_max_errors(None);
+
+    for column in 0..5 {
+      engine.emit(error_at(column));
+    }
+
+    assert!(!engine.should_abort());
+    assert_eq!(engine.error_count(), 5);
+  }
+
+  #[test]
+  fn test_label_cap_summarizes_overflow() {
+    let mut diagnostic = Diagnostic::new(
+      DiagnosticCode::DuplicateDeclaration,
+      "Duplicate declaration".to_string(),
+    );
+    for column in 0..12 {
+      diagnostic = diagnostic.with_label(Label::secondary(
+        Span {
+          file: "input".to_string(),
+          line: 1,
+          column,
+          length: 1,
+        },
+        Some(format!("location {}", column)),
+      ));
+    }
+
+    let output = DiagnosticFormatter::new(&diagnostic, "abcdefghijkl").format_plain();
+
+    for column in 0..8 {
+      assert!(output.contains(&format!("location {}\n", column)));
+    }
+    for column in 8..12 {
+      assert!(!output.contains(&format!("location {}\n", column)));
+    }
+    assert!(output.contains("... and 4 more related locations"));
+    assert_eq!(diagnostic.labels.len(), 12);
+  }
+
+  #[test]
+  fn test_plain_gutter_aligns_wide_line_numbers() {
+    let source = (1..=1234)
+      .map(|line| format!("line {}", line))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::DuplicateDeclaration,
+      "Duplicate declaration".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: "input".to_string(),
+        line: 5,
+        column: 1,
+        length: 4,
+      },
+      Some("declared again here".to_string()),
+    ))
+    .with_label(Label::secondary(
+      Span {
+        file: "input".to_string(),
+        line: 1234,
+        column: 1,
+        length: 4,
+      },
+      Some("first declared here".to_string()),
+    ))
+    .with_help("rename one of them".to_string());
+
+    let output = DiagnosticFormatter::new(&diagnostic, &source).format_plain();
+
+    assert!(output.contains("\n    5 | line 5\n"));
+    assert!(output.contains("\n 1234 | line 1234\n"));
+
+    let bars = output
+      .lines()
+      .filter_map(|line| line.find(['|', '=']))
+      .collect::<Vec<_>>();
+    assert!(bars.len() >= 6);
+    assert!(bars.iter().all(|column| *column == 6), "{}", output);
+  }
+
+  #[test]
+  fn test_colored_gutter_aligns_wide_line_numbers() {
+    colored::control::set_override(false);
+
+    let source = (1..=128)
+      .map(|line| format!("line {}", line))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::DuplicateDeclaration,
+      "Duplicate declaration".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: "input".to_string(),
+        line: 7,
+        column: 1,
+        length: 4,
+      },
+      Some("declared again here".to_string()),
+    ))
+    .with_label(Label::secondary(
+      Span {
+        file: "input".to_string(),
+        line: 128,
+        column: 1,
+        length: 4,
+      },
+      Some("first declared here".to_string()),
+    ))
+    .with_note("names must be unique".to_string())
+    .with_help("rename one of them".to_string());
+
+    let output = DiagnosticFormatter::new(&diagnostic, &source).format();
+
+    assert!(output.contains("\n   7 | line 7\n"), "{}", output);
+    assert!(output.contains("\n 128 | line 128\n"), "{}", output);
+    assert!(output.contains("\n 127 | line 127\n"), "{}", output);
+    assert!(output.contains("first declared here"), "{}", output);
+    assert!(output.contains("\n    ...\n"), "{}", output);
+
+    let bars = output
+      .lines()
+      .skip(2)
+      .filter_map(|line| line.find(['|', '=']))
+      .collect::<Vec<_>>();
+    assert!(bars.len() >= 6);
+    assert!(bars.iter().all(|column| *column == 5), "{}", output);
+  }
+
+  #[test]
+  fn test_colored_format_renders_a_shared_line_once() {
+    colored::control::set_override(false);
+
+    let source = "var a = 1;\nprint(a + b);\nprint(a);";
+    let at = |column, length| Span {
+      file: "input".to_string(),
+      line: 2,
+      column,
+      length,
+    };
+    let diagnostic = Diagnostic::new(DiagnosticCode::TypeMismatch, "Bad operands".to_string())
+      .with_label(Label::primary(at(6, 1), Some("a number".to_string())))
+      .with_label(Label::secondary(at(10, 1), Some("undefined".to_string())));
+
+    let output = DiagnosticFormatter::new(&diagnostic, source).format();
+
+    assert_eq!(output.matches("print(a + b);").count(), 1, "{}", output);
+    assert!(output.contains(" 1 | var a = 1;\n"), "{}", output);
+    assert!(output.contains(" 3 | print(a);\n"), "{}", output);
+    assert!(output.contains("a number"), "{}", output);
+    assert!(output.contains("undefined"), "{}", output);
+  }
+
+  #[test]
+  fn test_errors_first_renders_errors_before_warnings() {
+    let mut engine = DiagnosticEngine::new().with_errors_first(true);
+
+    let at = |code: DiagnosticCode, message: &str, line: usize| {
+      Diagnostic::new(code, message.to_string()).with_label(Label::primary(
+        Span {
+          file: "input".to_string(),
+          line,
+          column: 1,
+          length: 1,
+        },
+        None,
+      ))
+    };
+
+    engine.emit(at(DiagnosticCode::UnusedVariable, "early warning", 1));
+    engine.emit(at(DiagnosticCode::UnexpectedToken, "late error", 3));
+    engine.emit(at(DiagnosticCode::UnexpectedToken, "early error", 2));
+
+    let output = engine.format_all_plain("a\nb\nc\n");
+    let positions = ["early error", "late error", "early warning"]
+      .iter()
+      .map(|message| output.find(&format!("]: {}\n", message)).unwrap())
+      .collect::<Vec<_>>();
+
+    assert!(
+      positions.windows(2).all(|pair| pair[0] < pair[1]),
+      "diagnostics out of order in\n{}",
+      output
+    );
+    assert!(output.rfind("]: early warning").unwrap() < output.find("error: could not").unwrap());
+  }
+
+  #[test]
+  fn test_runtime_error_codes_are_distinct() {
+    use diagnostic::diagnostic_code::Severity;
+
+    let codes = [
+      DiagnosticCode::DivisionByZero,
+      DiagnosticCode::InvalidUnaryOperator,
+      DiagnosticCode::TypeError,
+    ]
+    .map(|code| {
+      assert_eq!(code.severity(), Severity::Error);
+      code.code()
+    });
+
+    assert!(codes.iter().all(|code| !code.is_empty()));
+    assert_ne!(codes[0], codes[1]);
+    assert_ne!(codes[0], codes[2]);
+    assert_ne!(codes[1], codes[2]);
+  }
+
+  #[test]
+  fn test_note_diagnostic_renders_note_header() {
+    let mut engine = DiagnosticEngine::new();
+    engine.emit(Diagnostic::note(
+      DiagnosticCode::Info,
+      "loaded 3 modules".to_string(),
+    ));
+
+    let output = engine.format_all_plain("");
+
+    assert!(
+      output.starts_with("note: [N0002]: loaded 3 modules"),
+      "{output}"
+    );
+    assert!(!output.contains("could not compile"));
+    assert_eq!(engine.error_count(), 0);
+    assert_eq!(engine.warning_count(), 0);
+    assert!(!engine.has_errors());
+  }
+
+  #[test]
+  fn test_help_diagnostic_is_not_counted() {
+    let mut engine = DiagnosticEngine::new();
+    engine.emit(Diagnostic::help_diag(
+      DiagnosticCode::Info,
+      "run with --sandbox to skip sleep".to_string(),
+    ));
+
+    let output = engine.format_all_plain("");
+
+    assert!(output.starts_with("help: [N0002]: run with --sandbox to skip sleep"));
+    assert_eq!(engine.error_count(), 0);
+    assert_eq!(engine.get_diagnostics().len(), 1);
+  }
+
+  fn label_on_line(line: usize) -> Diagnostic {
+    Diagnostic::new(
+      DiagnosticCode::UndeclaredVariable,
+      "Undeclared variable".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: "input".to_string(),
+        line,
+        column: 1,
+        length: 5,
+      },
+      Some("not declared".to_string()),
+    ))
+  }
+
+  #[test]
+  fn test_context_lines_surround_the_labelled_line() {
+    let source = "line1\nline2\nline3\nline4\nline5";
+    let diagnostic = label_on_line(3);
+
+    let plain = DiagnosticFormatter::new(&diagnostic, source).format_plain();
+    let shown = plain
+      .lines()
+      .filter_map(|line| line.split_once(" | ").map(|(_, code)| code))
+      .filter(|code| code.starts_with("line"))
+      .collect::<Vec<_>>();
+    assert_eq!(shown, vec!["line2", "line3", "line4"], "{plain}");
+    // Only the labelled line is underlined
+    assert!(plain.contains(" 3 | line3\n   | ^^^^^ not declared\n 4 | line4\n"));
+
+    colored::control::set_override(false);
+    let output = DiagnosticFormatter::new(&diagnostic, source).format();
+    for line in ["2 | line2", "3 | line3", "4 | line4"] {
+      assert!(output.contains(line), "missing {line} in\n{output}");
+    }
+    assert!(!output.contains("line1") && !output.contains("line5"));
+  }
+
+  #[test]
+  fn test_context_lines_stop_at_the_source_boundaries() {
+    let source = "line1\nline2\nline3";
+
+    let first = DiagnosticFormatter::new(&label_on_line(1), source).format_plain();
+    assert!(first.contains(" 1 | line1") && first.contains(" 2 | line2"));
+    assert!(!first.contains("line3"));
+
+    let last = DiagnosticFormatter::new(&label_on_line(3), source)
+      .with_context_lines(5)
+      .format_plain();
+    assert!(last.contains(" 1 | line1") && last.contains(" 3 | line3"));
+
+    let none = DiagnosticFormatter::new(&label_on_line(2), source)
+      .with_context_lines(0)
+      .format_plain();
+    assert!(!none.contains("line1") && !none.contains("line3"));
+  }
+
+  #[test]
+  fn test_labels_on_one_line_share_the_rendering() {
+    let at = |column: usize, length: usize| Span {
+      file: "input".to_string(),
+      line: 1,
+      column,
+      length,
+    };
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::WrongNumberOfArguments,
+      "Wrong number of arguments".to_string(),
+    )
+    .with_label(Label::primary(
+      at(9, 1),
+      Some("expected 1 argument".to_string()),
+    ))
+    .with_label(Label::secondary(at(5, 4), Some("called here".to_string())));
+    let source = "var add(1, 2);";
+
+    let plain = DiagnosticFormatter::new(&diagnostic, source).with_context_lines(0);
+    let output = plain.format_plain();
+
+    assert_eq!(output.matches(source).count(), 1, "{output}");
+    assert!(
+      output.contains("   |     ----^ expected 1 argument\n"),
+      "{output}"
+    );
+    assert!(output.contains("   |     called here\n"), "{output}");
+
+    colored::control::set_override(false);
+    let output = DiagnosticFormatter::new(&diagnostic, source).format();
+    assert_eq!(output.matches(source).count(), 1, "{output}");
+    assert_eq!(output.lines().filter(|line| line.contains('^')).count(), 1);
+  }
+
+  fn span(line: usize, column: usize, length: usize) -> Span {
+    Span {
+      file: "input".to_string(),
+      line,
+      column,
+      length,
+    }
+  }
+
+  #[test]
+  fn test_span_merge_adjacent() {
+    assert_eq!(span(1, 3, 2).merge(&span(1, 5, 4)), span(1, 3, 6));
+    // The order of the operands doesn't matter
+    assert_eq!(span(1, 5, 4).merge(&span(1, 3, 2)), span(1, 3, 6));
+  }
+
+  #[test]
+  fn test_span_merge_overlapping() {
+    assert_eq!(span(2, 1, 5).merge(&span(2, 3, 6)), span(2, 1, 8));
+    assert_eq!(span(2, 1, 10).merge(&span(2, 3, 2)), span(2, 1, 10));
+  }
+
+  #[test]
+  fn test_span_merge_on_different_lines_keeps_the_first() {
+    assert_eq!(span(3, 8, 2).merge(&span(1, 4, 1)), span(1, 4, 1));
+  }
+
+  #[test]
+  fn test_suggestion_inserts_replacement() {
+    let source = "return 1";
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::MissingSemicolon,
+      "Expected ';' after return value".to_string(),
+    )
+    .with_label(Label::primary(span(1, 8, 1), None))
+    .with_suggestion(span(1, 9, 0), ";".to_string());
+
+    let output = DiagnosticFormatter::new(&diagnostic, source).format_plain();
+    assert!(
+      output.contains("= help: try this: `return 1;`\n"),
+      "{output}"
+    );
+
+    colored::control::set_override(false);
+    let output = DiagnosticFormatter::new(&diagnostic, source).format();
+    assert!(output.contains("help: try this: `return 1;`\n"), "{output}");
+  }
+
+  #[test]
+  fn test_suggestion_replaces_span() {
+    let source = "var a = b;";
+    let diagnostic = Diagnostic::new(DiagnosticCode::UndeclaredVariable, "unknown".to_string())
+      .with_label(Label::primary(span(1, 9, 1), None))
+      .with_suggestion(span(1, 9, 1), "a".to_string());
+
+    let output = DiagnosticFormatter::new(&diagnostic, source).format_plain();
+    assert!(output.contains("try this: `var a = a;`"), "{output}");
+  }
+
+  #[test]
+  fn test_diagnostic_codes_are_unique() {
+    let mut seen = std::collections::HashMap::new();
+
+    for code in DiagnosticCode::ALL {
+      if let Some(other) = seen.insert(code.code(), code) {
+        panic!("{:?} and {:?} share {}", other, code, code.code());
+      }
+    }
+
+    assert_eq!(seen.len(), DiagnosticCode::ALL.len());
+  }
+
+  #[test]
+  fn test_source_map_renders_each_diagnostic_against_its_file() {
+    let mut sources = diagnostic::source_map::SourceMap::new();
+    sources.add_file("main.duck", "var a = b;\n");
+    sources.add_file("lib.duck", "print(1);\nprint(c);\n");
+
+    let mut engine = DiagnosticEngine::new().with_color(false);
+    for (file, line, column, name) in [("main.duck", 1, 9, "b"), ("lib.duck", 2, 7, "c")] {
+      engine.emit(
+        Diagnostic::new(
+          DiagnosticCode::UndeclaredVariable,
+          format!("Undefined variable \"{}\"", name),
+        )
+        .with_label(Label::primary(
+          Span {
+            file: file.to_string(),
+            line,
+            column,
+            length: 1,
+          },
+          None,
+        )),
+      );
+    }
+
+    let output = engine.format_all_sources(&sources);
+
+    assert!(output.contains("--> main.duck:1:9\n"), "{output}");
+    assert!(output.contains(" 1 | var a = b;\n"), "{output}");
+    assert!(output.contains("--> lib.duck:2:7\n"), "{output}");
+    assert!(output.contains(" 2 | print(c);\n"), "{output}");
+    assert!(output.contains(" 1 | print(1);\n"), "{output}");
+    assert_eq!(output.matches("var a = b;").count(), 1, "{output}");
+  }
+
+  #[test]
+  fn test_source_map_renders_each_label_against_its_file() {
+    let mut sources = diagnostic::source_map::SourceMap::new();
+    sources.add_file("main.duck", "import lib;\nshout(1, 2);\n");
+    sources.add_file("lib.duck", "fun shout(x) {\n  print(x);\n}\n");
+
+    let span = |file: &str, line, column, length| Span {
+      file: file.to_string(),
+      line,
+      column,
+      length,
+    };
+    let mut engine = DiagnosticEngine::new().with_color(false);
+    engine.emit(
+      Diagnostic::new(
+        DiagnosticCode::InvalidArguments,
+        "Expected 1 arguments but got 2".to_string(),
+      )
+      .with_label(Label::primary(
+        span("main.duck", 2, 1, 12),
+        Some("called here".to_string()),
+      ))
+      .with_label(Label::secondary(
+        span("lib.duck", 1, 5, 5),
+        Some("declared here".to_string()),
+      )),
+    );
+
+    let output = engine.format_all_sources(&sources);
+
+    assert!(output.contains("--> main.duck:2:1\n"), "{output}");
+    assert!(output.contains(" 2 | shout(1, 2);\n"), "{output}");
+    assert!(output.contains("::: lib.duck:1:5\n"), "{output}");
+    assert!(output.contains(" 1 | fun shout(x) {\n"), "{output}");
+    assert!(
+      output.contains("   |     ----- declared here\n"),
+      "{output}"
+    );
+  }
+
+  #[test]
+  fn test_sorting_keeps_files_apart() {
+    let sources = diagnostic::source_map::SourceMap::new();
+    let mut engine = DiagnosticEngine::new().with_color(false);
+    for file in ["b.duck", "a.duck", "b.duck"] {
+      engine.emit(
+        Diagnostic::new(DiagnosticCode::UndeclaredVariable, format!("in {}", file)).with_label(
+          Label::primary(
+            Span {
+              file: file.to_string(),
+              line: 1,
+              column: 1,
+              length: 1,
+            },
+            None,
+          ),
+        ),
+      );
+    }
+
+    let output = engine.format_all_sources(&sources);
+
+    let a = output.find("--> a.duck").expect("a.duck not rendered");
+    let b = output.find("--> b.duck").expect("b.duck not rendered");
+    assert!(a < b, "{output}");
+  }
+
+  #[test]
+  fn test_errors_and_warnings_iterate_by_severity() {
+    let mut engine = DiagnosticEngine::new();
+    engine.emit(label_on_line(1));
+    engine.emit(Diagnostic::new(
+      DiagnosticCode::UnusedVariable,
+      "Unused variable".to_string(),
+    ));
+    engine.emit(Diagnostic::help_diag(
+      DiagnosticCode::Info,
+      "not an error nor a warning".to_string(),
+    ));
+
+    let errors: Vec<_> = engine.errors().collect();
+    let warnings: Vec<_> = engine.warnings().collect();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::UndeclaredVariable);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, DiagnosticCode::UnusedVariable);
+  }
 }
@@ -77,13 +77,13 @@ impl LoxCallable for LoxClass {
 impl LoxClass {
   pub fn find_method(&self, name: &str) -> Option<&Arc<LoxFunction>> {
     if let Some(method) = self.methods.get(name) {
-      return Some(&method);
+      return Some(method);
     }
 
     if let LoxValue::Class(superclass_arc) = &self.superclass {
       // Recursively call find_method on the superclass's LoxClass
       // Note: We need to check if the superclass is actually a class before calling find_method
-      let superclass_loxclass: &LoxClass = &superclass_arc;
+      let superclass_loxclass: &LoxClass = superclass_arc;
       return superclass_loxclass.find_method(name);
     }
 
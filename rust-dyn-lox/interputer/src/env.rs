@@ -3,32 +3,51 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 #[derive(Debug, Clone)]
 pub struct Env {
-  pub values: HashMap<String, LoxValue>,
+  /// Slot of every variable defined in this frame
+  pub names: HashMap<String, usize>,
+  /// Variables in the order they were first defined, with their name so a cached slot can be
+  /// checked against the variable it's expected to hold
+  pub slots: Vec<(String, LoxValue)>,
   pub enclosing: Option<Rc<RefCell<Env>>>,
 }
 
+impl Default for Env {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl Env {
   pub fn new() -> Self {
     Self {
-      values: HashMap::new(),
+      names: HashMap::new(),
+      slots: Vec::new(),
       enclosing: None,
     }
   }
 
   pub fn with_enclosing(&mut self, env: Rc<RefCell<Env>>) -> Self {
     Self {
-      values: HashMap::new(),
+      names: HashMap::new(),
+      slots: Vec::new(),
       enclosing: Some(env),
     }
   }
 
+  /// Define a variable in this frame, redefining a name keeps its slot
   pub fn define(&mut self, name: String, value: LoxValue) {
-    self.values.insert(name, value);
+    match self.names.get(&name) {
+      Some(&slot) => self.slots[slot].1 = value,
+      None => {
+        self.names.insert(name.clone(), self.slots.len());
+        self.slots.push((name, value));
+      },
+    }
   }
 
   pub fn get(&self, name: &str) -> Option<LoxValue> {
-    if let Some(v) = self.values.get(name) {
-      return Some(v.clone());
+    if let Some(v) = self.get_local(name) {
+      return Some(v);
     }
     if let Some(enclosing) = &self.enclosing {
       return enclosing.borrow().get(name);
@@ -37,8 +56,7 @@ impl Env {
   }
 
   pub fn assign(&mut self, name: &str, value: LoxValue) -> bool {
-    if self.values.contains_key(name) {
-      self.values.insert(name.to_string(), value);
+    if self.assign_local(name, value.clone()) {
       return true;
     }
     if let Some(enclosing) = &mut self.enclosing {
@@ -51,35 +69,71 @@ impl Env {
   pub fn get_at(&self, distance: usize, name: &str) -> Option<LoxValue> {
     if distance == 0 {
       // Distance 0 means THIS environment
-      return self.values.get(name).cloned();
+      return self.get_local(name);
     }
 
     // Otherwise, walk up the chain
     self
       .ancestor(distance)
-      .and_then(|env| env.borrow().values.get(name).cloned())
+      .and_then(|env| env.borrow().get_local(name))
   }
 
   /// Assign at a specific depth (0 = current scope)
   pub fn assign_at(&mut self, distance: usize, name: &str, value: LoxValue) -> bool {
     if distance == 0 {
-      if self.values.contains_key(name) {
-        self.values.insert(name.to_string(), value);
-        return true;
-      }
-      return false;
+      return self.assign_local(name, value);
     }
 
     if let Some(env) = self.ancestor(distance) {
-      let mut env = env.borrow_mut();
-      if env.values.contains_key(name) {
-        env.values.insert(name.to_string(), value);
-        return true;
-      }
+      return env.borrow_mut().assign_local(name, value);
     }
     false
   }
 
+  /// Slot of `name` in the frame at a specific depth (0 = current scope)
+  pub fn slot_at(&self, distance: usize, name: &str) -> Option<usize> {
+    if distance == 0 {
+      return self.names.get(name).copied();
+    }
+
+    self
+      .ancestor(distance)
+      .and_then(|env| env.borrow().names.get(name).copied())
+  }
+
+  /// Get value by slot at a specific depth (0 = current scope), `None` if the slot doesn't hold
+  /// `name` in that frame
+  pub fn get_slot_at(&self, distance: usize, slot: usize, name: &str) -> Option<LoxValue> {
+    if distance == 0 {
+      return self.get_slot(slot, name);
+    }
+
+    self
+      .ancestor(distance)
+      .and_then(|env| env.borrow().get_slot(slot, name))
+  }
+
+  fn get_slot(&self, slot: usize, name: &str) -> Option<LoxValue> {
+    match self.slots.get(slot) {
+      Some((slot_name, value)) if slot_name == name => Some(value.clone()),
+      _ => None,
+    }
+  }
+
+  fn get_local(&self, name: &str) -> Option<LoxValue> {
+    self.names.get(name).map(|&slot| self.slots[slot].1.clone())
+  }
+
+  fn assign_local(&mut self, name: &str, value: LoxValue) -> bool {
+    match self.names.get(name) {
+      Some(&slot) => {
+        self.slots[slot].1 = value;
+        true
+      },
+      None => false,
+    }
+  }
+
   /// Walk up the environment chain by 'distance' steps
   /// distance=1 means parent, distance=2 means grandparent, etc.
   fn ancestor(&self, distance: usize) -> Option<Rc<RefCell<Env>>> {
@@ -0,0 +1,22 @@
+/// Why running a program failed, each kind has the exit code Lox implementations conventionally
+/// use for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunError {
+  /// The source file could not be read
+  Io,
+  /// Scanning, parsing or the semantic analysis reported an error, nothing was run
+  Compile,
+  /// The program started and hit an error while running
+  Runtime,
+}
+
+impl RunError {
+  /// Function that returns the process exit code for the failure.
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      RunError::Compile => 65,
+      RunError::Io => 66,
+      RunError::Runtime => 70,
+    }
+  }
+}
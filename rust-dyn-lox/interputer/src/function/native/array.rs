@@ -0,0 +1,177 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use diagnostic::{
+  diagnostic::{Diagnostic, Label},
+  diagnostic_code::DiagnosticCode,
+  DiagnosticEngine,
+};
+use scanner::token::Token;
+
+use crate::{
+  function::LoxCallable,
+  interpreter::Interpreter,
+  lox_value::{InterpreterError, LoxValue},
+};
+
+/// `array.sort()` and `array.sort(comparator)` bound to the array they were read from, sorts the
+/// array in place and returns it.
+pub struct ArraySortMethod {
+  pub array: Rc<RefCell<Vec<LoxValue>>>,
+  /// The `sort` token used to point the diagnostics at the call
+  pub name: Token,
+}
+
+impl LoxCallable for ArraySortMethod {
+  fn arity(&self) -> usize {
+    usize::MAX
+  }
+
+  fn call(
+    &self,
+    interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let values = self.array.borrow().clone();
+
+    let sorted = match arguments.as_slice() {
+      [] => {
+        if let Some(value) = values
+          .iter()
+          .find(|value| value.type_name() != values[0].type_name())
+        {
+          let diagnostic = Diagnostic::new(
+            DiagnosticCode::TypeMismatch,
+            "Cannot sort an array with mixed types".to_string(),
+          )
+          .with_label(Label::primary(
+            self.name.source_span(),
+            Some(format!(
+              "this array holds both {} and {} values",
+              values[0].type_name(),
+              value.type_name()
+            )),
+          ))
+          .with_help("pass a comparator like `sort(fun (a, b) { ... })`".to_string());
+          engine.emit(diagnostic);
+
+          return Err(InterpreterError::RuntimeError);
+        }
+
+        merge_sort(values, &mut |a, b| Ok(a.compare(b)))?
+      },
+      [(comparator, _)] => {
+        let comparator = comparator.clone();
+        merge_sort(values, &mut |a, b| {
+          self.compare_with(interpreter, &comparator, a, b, engine)
+        })?
+      },
+      _ => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::WrongNumberOfArguments,
+          "Wrong number of arguments".to_string(),
+        )
+        .with_label(Label::primary(
+          self.name.source_span(),
+          Some(format!(
+            "`sort` takes an optional comparator but got {} arguments",
+            arguments.len()
+          )),
+        ));
+        engine.emit(diagnostic);
+
+        return Err(InterpreterError::RuntimeError);
+      },
+    };
+
+    *self.array.borrow_mut() = sorted;
+    Ok(LoxValue::Array(self.array.clone()))
+  }
+}
+
+impl ArraySortMethod {
+  /// Function that calls the user comparator, a negative result orders `a` first
+  fn compare_with(
+    &self,
+    interpreter: &mut Interpreter,
+    comparator: &LoxValue,
+    a: &LoxValue,
+    b: &LoxValue,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<Ordering, InterpreterError> {
+    let arguments = vec![(a.clone(), None), (b.clone(), None)];
+    let result = match comparator {
+      LoxValue::Function(function) => function.call(interpreter, arguments, engine),
+      LoxValue::NativeFunction(function) => function.call(interpreter, arguments, engine),
+      _ => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::TypeMismatch,
+          "The comparator passed to `sort` is not a function".to_string(),
+        )
+        .with_label(Label::primary(
+          self.name.source_span(),
+          Some(format!("got a {} instead", comparator.type_name())),
+        ));
+        engine.emit(diagnostic);
+
+        return Err(InterpreterError::RuntimeError);
+      },
+    };
+
+    // Functions hand their `return` value back as an error
+    let result = match result {
+      Ok(value) | Err(InterpreterError::Return(value)) => value,
+      Err(err) => return Err(err),
+    };
+
+    match result {
+      LoxValue::Number(n) if n < 0.0 => Ok(Ordering::Less),
+      LoxValue::Number(n) if n > 0.0 => Ok(Ordering::Greater),
+      LoxValue::Number(_) => Ok(Ordering::Equal),
+      value => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::TypeMismatch,
+          "The comparator passed to `sort` must return a number".to_string(),
+        )
+        .with_label(Label::primary(
+          self.name.source_span(),
+          Some(format!("the comparator returned a {}", value.type_name())),
+        ));
+        engine.emit(diagnostic);
+
+        Err(InterpreterError::RuntimeError)
+      },
+    }
+  }
+}
+
+/// Stable merge sort that stops at the first comparator error, unlike `slice::sort_by` it's fine
+/// with comparators that aren't a total order.
+fn merge_sort(
+  mut values: Vec<LoxValue>,
+  compare: &mut impl FnMut(&LoxValue, &LoxValue) -> Result<Ordering, InterpreterError>,
+) -> Result<Vec<LoxValue>, InterpreterError> {
+  if values.len() <= 1 {
+    return Ok(values);
+  }
+
+  let right = values.split_off(values.len() / 2);
+  let left = merge_sort(values, compare)?;
+  let right = merge_sort(right, compare)?;
+
+  let mut merged = Vec::with_capacity(left.len() + right.len());
+  let mut left = left.into_iter().peekable();
+  let mut right = right.into_iter().peekable();
+
+  while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+    if compare(a, b)? == Ordering::Greater {
+      merged.push(right.next().unwrap());
+    } else {
+      merged.push(left.next().unwrap());
+    }
+  }
+  merged.extend(left);
+  merged.extend(right);
+
+  Ok(merged)
+}
@@ -40,3 +40,35 @@ impl LoxCallable for ClockFunction {
     Ok(LoxValue::Number(now))
   }
 }
+
+/// Clock native function with millisecond precision, for timing short runs
+pub struct ClockMillisFunction;
+
+impl ClockMillisFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "clock_millis".to_string(),
+      LoxValue::NativeFunction(Arc::new(ClockMillisFunction)),
+    );
+  }
+}
+
+impl LoxCallable for ClockMillisFunction {
+  fn arity(&self) -> usize {
+    0
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut crate::interpreter::Interpreter,
+    _arguments: Vec<(crate::lox_value::LoxValue, Option<scanner::token::Token>)>,
+    _engine: &mut diagnostic::DiagnosticEngine,
+  ) -> Result<crate::lox_value::LoxValue, InterpreterError> {
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_millis();
+
+    Ok(LoxValue::Number(now as f64))
+  }
+}
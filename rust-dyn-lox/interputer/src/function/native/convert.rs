@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use diagnostic::{
+  diagnostic::{Diagnostic, Label},
+  diagnostic_code::DiagnosticCode,
+  DiagnosticEngine,
+};
+use scanner::token::Token;
+
+use crate::{
+  function::LoxCallable,
+  interpreter::Interpreter,
+  lox_value::{InterpreterError, LoxValue},
+};
+
+/// Num native function, parses a string into a number
+pub struct NumFunction;
+
+/// Str native function, renders any value the way `print` shows it
+pub struct StrFunction;
+
+impl NumFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "num".to_string(),
+      LoxValue::NativeFunction(Arc::new(NumFunction)),
+    );
+  }
+}
+
+impl StrFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "str".to_string(),
+      LoxValue::NativeFunction(Arc::new(StrFunction)),
+    );
+  }
+}
+
+impl LoxCallable for NumFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let (value, token) = &arguments[0];
+
+    let diagnostic = match value {
+      LoxValue::String(text) => match text.trim().parse::<f64>() {
+        // `inf` and `NaN` parse too but aren't numbers a Lox literal can write
+        Ok(n) if n.is_finite() => return Ok(LoxValue::Number(n)),
+        _ => Diagnostic::new(
+          DiagnosticCode::InvalidNumber,
+          format!("num could not parse {:?} as a number", text),
+        )
+        .with_help(
+          "pass digits with an optional sign and decimal point, like `num(\"-4.5\")`".to_string(),
+        ),
+      },
+      _ => Diagnostic::new(
+        DiagnosticCode::TypeError,
+        format!("num expects a string, got {}", value.type_name()),
+      ),
+    };
+
+    let diagnostic = match token {
+      Some(token) => diagnostic.with_label(Label::primary(
+        token.source_span(),
+        Some(format!("this is a {}", value.type_name())),
+      )),
+      None => diagnostic,
+    };
+
+    engine.emit(diagnostic);
+    Err(InterpreterError::RuntimeError)
+  }
+}
+
+impl LoxCallable for StrFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    _engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    Ok(LoxValue::String(arguments[0].0.to_string()))
+  }
+}
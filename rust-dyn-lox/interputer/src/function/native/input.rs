@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use crate::{
+  function::LoxCallable,
+  interpreter::Interpreter,
+  lox_value::{InterpreterError, LoxValue},
+};
+
+/// Input native function, reads a line from the interpreter input
+pub struct InputFunction;
+
+impl InputFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "input".to_string(),
+      LoxValue::NativeFunction(Arc::new(InputFunction)),
+    );
+  }
+}
+
+impl LoxCallable for InputFunction {
+  fn arity(&self) -> usize {
+    0
+  }
+
+  fn call(
+    &self,
+    interpreter: &mut crate::interpreter::Interpreter,
+    _arguments: Vec<(crate::lox_value::LoxValue, Option<scanner::token::Token>)>,
+    _engine: &mut diagnostic::DiagnosticEngine,
+  ) -> Result<crate::lox_value::LoxValue, InterpreterError> {
+    // Running out of input is `nil`, so a script can loop until it's done
+    Ok(match interpreter.input.read_line() {
+      Some(line) => LoxValue::String(line),
+      None => LoxValue::Nil,
+    })
+  }
+}
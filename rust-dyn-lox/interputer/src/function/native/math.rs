@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use diagnostic::{
+  diagnostic::{Diagnostic, Label},
+  diagnostic_code::DiagnosticCode,
+  DiagnosticEngine,
+};
+use scanner::token::Token;
+
+use crate::{
+  function::LoxCallable,
+  interpreter::Interpreter,
+  lox_value::{InterpreterError, LoxValue},
+};
+
+/// Min native function, the smaller of two numbers
+pub struct MinFunction;
+
+/// Max native function, the larger of two numbers
+pub struct MaxFunction;
+
+/// Abs native function, the absolute value of a number
+pub struct AbsFunction;
+
+/// Floor native function, the largest integer not above a number
+pub struct FloorFunction;
+
+/// Ceil native function, the smallest integer not below a number
+pub struct CeilFunction;
+
+/// Round native function, the nearest integer with halves rounded away from zero
+pub struct RoundFunction;
+
+impl MinFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "min".to_string(),
+      LoxValue::NativeFunction(Arc::new(MinFunction)),
+    );
+  }
+}
+
+impl MaxFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "max".to_string(),
+      LoxValue::NativeFunction(Arc::new(MaxFunction)),
+    );
+  }
+}
+
+impl AbsFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "abs".to_string(),
+      LoxValue::NativeFunction(Arc::new(AbsFunction)),
+    );
+  }
+}
+
+impl LoxCallable for MinFunction {
+  fn arity(&self) -> usize {
+    2
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let a = number_argument("min", &arguments[0], engine)?;
+    let b = number_argument("min", &arguments[1], engine)?;
+    Ok(LoxValue::Number(a.min(b)))
+  }
+}
+
+impl LoxCallable for MaxFunction {
+  fn arity(&self) -> usize {
+    2
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let a = number_argument("max", &arguments[0], engine)?;
+    let b = number_argument("max", &arguments[1], engine)?;
+    Ok(LoxValue::Number(a.max(b)))
+  }
+}
+
+impl LoxCallable for AbsFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let n = number_argument("abs", &arguments[0], engine)?;
+    Ok(LoxValue::Number(n.abs()))
+  }
+}
+
+impl FloorFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "floor".to_string(),
+      LoxValue::NativeFunction(Arc::new(FloorFunction)),
+    );
+  }
+}
+
+impl LoxCallable for FloorFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let n = number_argument("floor", &arguments[0], engine)?;
+    Ok(LoxValue::Number(n.floor()))
+  }
+}
+
+impl CeilFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "ceil".to_string(),
+      LoxValue::NativeFunction(Arc::new(CeilFunction)),
+    );
+  }
+}
+
+impl LoxCallable for CeilFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let n = number_argument("ceil", &arguments[0], engine)?;
+    Ok(LoxValue::Number(n.ceil()))
+  }
+}
+
+impl RoundFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "round".to_string(),
+      LoxValue::NativeFunction(Arc::new(RoundFunction)),
+    );
+  }
+}
+
+impl LoxCallable for RoundFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    arguments: Vec<(LoxValue, Option<Token>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<LoxValue, InterpreterError> {
+    let n = number_argument("round", &arguments[0], engine)?;
+    Ok(LoxValue::Number(n.round()))
+  }
+}
+
+/// Function that unwraps a numeric argument of the `name` native, anything else is a type error.
+fn number_argument(
+  name: &str,
+  (value, token): &(LoxValue, Option<Token>),
+  engine: &mut DiagnosticEngine,
+) -> Result<f64, InterpreterError> {
+  if let LoxValue::Number(n) = value {
+    return Ok(*n);
+  }
+
+  let mut diagnostic = Diagnostic::new(
+    DiagnosticCode::TypeError,
+    format!("{} expects numbers, got {}", name, value.type_name()),
+  );
+
+  if let Some(token) = token {
+    diagnostic = diagnostic.with_label(Label::primary(
+      token.source_span(),
+      Some(format!("this is a {}", value.type_name())),
+    ));
+  }
+
+  engine.emit(diagnostic);
+  Err(InterpreterError::RuntimeError)
+}
@@ -1,2 +1,7 @@
+pub mod array;
 pub mod clock;
+pub mod convert;
+pub mod input;
+pub mod math;
 pub mod print;
+pub mod sleep;
@@ -25,21 +25,73 @@ impl LoxCallable for PrintFunction {
 
   fn call(
     &self,
-    _interpreter: &mut crate::interpreter::Interpreter,
+    interpreter: &mut crate::interpreter::Interpreter,
     arguments: Vec<(crate::lox_value::LoxValue, Option<scanner::token::Token>)>,
     _engine: &mut diagnostic::DiagnosticEngine,
   ) -> Result<crate::lox_value::LoxValue, InterpreterError> {
-    // Map each (LoxValue, _) to string using Display
-    let output = arguments
-      .iter()
-      .map(|(val, _)| val.to_string())
-      .collect::<Vec<_>>()
-      .join(" ");
-
-    // Print to stdout
-    println!("{}", output);
-
-    // Return nil (like Lox `print` does)
-    Ok(crate::lox_value::LoxValue::Nil)
+    let output = render(interpreter, &arguments);
+
+    // Print to the interpreter output sink
+    interpreter.output.write_line(&output);
+
+    Ok(printed_value(arguments, output))
+  }
+}
+
+/// Print native function that writes to the error sink, stderr by default
+pub struct EprintFunction;
+
+impl EprintFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "eprint".to_string(),
+      LoxValue::NativeFunction(Arc::new(EprintFunction)),
+    );
+  }
+}
+
+impl LoxCallable for EprintFunction {
+  fn arity(&self) -> usize {
+    usize::MAX
+  }
+
+  fn call(
+    &self,
+    interpreter: &mut crate::interpreter::Interpreter,
+    arguments: Vec<(crate::lox_value::LoxValue, Option<scanner::token::Token>)>,
+    _engine: &mut diagnostic::DiagnosticEngine,
+  ) -> Result<crate::lox_value::LoxValue, InterpreterError> {
+    let output = render(interpreter, &arguments);
+
+    interpreter.error_output.write_line(&output);
+
+    Ok(printed_value(arguments, output))
+  }
+}
+
+/// Function that joins the arguments with spaces, each one rendered with `Display`
+fn render(
+  interpreter: &Interpreter,
+  arguments: &[(LoxValue, Option<scanner::token::Token>)],
+) -> String {
+  arguments
+    .iter()
+    .map(|(val, _)| {
+      val.display_limited(interpreter.max_print_depth, interpreter.max_print_elements)
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Function that returns what was printed so the call can be used as an expression, the argument
+/// itself when there's a single one and the printed line when there are several
+fn printed_value(
+  mut arguments: Vec<(LoxValue, Option<scanner::token::Token>)>,
+  output: String,
+) -> LoxValue {
+  match arguments.len() {
+    0 => LoxValue::Nil,
+    1 => arguments.remove(0).0,
+    _ => LoxValue::String(output),
   }
 }
@@ -0,0 +1,66 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use diagnostic::{
+  diagnostic::{Diagnostic, Label},
+  diagnostic_code::DiagnosticCode,
+};
+
+use crate::{
+  function::LoxCallable,
+  interpreter::Interpreter,
+  lox_value::{InterpreterError, LoxValue},
+};
+
+/// Sleep native function, parks the thread for the given number of milliseconds
+pub struct SleepFunction;
+
+impl SleepFunction {
+  pub fn add(interpreter: &mut Interpreter) {
+    interpreter.env.borrow_mut().define(
+      "sleep".to_string(),
+      LoxValue::NativeFunction(Arc::new(SleepFunction)),
+    );
+  }
+}
+
+impl LoxCallable for SleepFunction {
+  fn arity(&self) -> usize {
+    1
+  }
+
+  fn call(
+    &self,
+    _interpreter: &mut crate::interpreter::Interpreter,
+    arguments: Vec<(crate::lox_value::LoxValue, Option<scanner::token::Token>)>,
+    engine: &mut diagnostic::DiagnosticEngine,
+  ) -> Result<crate::lox_value::LoxValue, InterpreterError> {
+    let (value, token) = &arguments[0];
+
+    match value {
+      LoxValue::Number(millis) if millis.is_finite() && *millis >= 0.0 => {
+        thread::sleep(Duration::from_secs_f64(*millis / 1000.0));
+        Ok(LoxValue::Nil)
+      },
+      _ => {
+        let mut diagnostic = Diagnostic::new(
+          DiagnosticCode::TypeError,
+          format!(
+            "sleep expects a non-negative number of milliseconds, got {}",
+            value
+          ),
+        )
+        .with_help("pass the duration in milliseconds, like `sleep(500)`".to_string());
+
+        if let Some(token) = token {
+          diagnostic = diagnostic.with_label(Label::primary(
+            token.source_span(),
+            Some(format!("this is a {}", value.type_name())),
+          ));
+        }
+
+        engine.emit(diagnostic);
+        Err(InterpreterError::RuntimeError)
+      },
+    }
+  }
+}
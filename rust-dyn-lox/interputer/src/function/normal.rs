@@ -45,7 +45,7 @@ impl LoxCallable for LoxFunction {
         .define(self.params[i].lexeme.to_string(), arg_val.clone());
     }
 
-    match interpreter.eval_block(Box::new(self.body.clone()), &mut enclosing_env, engine) {
+    match interpreter.eval_block(self.body.clone(), &mut enclosing_env, engine) {
       Ok((v, _)) => {
         if self.is_initializer {
           return Ok(enclosing_env.borrow().get_at(1, "this").unwrap());
@@ -58,7 +58,7 @@ impl LoxCallable for LoxFunction {
           if self.is_initializer {
             return Ok(enclosing_env.borrow().get_at(1, "this").unwrap());
           }
-          return Ok(v);
+          Ok(v)
         },
         _ => Ok(LoxValue::Nil),
       },
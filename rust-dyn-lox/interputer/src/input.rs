@@ -0,0 +1,61 @@
+use std::{
+  cell::RefCell,
+  collections::VecDeque,
+  io::{self, BufRead, Read},
+  rc::Rc,
+};
+
+/// Where the `input` native of the interpreter reads lines from.
+#[derive(Debug, Clone, Default)]
+pub enum Input {
+  /// Read straight from the process stdin, used by the binary.
+  #[default]
+  Stdin,
+  /// Hand out the lines of a fixed text, used for feeding a run a known input.
+  Buffer(Rc<RefCell<VecDeque<String>>>),
+}
+
+impl Input {
+  /// Function that creates a source handing out the lines of `text`.
+  pub fn buffer(text: &str) -> Self {
+    Input::Buffer(Rc::new(RefCell::new(
+      text.lines().map(str::to_string).collect(),
+    )))
+  }
+
+  /// Function that reads the next line without its trailing newline, `None` at the end of input.
+  pub fn read_line(&self) -> Option<String> {
+    match self {
+      Input::Stdin => {
+        let mut line = String::new();
+        match std::io::stdin().lock().read_line(&mut line) {
+          Ok(0) | Err(_) => None,
+          Ok(_) => {
+            let trimmed = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(trimmed);
+            Some(line)
+          },
+        }
+      },
+      Input::Buffer(lines) => lines.borrow_mut().pop_front(),
+    }
+  }
+
+  /// Function that reads everything that's left, every line ends with a newline.
+  pub fn read_to_end(&self) -> io::Result<String> {
+    match self {
+      Input::Stdin => {
+        let mut text = String::new();
+        io::stdin().lock().read_to_string(&mut text)?;
+        Ok(text)
+      },
+      Input::Buffer(lines) => Ok(
+        lines
+          .borrow_mut()
+          .drain(..)
+          .map(|line| line + "\n")
+          .collect(),
+      ),
+    }
+  }
+}
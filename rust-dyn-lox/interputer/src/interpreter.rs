@@ -6,23 +6,71 @@ use diagnostic::{
   DiagnosticEngine,
 };
 use parser::{expr::Expr, stmt::Stmt};
-use scanner::token::{types::Literal, Token};
+use scanner::token::{
+  types::{Literal, TokenType},
+  Token,
+};
+use semantic_analysis::resolver::UseSite;
 
 use crate::{
-  class::{LoxClass, LoxClassInstance},
+  class::LoxClass,
   env::Env,
   function::{
-    native::{clock::ClockFunction, print::PrintFunction},
+    native::{
+      array::ArraySortMethod,
+      clock::{ClockFunction, ClockMillisFunction},
+      convert::{NumFunction, StrFunction},
+      input::InputFunction,
+      math::{AbsFunction, CeilFunction, FloorFunction, MaxFunction, MinFunction, RoundFunction},
+      print::{EprintFunction, PrintFunction},
+      sleep::SleepFunction,
+    },
     normal::LoxFunction,
     LoxCallable,
   },
-  lox_value::{InterpreterError, LoxValue},
+  input::Input,
+  lox_value::{
+    InterpreterError, LoxValue, DEFAULT_MAX_DISPLAY_DEPTH, DEFAULT_MAX_DISPLAY_ELEMENTS,
+  },
+  output::Output,
 };
 
+/// How many calls can be nested before the program fails with a diagnostic
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
 #[derive(Debug, Clone)]
 pub struct Interpreter {
   pub env: Rc<RefCell<Env>>,
-  pub locals: HashMap<String, usize>,
+  pub locals: HashMap<UseSite, usize>,
+  /// Sink that receives everything the program prints
+  pub output: Output,
+  /// Sink that receives everything the program prints with `eprint`
+  pub error_output: Output,
+  /// Source the `input` native reads lines from
+  pub input: Input,
+  /// Leave out the natives that reach outside the program, like `sleep`
+  pub sandbox: bool,
+  /// Register the `print` and `eprint` natives, off for hosts that only want the value a program evaluates to
+  pub native_print: bool,
+  /// How many levels of nested arrays `print` renders
+  pub max_print_depth: usize,
+  /// How many elements of an array `print` renders
+  pub max_print_elements: usize,
+  /// Look resolved identifiers up by the slot cached for them instead of by name
+  pub cache_lookups: bool,
+  /// How many calls can be nested before the program fails instead of overflowing the stack
+  pub max_call_depth: usize,
+  /// How many calls are running right now
+  call_depth: usize,
+  /// Slot in its frame of every resolved identifier read so far, keyed by the same use site as
+  /// its depth in `locals`
+  slot_cache: RefCell<HashMap<UseSite, usize>>,
+}
+
+impl Default for Interpreter {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Interpreter {
@@ -30,24 +78,64 @@ impl Interpreter {
     Self {
       env: Rc::new(RefCell::new(Env::new())),
       locals: HashMap::new(),
+      output: Output::Stdout,
+      error_output: Output::Stderr,
+      input: Input::Stdin,
+      sandbox: false,
+      native_print: true,
+      max_print_depth: DEFAULT_MAX_DISPLAY_DEPTH,
+      max_print_elements: DEFAULT_MAX_DISPLAY_ELEMENTS,
+      cache_lookups: true,
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+      call_depth: 0,
+      slot_cache: RefCell::new(HashMap::new()),
     }
   }
 
+  /// Function that runs the program and returns the value of its last statement when that's an
+  /// expression statement, `nil` otherwise.
   pub fn run(
     &mut self,
     ast: Vec<Stmt>,
-    locals: HashMap<String, usize>,
+    locals: HashMap<UseSite, usize>,
     engine: &mut DiagnosticEngine,
-  ) {
-    PrintFunction::add(self);
+  ) -> LoxValue {
+    if self.native_print {
+      PrintFunction::add(self);
+      EprintFunction::add(self);
+    }
     ClockFunction::add(self);
+    ClockMillisFunction::add(self);
+    FloorFunction::add(self);
+    CeilFunction::add(self);
+    RoundFunction::add(self);
+    NumFunction::add(self);
+    StrFunction::add(self);
+    InputFunction::add(self);
+    MinFunction::add(self);
+    MaxFunction::add(self);
+    AbsFunction::add(self);
+    if !self.sandbox {
+      SleepFunction::add(self);
+    }
     self.locals = locals;
 
     let mut env = self.env.clone();
+    let mut result = LoxValue::Nil;
     for stmt in ast {
-      let _ = self.eval_stmt(stmt, &mut env, engine);
+      result = match stmt {
+        Stmt::Expr(expr) => match self.eval_expr(expr, &mut env, engine) {
+          Ok((value, _)) => value,
+          Err(_) => LoxValue::Nil,
+        },
+        stmt => {
+          let _ = self.eval_stmt(stmt, &mut env, engine);
+          LoxValue::Nil
+        },
+      };
     }
     self.env = env;
+    result
   }
 
   pub fn eval_stmt(
@@ -59,36 +147,44 @@ impl Interpreter {
     match stmt {
       Stmt::Expr(expr) => {
         self.eval_expr(expr, env, engine)?;
-        return Ok(());
+        Ok(())
       },
       Stmt::VarDecl(identifier_token, expr) => match expr {
         Some(expr) => {
           let (expr_value, _) = self.eval_expr(expr, env, engine)?;
           env.borrow_mut().define(identifier_token.lexeme, expr_value);
-          return Ok(());
+          Ok(())
         },
         None => {
           env
             .borrow_mut()
             .define(identifier_token.lexeme, LoxValue::Nil);
-          return Ok(());
+          Ok(())
         },
       },
       Stmt::Block(block) => {
-        self.eval_block(block, env, engine)?;
-        return Ok(());
+        self.eval_block(*block, env, engine)?;
+        Ok(())
       },
       Stmt::If(condition, then_branch, else_branch) => {
         self.eval_if(env, *condition, *then_branch, else_branch, engine)?;
-        return Ok(());
+        Ok(())
+      },
+      Stmt::While(condition, stmt, increment, label) => {
+        self.eval_while(env, *condition, *stmt, increment.map(|i| *i), label, engine)?;
+        Ok(())
+      },
+      Stmt::DoWhile(stmt, condition, label) => {
+        self.eval_do_while(env, *stmt, *condition, label, engine)?;
+        Ok(())
       },
-      Stmt::While(condition, stmt) => {
-        self.eval_while(env, *condition, *stmt, engine)?;
-        return Ok(());
+      Stmt::Switch(discriminant, cases) => {
+        self.eval_switch(env, *discriminant, cases, engine)?;
+        Ok(())
       },
       Stmt::Fun(name, params, body) => {
         self.eval_fun(env, name, params, *body, engine)?;
-        return Ok(());
+        Ok(())
       },
       Stmt::Return(name, _) => {
         let diagnostic = Diagnostic::new(
@@ -100,42 +196,13 @@ impl Interpreter {
           Some("return statement here".to_string()),
         ));
 
-        engine.emit(diagnostic);
-        return Ok(());
-      },
-      Stmt::Break(token) => {
-        let mut token = token;
-        token.position.0 -= 1;
-        token.position.1 += 7;
-        // At top level, this is an error
-        let diagnostic = Diagnostic::new(
-          DiagnosticCode::BreakOutsideLoop,
-          "Break statement outside of loop".to_string(),
-        )
-        .with_label(Label::primary(
-          token.to_span(),
-          Some("break not allowed here".to_string()),
-        ))
-        .with_help("Break statements can only be used inside while loops".to_string());
-
         engine.emit(diagnostic);
         Ok(())
       },
-
-      Stmt::Continue(token) => {
-        // At top level, this is an error
-        let diagnostic = Diagnostic::new(
-          DiagnosticCode::ContinueOutsideLoop,
-          "Continue statement outside of loop".to_string(),
-        )
-        .with_label(Label::primary(
-          token.to_span(),
-          Some("continue not allowed here".to_string()),
-        ))
-        .with_help("Continue statements can only be used inside while loops".to_string());
-
-        engine.emit(diagnostic);
-        Ok(())
+      // The resolver already rejects a `break` or `continue` outside of a loop
+      Stmt::Break(_token, label) => Err(InterpreterError::Break(label.map(|label| label.lexeme))),
+      Stmt::Continue(_token, label) => {
+        Err(InterpreterError::Continue(label.map(|label| label.lexeme)))
       },
       Stmt::Class(name, superclass, methods, static_methods) => {
         self.eval_class(env, name, superclass, *methods, *static_methods, engine)?;
@@ -245,7 +312,7 @@ impl Interpreter {
     env: &mut Rc<RefCell<Env>>,
     methods: Vec<Stmt>,
     methods_map: &mut HashMap<String, Arc<LoxFunction>>,
-    engine: &mut DiagnosticEngine,
+    _engine: &mut DiagnosticEngine,
   ) {
     for method in methods {
       match method {
@@ -310,18 +377,15 @@ impl Interpreter {
       })
       .collect::<Result<Vec<_>, _>>()?;
 
-    match body {
-      Stmt::Block(body) => {
-        let function = Arc::new(LoxFunction {
-          params: params_names,
-          body: *body,
-          closure: env.borrow().enclosing.clone().unwrap_or(env.clone()),
-          is_initializer: false,
-        });
+    if let Stmt::Block(body) = body {
+      let function = Arc::new(LoxFunction {
+        params: params_names,
+        body: *body,
+        closure: env.clone(),
+        is_initializer: false,
+      });
 
-        env.borrow_mut().define(name, LoxValue::Function(function));
-      },
-      _ => {},
+      env.borrow_mut().define(name, LoxValue::Function(function));
     };
 
     Ok((LoxValue::Nil, None))
@@ -332,6 +396,8 @@ impl Interpreter {
     env: &mut Rc<RefCell<Env>>,
     condition: Expr,
     stmt: Stmt,
+    increment: Option<Expr>,
+    label: Option<Token>,
     engine: &mut DiagnosticEngine,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
     loop {
@@ -341,18 +407,84 @@ impl Interpreter {
         break;
       }
 
-      // Execute the body and handle break/continue
+      // Execute the body and handle break/continue, the ones aimed at an outer loop propagate
+      match self.eval_stmt(stmt.clone(), env, engine) {
+        Ok(_) => {}, // Normal execution, continue loop
+        Err(InterpreterError::Break(target)) if InterpreterError::targets(&target, &label) => {
+          break; // Break out of loop
+        },
+        Err(InterpreterError::Continue(target)) if InterpreterError::targets(&target, &label) => {
+          // Continue to next iteration
+        },
+        Err(e) => return Err(e), // Propagate other errors (like Return)
+      }
+
+      // The increment of a `for` runs before the next check of the condition
+      if let Some(increment) = &increment {
+        self.eval_expr(increment.clone(), env, engine)?;
+      }
+    }
+
+    Ok((LoxValue::Nil, None))
+  }
+
+  /// Function that runs the body once before checking the condition for the first time
+  fn eval_do_while(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    stmt: Stmt,
+    condition: Expr,
+    label: Option<Token>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    loop {
       match self.eval_stmt(stmt.clone(), env, engine) {
-        Ok(_) => continue,                           // Normal execution, continue loop
-        Err(InterpreterError::Break) => break,       // Break out of loop
-        Err(InterpreterError::Continue) => continue, // Continue to next iteration
-        Err(e) => return Err(e),                     // Propagate other errors (like Return)
+        Ok(_) => {},
+        Err(InterpreterError::Continue(target)) if InterpreterError::targets(&target, &label) => {},
+        Err(InterpreterError::Break(target)) if InterpreterError::targets(&target, &label) => break,
+        Err(e) => return Err(e),
+      }
+
+      let (condition_val, _) = self.eval_expr(condition.clone(), env, engine)?;
+
+      if !self.is_truthy(&condition_val) {
+        break;
       }
     }
 
     Ok((LoxValue::Nil, None))
   }
 
+  /// Function that runs the statements of the first case equal to the discriminant, or of the
+  /// `default` case when none is, the cases after it are never run
+  fn eval_switch(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    discriminant: Expr,
+    cases: Vec<(Option<Expr>, Vec<Stmt>)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let (discriminant_val, _) = self.eval_expr(discriminant, env, engine)?;
+
+    let mut default = None;
+    for (value, stmts) in cases {
+      let Some(value) = value else {
+        default = Some(stmts);
+        continue;
+      };
+
+      let (case_val, _) = self.eval_expr(value, env, engine)?;
+      if Self::is_equal(&discriminant_val, &case_val) {
+        return self.eval_block(stmts, env, engine);
+      }
+    }
+
+    match default {
+      Some(stmts) => self.eval_block(stmts, env, engine),
+      None => Ok((LoxValue::Nil, None)),
+    }
+  }
+
   fn eval_if(
     &mut self,
     env: &mut Rc<RefCell<Env>>,
@@ -361,35 +493,19 @@ impl Interpreter {
     else_branch: Option<Box<Stmt>>,
     engine: &mut DiagnosticEngine,
   ) -> Result<(), InterpreterError> {
-    let (expr_val, token) = self.eval_expr(condition, env, engine)?;
+    let (expr_val, _) = self.eval_expr(condition, env, engine)?;
 
-    match expr_val {
-      LoxValue::Bool(v) => {
-        if v {
-          self.eval_stmt(then_branch, env, engine)?;
-        } else {
-          if let Some(else_branch) = else_branch {
-            self.eval_stmt(*else_branch, env, engine)?;
-          }
-        }
-        Ok(())
-      },
-      _ => {
-        self.emit_type_error(
-          engine,
-          &token.unwrap(),
-          None,
-          "If condition must be a boolean",
-          &format!("Expected boolean, found {}", &expr_val.to_string()),
-        )?;
-        Err(InterpreterError::RuntimeError)
-      },
+    if self.is_truthy(&expr_val) {
+      self.eval_stmt(then_branch, env, engine)?;
+    } else if let Some(else_branch) = else_branch {
+      self.eval_stmt(*else_branch, env, engine)?;
     }
+    Ok(())
   }
 
   pub fn eval_block(
     &mut self,
-    block: Box<Vec<Stmt>>,
+    block: Vec<Stmt>,
     env: &mut Rc<RefCell<Env>>,
     engine: &mut DiagnosticEngine,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
@@ -397,7 +513,7 @@ impl Interpreter {
       env.borrow_mut().with_enclosing(Rc::clone(env)),
     ));
 
-    for stmt in *block {
+    for stmt in block {
       match stmt {
         Stmt::VarDecl(identifier_token, expr) => match expr {
           Some(expr) => {
@@ -416,7 +532,7 @@ impl Interpreter {
           self.eval_expr(expr, &mut enclosing_env, engine)?;
         },
         Stmt::Block(block) => {
-          self.eval_block(block, &mut enclosing_env, engine)?;
+          self.eval_block(*block, &mut enclosing_env, engine)?;
         },
         Stmt::If(condition, then_branch, else_branch) => {
           self.eval_if(
@@ -427,8 +543,21 @@ impl Interpreter {
             engine,
           )?;
         },
-        Stmt::While(condition, stmt) => {
-          self.eval_while(&mut enclosing_env, *condition, *stmt, engine)?;
+        Stmt::While(condition, stmt, increment, label) => {
+          self.eval_while(
+            &mut enclosing_env,
+            *condition,
+            *stmt,
+            increment.map(|i| *i),
+            label,
+            engine,
+          )?;
+        },
+        Stmt::DoWhile(stmt, condition, label) => {
+          self.eval_do_while(&mut enclosing_env, *stmt, *condition, label, engine)?;
+        },
+        Stmt::Switch(discriminant, cases) => {
+          self.eval_switch(&mut enclosing_env, *discriminant, cases, engine)?;
         },
         Stmt::Fun(name, params, body) => {
           self.eval_fun(&mut enclosing_env, name, params, *body, engine)?;
@@ -436,14 +565,21 @@ impl Interpreter {
         Stmt::Return(name, value) => {
           self.eval_return(&mut enclosing_env, name, value, engine)?;
         },
-        Stmt::Break(token) => {
-          return Err(InterpreterError::Break);
+        Stmt::Break(_token, label) => {
+          return Err(InterpreterError::Break(label.map(|label| label.lexeme)));
         },
-        Stmt::Continue(token) => {
-          return Err(InterpreterError::Continue);
+        Stmt::Continue(_token, label) => {
+          return Err(InterpreterError::Continue(label.map(|label| label.lexeme)));
         },
         Stmt::Class(name, superclass, methods, static_methods) => {
-          self.eval_class(env, name, superclass, *methods, *static_methods, engine)?;
+          self.eval_class(
+            &mut enclosing_env,
+            name,
+            superclass,
+            *methods,
+            *static_methods,
+            engine,
+          )?;
         },
       }
     }
@@ -462,6 +598,7 @@ impl Interpreter {
       Expr::Grouping(expr) => self.eval_grouping(env, *expr, engine),
       Expr::Unary { operator, rhs } => self.eval_unary(env, operator, *rhs, engine),
       Expr::Binary { lhs, operator, rhs } => self.eval_binary(env, *lhs, operator, *rhs, engine),
+      Expr::Logical { lhs, operator, rhs } => self.eval_logical(env, operator, *lhs, *rhs, engine),
       Expr::Ternary {
         condition,
         then_branch,
@@ -486,7 +623,140 @@ impl Interpreter {
       } => self.eval_set(env, *object, name, *value, engine),
       Expr::This(token) => self.eval_identifier(token, env, engine),
       Expr::Super(token, name) => self.eval_super_expr(token, name, env),
+      Expr::Template {
+        token,
+        parts,
+        expressions,
+      } => self.eval_template(env, token, parts, expressions, engine),
+      Expr::Array { bracket, elements } => self.eval_array(env, bracket, elements, engine),
+      Expr::Lambda {
+        keyword,
+        name,
+        params,
+        body,
+      } => self.eval_lambda(env, keyword, name, params, *body),
+      Expr::Match {
+        keyword,
+        scrutinee,
+        arms,
+      } => self.eval_match(env, keyword, *scrutinee, arms, engine),
+    }
+  }
+
+  /// Function that evaluates the value of the first arm whose pattern equals the scrutinee, the
+  /// `_` arm matches anything, and errors when no arm matches
+  fn eval_match(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    keyword: Token,
+    scrutinee: Expr,
+    arms: Vec<(Option<Expr>, Expr)>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let (scrutinee_val, _) = self.eval_expr(scrutinee, env, engine)?;
+
+    for (pattern, value) in arms {
+      if let Some(pattern) = pattern {
+        let (pattern_val, _) = self.eval_expr(pattern, env, engine)?;
+        if !Self::is_equal(&scrutinee_val, &pattern_val) {
+          continue;
+        }
+      }
+
+      return self.eval_expr(value, env, engine);
+    }
+
+    self.emit_error(
+      engine,
+      DiagnosticCode::NonExhaustiveMatch,
+      &format!("No match arm covers the value {}", scrutinee_val),
+      &keyword,
+      "none of the arms of this match matched",
+      Some("add a `_ => ...` arm to handle every other value"),
+    )
+  }
+
+  fn eval_lambda(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    keyword: Token,
+    name: Option<Token>,
+    params: Vec<Expr>,
+    body: Stmt,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let params_names = params
+      .into_iter()
+      .map(|expr| match expr {
+        Expr::Identifier(token) => Ok(token),
+        _ => Err(InterpreterError::RuntimeError),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let Stmt::Block(body) = body else {
+      return Err(InterpreterError::RuntimeError);
+    };
+
+    // A named function closes over a frame holding just its name, matching the resolver's scope
+    let closure = match &name {
+      Some(_) => Rc::new(RefCell::new(Env::new().with_enclosing(env.clone()))),
+      None => env.clone(),
+    };
+
+    let function = Arc::new(LoxFunction {
+      params: params_names,
+      body: *body,
+      closure: closure.clone(),
+      is_initializer: false,
+    });
+
+    if let Some(name) = name {
+      closure
+        .borrow_mut()
+        .define(name.lexeme, LoxValue::Function(function.clone()));
     }
+
+    Ok((LoxValue::Function(function), Some(keyword)))
+  }
+
+  fn eval_array(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    bracket: Token,
+    elements: Vec<Expr>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let mut values = Vec::with_capacity(elements.len());
+    for element in elements {
+      let (value, _) = self.eval_expr(element, env, engine)?;
+      values.push(value);
+    }
+
+    Ok((
+      LoxValue::Array(Rc::new(RefCell::new(values))),
+      Some(bracket),
+    ))
+  }
+
+  fn eval_template(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    token: Token,
+    parts: Vec<String>,
+    expressions: Vec<Expr>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let mut result = String::new();
+    let mut expressions = expressions.into_iter();
+
+    for part in parts {
+      result.push_str(&part);
+      if let Some(expr) = expressions.next() {
+        let (value, _) = self.eval_expr(expr, env, engine)?;
+        result.push_str(&value.to_string());
+      }
+    }
+
+    Ok((LoxValue::String(result), Some(token)))
   }
 
   fn eval_super_expr(
@@ -498,7 +768,7 @@ impl Interpreter {
     // The Resolver guaranteed this is in `self.locals`.
     let &distance = self
       .locals
-      .get(&keyword.lexeme)
+      .get(&UseSite::of(&keyword))
       .ok_or(InterpreterError::RuntimeError)?; // Should not fail if resolved
 
     // 1. Look up "super" (the superclass object) at the resolved distance.
@@ -560,6 +830,26 @@ impl Interpreter {
       return Err(InterpreterError::RuntimeError);
     }
 
+    if let LoxValue::Array(array) = object_val {
+      return match name.lexeme.as_str() {
+        "sort" => Ok((
+          LoxValue::NativeFunction(Arc::new(ArraySortMethod {
+            array,
+            name: name.clone(),
+          })),
+          Some(name),
+        )),
+        _ => self.emit_error(
+          engine,
+          DiagnosticCode::TypeMismatch,
+          &format!("Undefined array method '{}'", name.lexeme),
+          &name,
+          "arrays have no such method",
+          Some("Arrays support: sort"),
+        ),
+      };
+    }
+
     if let LoxValue::Instance(instance) = object_val {
       if let Some(field) = instance.borrow().fields.get(&name.lexeme) {
         return Ok((field.clone(), Some(name)));
@@ -581,8 +871,21 @@ impl Interpreter {
       return Err(InterpreterError::RuntimeError);
     }
 
-    eprintln!("Cannot read property '{}' of non-instance", name.lexeme);
-    return Err(InterpreterError::RuntimeError);
+    self.emit_error_at(
+      engine,
+      DiagnosticCode::TypeMismatch,
+      &format!(
+        "Only instances have properties, found {}",
+        object_val.type_name()
+      ),
+      name.source_span(),
+      &format!(
+        "a {} has no property '{}'",
+        object_val.type_name(),
+        name.lexeme
+      ),
+      None,
+    )
   }
 
   fn eval_set(
@@ -607,8 +910,21 @@ impl Interpreter {
       return Ok((value_result, Some(name)));
     }
 
-    eprintln!("Only instances have fields");
-    Err(InterpreterError::RuntimeError)
+    self.emit_error_at(
+      engine,
+      DiagnosticCode::TypeMismatch,
+      &format!(
+        "Only instances have fields, found {}",
+        object_val.type_name()
+      ),
+      name.source_span(),
+      &format!(
+        "can't set '{}' on a {}",
+        name.lexeme,
+        object_val.type_name()
+      ),
+      None,
+    )
   }
 
   fn eval_call(
@@ -648,7 +964,7 @@ impl Interpreter {
           ))
           .with_label(Label::secondary(
             Span {
-              length: (args_space + 2 as usize),
+              length: (args_space + 2_usize),
               column: token.position.1 + 1,
               ..token.to_span()
             },
@@ -659,8 +975,10 @@ impl Interpreter {
           return Err(InterpreterError::RuntimeError);
         }
 
-        let result = fnc.call(self, args_val, engine)?;
-        return Ok((result, Some(paren)));
+        let result = self.nested_call(&paren, engine, |interpreter, engine| {
+          fnc.call(interpreter, args_val, engine)
+        })?;
+        Ok((result, Some(paren)))
       },
       LoxValue::NativeFunction(fnc) => {
         if fnc.arity() != usize::MAX && args_val.len() != fnc.arity() {
@@ -668,7 +986,7 @@ impl Interpreter {
         }
 
         let result = fnc.call(self, args_val, engine)?;
-        return Ok((result, Some(paren)));
+        Ok((result, Some(paren)))
       },
       LoxValue::Class(class) => {
         // Check arity
@@ -694,9 +1012,11 @@ impl Interpreter {
         }
 
         // Call the class (which handles init() internally)
-        let result = class.call(self, args_val, engine)?;
+        let result = self.nested_call(&paren, engine, |interpreter, engine| {
+          class.call(interpreter, args_val, engine)
+        })?;
 
-        return Ok((result, Some(paren)));
+        Ok((result, Some(paren)))
       },
       _ => Err(InterpreterError::RuntimeError),
     }
@@ -717,14 +1037,39 @@ impl Interpreter {
     Ok(args_val)
   }
 
+  /// Function that reads a resolved identifier through the slot cached for its position, the
+  /// slot is looked up by name and cached the first time, `None` sends the caller down the
+  /// lookup by name.
+  fn get_cached(&self, token: &Token, depth: usize, env: &Rc<RefCell<Env>>) -> Option<LoxValue> {
+    let env = env.borrow();
+    let site = UseSite::of(token);
+    let cached = self.slot_cache.borrow().get(&site).copied();
+
+    if let Some(slot) = cached {
+      if let Some(v) = env.get_slot_at(depth, slot, &token.lexeme) {
+        return Some(v);
+      }
+    }
+
+    let slot = env.slot_at(depth, &token.lexeme)?;
+    self.slot_cache.borrow_mut().insert(site, slot);
+    env.get_slot_at(depth, slot, &token.lexeme)
+  }
+
   fn eval_identifier(
     &self,
     mut token: Token,
     env: &mut Rc<RefCell<Env>>,
     engine: &mut DiagnosticEngine,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
-    if let Some(&depth) = self.locals.get(&token.lexeme) {
-      match env.borrow_mut().get_at(depth, &token.lexeme.as_str()) {
+    if let Some(&depth) = self.locals.get(&UseSite::of(&token)) {
+      if self.cache_lookups {
+        if let Some(v) = self.get_cached(&token, depth, env) {
+          return Ok((v, Some(token)));
+        }
+      }
+
+      match env.borrow_mut().get_at(depth, token.lexeme.as_str()) {
         Some(v) => return Ok((v.clone(), Some(token))),
         None => {
           eprintln!(
@@ -767,7 +1112,7 @@ impl Interpreter {
     let (value, token) = self.eval_expr(value, env, engine)?;
 
     // Check if we have a resolved depth
-    if let Some(&depth) = self.locals.get(&name.lexeme) {
+    if let Some(&depth) = self.locals.get(&UseSite::of(&name)) {
       if env
         .borrow_mut()
         .assign_at(depth, &name.lexeme, value.clone())
@@ -824,9 +1169,10 @@ impl Interpreter {
     match operator.lexeme.as_str() {
       "%" | "*" | "/" | "-" => self.eval_arithmetic(env, operator, lhs, rhs, engine),
       "+" => self.eval_addition(env, operator, lhs, rhs, engine),
+      "**" => self.eval_power(env, operator, lhs, rhs, engine),
+      "&" | "|" | "^" | "<<" | ">>" => self.eval_bitwise(env, operator, lhs, rhs, engine),
       "==" | "!=" => self.eval_equality(env, operator, lhs, rhs, engine),
       ">" | ">=" | "<" | "<=" => self.eval_comparison(env, operator, lhs, rhs, engine),
-      "||" | "&&" => self.eval_logical(env, operator, lhs, rhs, engine),
       "," => Err(InterpreterError::RuntimeError),
       _ => self.emit_error(
         engine,
@@ -834,7 +1180,7 @@ impl Interpreter {
         &format!("Unknown binary operator '{}'", operator.lexeme),
         &operator,
         "This operator is not supported",
-        Some("Valid operators are: +, -, %, *, /, ==, !=, <, <=, >, >="),
+        Some("Valid operators are: +, -, %, *, /, **, &, |, ^, <<, >>, ==, !=, <, <=, >, >="),
       ),
     }
   }
@@ -851,8 +1197,8 @@ impl Interpreter {
 
     let is_truthy = self.is_truthy(&lhs_val);
 
-    match operator.lexeme.as_str() {
-      "||" => {
+    match operator.token_type {
+      TokenType::Or => {
         // short-circuit: if lhs is truthy, return it
         if is_truthy {
           Ok((lhs_val, lhs_token))
@@ -860,7 +1206,7 @@ impl Interpreter {
           self.eval_expr(rhs, env, engine)
         }
       },
-      "&&" => {
+      TokenType::And => {
         // short-circuit: if lhs is falsy, return it
         if !is_truthy {
           Ok((lhs_val, lhs_token))
@@ -872,6 +1218,125 @@ impl Interpreter {
     }
   }
 
+  fn eval_power(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    operator: Token,
+    lhs: Expr,
+    rhs: Expr,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let (lhs_val, lhs_token) = self.eval_expr(lhs, env, engine)?;
+    let (rhs_val, rhs_token) = self.eval_expr(rhs, env, engine)?;
+
+    match (&lhs_val, &rhs_val) {
+      (LoxValue::Number(a), LoxValue::Number(b)) => {
+        Ok((LoxValue::Number(a.powf(*b)), Some(operator)))
+      },
+      (lhs, rhs) => self.emit_error_at(
+        engine,
+        DiagnosticCode::InvalidOperator,
+        &format!(
+          "Cannot raise {} to the power of {}",
+          lhs.type_name(),
+          rhs.type_name()
+        ),
+        Self::operands_span(lhs_token.as_ref(), &operator, rhs_token.as_ref()),
+        "Both operands must be numbers",
+        None,
+      ),
+    }
+  }
+
+  /// Function that evaluates the bitwise operators, the operands have to be numbers holding an
+  /// integer and are worked on as `i64`
+  fn eval_bitwise(
+    &mut self,
+    env: &mut Rc<RefCell<Env>>,
+    operator: Token,
+    lhs: Expr,
+    rhs: Expr,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    let (lhs_val, lhs_token) = self.eval_expr(lhs, env, engine)?;
+    let (rhs_val, rhs_token) = self.eval_expr(rhs, env, engine)?;
+
+    let (a, b) = match (Self::as_integer(&lhs_val), Self::as_integer(&rhs_val)) {
+      (Some(a), Some(b)) => (a, b),
+      (a, _) => {
+        let (bad_token, bad_value) = if a.is_none() {
+          (lhs_token, &lhs_val)
+        } else {
+          (rhs_token, &rhs_val)
+        };
+
+        // A whole number only gets here when it doesn't fit in an `i64`
+        let help = if matches!(bad_value, LoxValue::Number(n) if n.fract() == 0.0) {
+          "Bitwise operands must fit in a 64-bit integer"
+        } else {
+          "Use `floor(...)` to drop the fractional part first"
+        };
+
+        return self.emit_error_at(
+          engine,
+          DiagnosticCode::TypeMismatch,
+          &format!(
+            "Operator '{}' expects integers, found {} {}",
+            operator.lexeme,
+            bad_value.type_name(),
+            bad_value
+          ),
+          Self::operands_span(bad_token.as_ref(), &operator, None),
+          "Both operands must be whole numbers",
+          Some(help),
+        );
+      },
+    };
+
+    let result = match operator.token_type {
+      TokenType::Ampersand => a & b,
+      TokenType::Pipe => a | b,
+      TokenType::Caret => a ^ b,
+      TokenType::LessLess | TokenType::GreaterGreater => {
+        let shifted = u32::try_from(b).ok().and_then(|b| {
+          if operator.token_type == TokenType::LessLess {
+            a.checked_shl(b)
+          } else {
+            a.checked_shr(b)
+          }
+        });
+
+        match shifted {
+          Some(shifted) => shifted,
+          None => {
+            return self.emit_error_at(
+              engine,
+              DiagnosticCode::InvalidOperator,
+              &format!("Cannot shift by {}", b),
+              Self::operands_span(None, &operator, rhs_token.as_ref()),
+              "The shift amount must be between 0 and 63",
+              None,
+            );
+          },
+        }
+      },
+      _ => unreachable!(),
+    };
+
+    Ok((LoxValue::Number(result as f64), Some(operator)))
+  }
+
+  /// Function that returns the integer a number holds, nothing for fractions, numbers outside the
+  /// `i64` range and non-numbers
+  fn as_integer(value: &LoxValue) -> Option<i64> {
+    // `i64::MIN` is a power of two so it converts exactly, `-i64::MIN` is the first one past the end
+    let range = i64::MIN as f64..-(i64::MIN as f64);
+    match value {
+      LoxValue::Number(n) if n.fract() == 0.0 && range.contains(n) => Some(*n as i64),
+      _ => None,
+    }
+  }
+
   fn eval_arithmetic(
     &mut self,
     env: &mut Rc<RefCell<Env>>,
@@ -919,11 +1384,11 @@ impl Interpreter {
           engine,
           &operator,
           bad_token.as_ref(),
-          &format!("Arithmetic operations require numeric operands"),
+          "Arithmetic operations require numeric operands",
           &format!("Expected number, found {}", &bad_value.to_string()),
         )
       },
-      (lhs, rhs) => self.emit_error(
+      (lhs, rhs) => self.emit_error_at(
         engine,
         DiagnosticCode::InvalidOperator,
         &format!(
@@ -931,7 +1396,7 @@ impl Interpreter {
           &lhs.to_string(),
           &rhs.to_string()
         ),
-        &operator,
+        Self::operands_span(lhs_token.as_ref(), &operator, rhs_token.as_ref()),
         "Both operands must be numbers",
         Some(&format!(
           "Left operand is {}, right operand is {}",
@@ -950,8 +1415,8 @@ impl Interpreter {
     rhs: Expr,
     engine: &mut DiagnosticEngine,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
-    let (lhs_val, _) = self.eval_expr(lhs, env, engine)?;
-    let (rhs_val, _) = self.eval_expr(rhs, env, engine)?;
+    let (lhs_val, lhs_token) = self.eval_expr(lhs, env, engine)?;
+    let (rhs_val, rhs_token) = self.eval_expr(rhs, env, engine)?;
 
     match (lhs_val, rhs_val) {
       (LoxValue::Number(a), LoxValue::Number(b)) => Ok((LoxValue::Number(a + b), Some(operator))),
@@ -959,18 +1424,33 @@ impl Interpreter {
         Ok((LoxValue::String(format!("{}{}", a, b)), Some(operator)))
       },
       (LoxValue::String(a), LoxValue::Number(b)) => {
+        Self::warn_implicit_conversion(engine, &operator);
         Ok((LoxValue::String(format!("{}{}", a, b)), Some(operator)))
       },
       (LoxValue::Number(a), LoxValue::String(b)) => {
+        Self::warn_implicit_conversion(engine, &operator);
         Ok((LoxValue::String(format!("{}{}", a, b)), Some(operator)))
       },
-      (lhs, rhs) => self.emit_error(
+      (LoxValue::Array(a), LoxValue::Array(b)) => {
+        // Concatenating builds a new array, the operands are left as they were
+        let elements = a
+          .borrow()
+          .iter()
+          .chain(b.borrow().iter())
+          .cloned()
+          .collect();
+        Ok((
+          LoxValue::Array(Rc::new(RefCell::new(elements))),
+          Some(operator),
+        ))
+      },
+      (lhs, rhs) => self.emit_error_at(
         engine,
         DiagnosticCode::InvalidOperator,
         &format!("Cannot add {} and {}", &lhs.to_string(), &rhs.to_string()),
-        &operator,
-        "Operands must be two numbers or at least one string",
-        Some(&format!("Try converting both operands to the same type")),
+        Self::operands_span(lhs_token.as_ref(), &operator, rhs_token.as_ref()),
+        "Operands must be two numbers, two arrays or at least one string",
+        Some("Try converting both operands to the same type"),
       ),
     }
   }
@@ -1002,8 +1482,8 @@ impl Interpreter {
     rhs: Expr,
     engine: &mut DiagnosticEngine,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
-    let (lhs_val, _) = self.eval_expr(lhs, env, engine)?;
-    let (rhs_val, _) = self.eval_expr(rhs, env, engine)?;
+    let (lhs_val, lhs_token) = self.eval_expr(lhs, env, engine)?;
+    let (rhs_val, rhs_token) = self.eval_expr(rhs, env, engine)?;
 
     match (lhs_val, rhs_val) {
       (LoxValue::Number(a), LoxValue::Number(b)) => {
@@ -1016,7 +1496,7 @@ impl Interpreter {
         };
         Ok((LoxValue::Bool(result), Some(operator)))
       },
-      (lhs, rhs) => self.emit_error(
+      (lhs, rhs) => self.emit_error_at(
         engine,
         DiagnosticCode::InvalidOperator,
         &format!(
@@ -1024,9 +1504,9 @@ impl Interpreter {
           &lhs.to_string(),
           &rhs.to_string()
         ),
-        &operator,
+        Self::operands_span(lhs_token.as_ref(), &operator, rhs_token.as_ref()),
         "Comparison operators require numeric operands",
-        Some(&format!("Both operands must be numbers for comparison")),
+        Some("Both operands must be numbers for comparison"),
       ),
     }
   }
@@ -1045,6 +1525,10 @@ impl Interpreter {
         let is_truthy = self.is_truthy(&rhs_val);
         Ok((LoxValue::Bool(!is_truthy), Some(operator)))
       },
+      "typeof" => Ok((
+        LoxValue::String(rhs_val.type_name().to_string()),
+        Some(operator),
+      )),
       "-" => match rhs_val {
         LoxValue::Number(n) => Ok((LoxValue::Number(-n), Some(operator))),
         _ => self.emit_type_error(
@@ -1061,7 +1545,7 @@ impl Interpreter {
         &format!("Unknown unary operator '{}'", operator.lexeme),
         &operator,
         "This operator is not supported as a unary operator",
-        Some("Valid unary operators are: !, -"),
+        Some("Valid unary operators are: !, -, typeof"),
       ),
     }
   }
@@ -1105,10 +1589,56 @@ impl Interpreter {
       (LoxValue::Number(a), LoxValue::Number(b)) => a == b,
       (LoxValue::String(a), LoxValue::String(b)) => a == b,
       (LoxValue::Bool(a), LoxValue::Bool(b)) => a == b,
-      _ => false,
+      // Functions, classes, instances and arrays are equal only to themselves
+      (LoxValue::Function(a), LoxValue::Function(b)) => Arc::ptr_eq(a, b),
+      (LoxValue::NativeFunction(a), LoxValue::NativeFunction(b)) => Arc::ptr_eq(a, b),
+      (LoxValue::Class(a), LoxValue::Class(b)) => Arc::ptr_eq(a, b),
+      (LoxValue::Instance(a), LoxValue::Instance(b)) => Rc::ptr_eq(a, b),
+      (LoxValue::Array(a), LoxValue::Array(b)) => Rc::ptr_eq(a, b),
+      // Values of different types are never equal, there's no coercion like `1 == "1"`. Every
+      // variant is listed so a new one has to decide how it compares.
+      (
+        LoxValue::Nil
+        | LoxValue::Number(_)
+        | LoxValue::String(_)
+        | LoxValue::Bool(_)
+        | LoxValue::Function(_)
+        | LoxValue::NativeFunction(_)
+        | LoxValue::Class(_)
+        | LoxValue::Instance(_)
+        | LoxValue::Array(_),
+        _,
+      ) => false,
     }
   }
 
+  /// Function that returns the span of a whole `lhs op rhs` expression, from the tokens its
+  /// operands evaluated from, an operand without one leaves its side at the operator
+  fn operands_span(lhs: Option<&Token>, operator: &Token, rhs: Option<&Token>) -> Span {
+    [lhs, rhs]
+      .into_iter()
+      .flatten()
+      .fold(operator.source_span(), |span, token| {
+        span.merge(&token.source_span())
+      })
+  }
+
+  /// Function that warns about a `+` turning its number operand into a string, the
+  /// concatenation still goes ahead
+  fn warn_implicit_conversion(engine: &mut DiagnosticEngine, operator: &Token) {
+    engine.emit(
+      Diagnostic::new(
+        DiagnosticCode::ImplicitConversion,
+        "Number implicitly converted to a string".to_string(),
+      )
+      .with_label(Label::primary(
+        operator.source_span(),
+        Some("this concatenates a number with a string".to_string()),
+      ))
+      .with_note("wrap the number in `str(...)` to make the conversion explicit".to_string()),
+    );
+  }
+
   fn emit_error(
     &self,
     engine: &mut DiagnosticEngine,
@@ -1117,9 +1647,50 @@ impl Interpreter {
     token: &Token,
     label_msg: &str,
     help: Option<&str>,
+  ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
+    self.emit_error_at(engine, code, message, token.source_span(), label_msg, help)
+  }
+
+  /// Function that runs a call one level deeper, failing with a diagnostic on the call once
+  /// `max_call_depth` calls are already running
+  fn nested_call(
+    &mut self,
+    paren: &Token,
+    engine: &mut DiagnosticEngine,
+    call: impl FnOnce(&mut Self, &mut DiagnosticEngine) -> Result<LoxValue, InterpreterError>,
+  ) -> Result<LoxValue, InterpreterError> {
+    if self.call_depth >= self.max_call_depth {
+      self.emit_error_at(
+        engine,
+        DiagnosticCode::MaxRecursionDepth,
+        &format!(
+          "Maximum recursion depth of {} exceeded",
+          self.max_call_depth
+        ),
+        paren.source_span(),
+        "this call is nested too deep",
+        Some("check that the recursion has a base case that is reached"),
+      )?;
+    }
+
+    self.call_depth += 1;
+    let result = call(self, engine);
+    self.call_depth -= 1;
+    result
+  }
+
+  /// Function that emits an error with its primary label on `span` instead of a single token
+  fn emit_error_at(
+    &self,
+    engine: &mut DiagnosticEngine,
+    code: DiagnosticCode,
+    message: &str,
+    span: Span,
+    label_msg: &str,
+    help: Option<&str>,
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
     let mut diagnostic = Diagnostic::new(code, message.to_string())
-      .with_label(Label::primary(token.to_span(), Some(label_msg.to_string())));
+      .with_label(Label::primary(span, Some(label_msg.to_string())));
 
     if let Some(help_msg) = help {
       diagnostic = diagnostic.with_help(help_msg.to_string());
@@ -1139,13 +1710,14 @@ impl Interpreter {
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
     let mut diagnostic = Diagnostic::new(DiagnosticCode::TypeError, message.to_string())
       .with_label(Label::primary(
-        operator.to_span(),
+        operator.source_span(),
         Some("operation here".to_string()),
       ));
 
+    // The source span covers a string operand with its quotes, as it's written
     if let Some(token) = operand_token {
       diagnostic = diagnostic.with_label(Label::secondary(
-        token.to_span(),
+        token.source_span(),
         Some(label_msg.to_string()),
       ));
     }
@@ -1154,6 +1726,7 @@ impl Interpreter {
     Err(InterpreterError::RuntimeError)
   }
 
+  #[allow(clippy::too_many_arguments)]
   fn emit_error_with_note(
     &self,
     engine: &mut DiagnosticEngine,
@@ -1167,14 +1740,14 @@ impl Interpreter {
   ) -> Result<(LoxValue, Option<Token>), InterpreterError> {
     let mut diagnostic = Diagnostic::new(code, message.to_string())
       .with_label(Label::primary(
-        primary_token.to_span(),
+        primary_token.source_span(),
         Some(primary_label.to_string()),
       ))
       .with_help(help.to_string());
 
     if let Some(token) = note_token {
       diagnostic = diagnostic.with_label(Label::secondary(
-        token.to_span(),
+        token.source_span(),
         Some(note_label.to_string()),
       ));
     }
@@ -1183,16 +1756,10 @@ impl Interpreter {
     Err(InterpreterError::RuntimeError)
   }
 
+  /// Function that decides the truthiness of a value for every condition, `if`, `while`, `do`,
+  /// `for`, `?:`, `!`, `and` and `or` alike. Like the reference Lox only `nil` and `false` are
+  /// falsy, `0` and `""` are truthy.
   fn is_truthy(&self, val: &LoxValue) -> bool {
-    return match &val {
-      LoxValue::Bool(b) => *b,
-      LoxValue::Nil => false,
-      LoxValue::Number(n) => *n != 0.0,
-      LoxValue::String(s) => !s.is_empty(),
-      LoxValue::Function(_) => false,
-      LoxValue::NativeFunction(_) => false,
-      LoxValue::Class(_) => false,
-      LoxValue::Instance(_) => false,
-    };
+    !matches!(val, LoxValue::Nil | LoxValue::Bool(false))
   }
 }
@@ -0,0 +1,14 @@
+// NOTE: The interpreter is single threaded, the `Arc`s around functions and classes are only
+// shared handles and never cross a thread boundary.
+#![allow(clippy::arc_with_non_send_sync)]
+
+pub mod class;
+pub mod env;
+pub mod error;
+pub mod function;
+pub mod input;
+pub mod interpreter;
+pub mod lox_value;
+pub mod metrics;
+pub mod output;
+pub mod runner;
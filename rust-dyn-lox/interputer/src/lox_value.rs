@@ -1,16 +1,36 @@
-use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc, sync::Arc};
+use std::{cell::RefCell, cmp::Ordering, fmt, rc::Rc, sync::Arc};
+
+use scanner::token::Token;
 
 use crate::{
   class::{LoxClass, LoxClassInstance},
   function::{normal::LoxFunction, LoxCallable},
 };
 
+/// How many levels of nested arrays are rendered before the rest shows as `[...]`
+pub const DEFAULT_MAX_DISPLAY_DEPTH: usize = 8;
+/// How many elements of an array are rendered before the rest shows as `, ...`
+pub const DEFAULT_MAX_DISPLAY_ELEMENTS: usize = 100;
+
 #[derive(Debug)]
 pub enum InterpreterError {
   Return(LoxValue),
   RuntimeError,
-  Break,
-  Continue,
+  /// The label of the loop to leave, the innermost loop when there's none
+  Break(Option<String>),
+  Continue(Option<String>),
+}
+
+impl InterpreterError {
+  /// Function that tells if a `break` or `continue` is aimed at the loop with the given label
+  pub fn targets(label: &Option<String>, loop_label: &Option<Token>) -> bool {
+    match label {
+      None => true,
+      Some(label) => loop_label
+        .as_ref()
+        .is_some_and(|loop_label| &loop_label.lexeme == label),
+    }
+  }
 }
 
 #[derive(Clone)]
@@ -20,9 +40,10 @@ pub enum LoxValue {
   String(String),
   Bool(bool),
   Function(Arc<LoxFunction>),
-  NativeFunction(Arc<dyn LoxCallable + Send + Sync>),
+  NativeFunction(Arc<dyn LoxCallable>),
   Class(Arc<LoxClass>),
   Instance(Rc<RefCell<LoxClassInstance>>),
+  Array(Rc<RefCell<Vec<LoxValue>>>),
 }
 
 impl fmt::Debug for LoxValue {
@@ -36,6 +57,7 @@ impl fmt::Debug for LoxValue {
       LoxValue::NativeFunction(_) => write!(f, "NativeFunction(<native>)"),
       LoxValue::Class(c) => write!(f, "Class({})", c.name),
       LoxValue::Instance(i) => write!(f, "Instance({})", i.borrow().class.name),
+      LoxValue::Array(a) => write!(f, "Array({:?})", a.borrow()),
     }
   }
 }
@@ -51,6 +73,100 @@ impl fmt::Display for LoxValue {
       LoxValue::NativeFunction(_) => write!(f, "<native function>"),
       LoxValue::Class(c) => write!(f, "{c:?}"),
       LoxValue::Instance(i) => write!(f, "{i:?}"),
+      LoxValue::Array(_) => {
+        self.write_limited(f, DEFAULT_MAX_DISPLAY_DEPTH, DEFAULT_MAX_DISPLAY_ELEMENTS)
+      },
+    }
+  }
+}
+
+impl LoxValue {
+  /// Function that renders the value like `Display` but with its own limits for nested and long
+  /// arrays
+  pub fn display_limited(&self, max_depth: usize, max_elements: usize) -> String {
+    let mut out = String::new();
+    // Writing into a `String` never fails
+    let _ = self.write_limited(&mut out, max_depth, max_elements);
+    out
+  }
+
+  /// Function that writes the value, arrays nested deeper than `max_depth` render as `[...]` and
+  /// the elements past `max_elements` as `, ...`. The depth is bounded so the recursion is too.
+  fn write_limited(
+    &self,
+    out: &mut impl fmt::Write,
+    max_depth: usize,
+    max_elements: usize,
+  ) -> fmt::Result {
+    let LoxValue::Array(array) = self else {
+      return write!(out, "{}", self);
+    };
+
+    if max_depth == 0 {
+      return write!(out, "[...]");
+    }
+
+    let array = array.borrow();
+    write!(out, "[")?;
+    for (i, value) in array.iter().take(max_elements).enumerate() {
+      if i > 0 {
+        write!(out, ", ")?;
+      }
+      match value {
+        LoxValue::String(s) => write!(out, "{s:?}")?,
+        value => value.write_limited(out, max_depth - 1, max_elements)?,
+      }
+    }
+    if array.len() > max_elements {
+      write!(out, ", ...")?;
+    }
+    write!(out, "]")
+  }
+
+  /// Function that returns the name of the value type used in the diagnostics
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      LoxValue::Nil => "nil",
+      LoxValue::Number(_) => "number",
+      LoxValue::String(_) => "string",
+      LoxValue::Bool(_) => "bool",
+      LoxValue::Function(_) | LoxValue::NativeFunction(_) => "function",
+      LoxValue::Class(_) => "class",
+      LoxValue::Instance(_) => "instance",
+      LoxValue::Array(_) => "array",
+    }
+  }
+
+  /// Function that orders two values, numbers by value (`NaN` after every number), strings
+  /// lexicographically, bools `false` first and arrays element by element. Values of different
+  /// types are ordered by their type so the result is always stable.
+  pub fn compare(&self, other: &LoxValue) -> Ordering {
+    fn rank(value: &LoxValue) -> u8 {
+      match value {
+        LoxValue::Nil => 0,
+        LoxValue::Bool(_) => 1,
+        LoxValue::Number(_) => 2,
+        LoxValue::String(_) => 3,
+        LoxValue::Array(_) => 4,
+        LoxValue::Function(_) | LoxValue::NativeFunction(_) => 5,
+        LoxValue::Class(_) => 6,
+        LoxValue::Instance(_) => 7,
+      }
+    }
+
+    match (self, other) {
+      (LoxValue::Number(a), LoxValue::Number(b)) => a.total_cmp(b),
+      (LoxValue::String(a), LoxValue::String(b)) => a.cmp(b),
+      (LoxValue::Bool(a), LoxValue::Bool(b)) => a.cmp(b),
+      (LoxValue::Array(a), LoxValue::Array(b)) => {
+        let (a, b) = (a.borrow(), b.borrow());
+        a.iter()
+          .zip(b.iter())
+          .map(|(a, b)| a.compare(b))
+          .find(|ordering| *ordering != Ordering::Equal)
+          .unwrap_or_else(|| a.len().cmp(&b.len()))
+      },
+      _ => rank(self).cmp(&rank(other)),
     }
   }
 }
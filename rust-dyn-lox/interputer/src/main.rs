@@ -1,48 +1,64 @@
 use colored::*;
+use compiler::runner::Runner;
 use diagnostic::{diagnostic::Diagnostic, diagnostic_code::DiagnosticCode, DiagnosticEngine};
-use runner::Runner;
 
-mod class;
-mod env;
-mod error;
-mod function;
-mod interpreter;
-mod lox_value;
-mod runner;
+const USAGE: &str =
+  "Usage: lox [--dump-ast] [--dump-tokens-json] [--emit-metrics] [--interactive-paste] [--lenient-eof] [--time] [--warn-uninit] [--warn-mixed-indent] [--sandbox] [--no-native-print] [script...]";
+
+/// Stack of the thread running the program, deep enough for `DEFAULT_MAX_CALL_DEPTH` nested
+/// calls even in a debug build, so deep recursion ends with a diagnostic and not an abort
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-  let args: Vec<String> = std::env::args().collect();
+  let runner = std::thread::Builder::new()
+    .stack_size(STACK_SIZE)
+    .spawn(run)
+    .expect("failed to spawn the interpreter thread");
+
+  if runner.join().is_err() {
+    std::process::exit(70);
+  }
+}
+
+fn run() {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let (flags, paths): (Vec<String>, Vec<String>) =
+    args.into_iter().partition(|arg| arg.starts_with("--"));
 
   let mut diagnostic = DiagnosticEngine::new();
   let mut compiler = Runner::new();
 
-  match args.len() {
-    1 => {
-      // Info message for interactive mode
-      println!("{}", "Running the interactive mode".cyan().bold());
-      compiler.run_interactive_mode(&mut diagnostic);
-    },
-    2 => {
-      // Info message for file mode
-      println!("{}", format!("Running file: {}", args[1]).cyan().bold());
-      compiler.run_file(args[1].clone(), &mut diagnostic);
-
-      // Check if compilation had errors
-      if diagnostic.has_errors() {
-        std::process::exit(65);
-      }
-    },
-    _ => {
-      // Error: Invalid arguments
-      let error = Diagnostic::new(
-        DiagnosticCode::InvalidArguments,
-        "invalid number of arguments".to_string(),
-      )
-      .with_help("Usage: lox [script]".to_string());
-
-      diagnostic.emit(error);
-      diagnostic.print_all("");
-      std::process::exit(64);
-    },
+  for flag in flags {
+    match flag.as_str() {
+      "--dump-ast" => compiler.dump_ast = true,
+      "--dump-tokens-json" => compiler.dump_tokens_json = true,
+      "--emit-metrics" => compiler.emit_metrics = true,
+      "--interactive-paste" => compiler.interactive_paste = true,
+      "--lenient-eof" => compiler.lenient_eof = true,
+      "--time" => compiler.time_phases = true,
+      "--warn-uninit" => compiler.warn_uninit = true,
+      "--warn-mixed-indent" => compiler.warn_mixed_indent = true,
+      "--sandbox" => compiler.sandbox = true,
+      "--no-native-print" => compiler.native_print = false,
+      _ => {
+        let error = Diagnostic::new(
+          DiagnosticCode::InvalidArguments,
+          format!("unknown flag: {}", flag),
+        )
+        .with_help(USAGE.to_string());
+
+        diagnostic.emit(error);
+        diagnostic.print_all("");
+        std::process::exit(64);
+      },
+    }
+  }
+
+  if paths.is_empty() {
+    // Info message for interactive mode
+    println!("{}", "Running the interactive mode".cyan().bold());
+    compiler.run_interactive_mode(&mut diagnostic);
+  } else if let Err(error) = compiler.run_files(&paths, &mut diagnostic) {
+    std::process::exit(error.exit_code());
   }
 }
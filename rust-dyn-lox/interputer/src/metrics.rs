@@ -0,0 +1,175 @@
+use std::fmt;
+
+use parser::{expr::Expr, stmt::Stmt};
+use scanner::token::{types::TokenType, Token};
+
+/// Size of a parsed program, reported by `--emit-metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+  /// Scanned tokens without the trailing `Eof`
+  pub tokens: usize,
+  /// Every statement of the tree, nested ones included, a `for` counts as the block and the
+  /// `while` it desugars to
+  pub statements: usize,
+  /// Deepest expression tree, a literal or an identifier on its own is `1`
+  pub max_expr_depth: usize,
+  /// Function declarations, methods included
+  pub functions: usize,
+  /// Class declarations
+  pub classes: usize,
+}
+
+impl Metrics {
+  /// Function that counts the metrics of the scanned tokens and the parsed tree.
+  pub fn collect(tokens: &[Token], ast: &[Stmt]) -> Self {
+    let mut metrics = Metrics {
+      tokens: tokens
+        .iter()
+        .filter(|token| token.token_type != TokenType::Eof)
+        .count(),
+      ..Default::default()
+    };
+
+    for stmt in ast {
+      metrics.visit_stmt(stmt);
+    }
+
+    metrics
+  }
+
+  fn visit_stmt(&mut self, stmt: &Stmt) {
+    self.statements += 1;
+
+    match stmt {
+      Stmt::Expr(expr) => self.visit_expr(expr),
+      Stmt::VarDecl(_, value) | Stmt::Return(_, value) => {
+        if let Some(value) = value {
+          self.visit_expr(value);
+        }
+      },
+      Stmt::Block(stmts) => stmts.iter().for_each(|stmt| self.visit_stmt(stmt)),
+      Stmt::If(condition, then_branch, else_branch) => {
+        self.visit_expr(condition);
+        self.visit_stmt(then_branch);
+        if let Some(else_branch) = else_branch {
+          self.visit_stmt(else_branch);
+        }
+      },
+      Stmt::While(condition, body, increment, _) => {
+        self.visit_expr(condition);
+        self.visit_stmt(body);
+        if let Some(increment) = increment {
+          self.visit_expr(increment);
+        }
+      },
+      Stmt::Switch(discriminant, cases) => {
+        self.visit_expr(discriminant);
+        for (value, stmts) in cases {
+          if let Some(value) = value {
+            self.visit_expr(value);
+          }
+          stmts.iter().for_each(|stmt| self.visit_stmt(stmt));
+        }
+      },
+      Stmt::DoWhile(body, condition, _) => {
+        self.visit_stmt(body);
+        self.visit_expr(condition);
+      },
+      Stmt::Fun(_, _, body) => {
+        self.functions += 1;
+        self.visit_stmt(body);
+      },
+      Stmt::Class(_, superclass, methods, static_methods) => {
+        self.classes += 1;
+        if let Some(superclass) = superclass {
+          self.visit_expr(superclass);
+        }
+        methods
+          .iter()
+          .chain(static_methods.iter())
+          .for_each(|method| self.visit_stmt(method));
+      },
+      Stmt::Break(..) | Stmt::Continue(..) => {},
+    }
+  }
+
+  fn visit_expr(&mut self, expr: &Expr) {
+    self.max_expr_depth = self.max_expr_depth.max(expr_depth(expr));
+    self.visit_lambdas(expr);
+  }
+
+  /// Function that counts the anonymous functions in an expression along with their bodies
+  fn visit_lambdas(&mut self, expr: &Expr) {
+    if let Expr::Lambda { body, .. } = expr {
+      self.functions += 1;
+      self.visit_stmt(body);
+    }
+    expr_children(expr)
+      .into_iter()
+      .for_each(|child| self.visit_lambdas(child));
+  }
+}
+
+/// Function that returns the height of an expression tree, an anonymous function body is made of
+/// statements and doesn't add to it.
+fn expr_depth(expr: &Expr) -> usize {
+  1 + expr_children(expr)
+    .into_iter()
+    .map(expr_depth)
+    .max()
+    .unwrap_or(0)
+}
+
+/// Function that returns the direct sub-expressions of an expression.
+fn expr_children(expr: &Expr) -> Vec<&Expr> {
+  match expr {
+    Expr::Literal(_)
+    | Expr::Identifier(_)
+    | Expr::This(_)
+    | Expr::Super(_, _)
+    | Expr::Lambda { .. } => vec![],
+    Expr::Unary { rhs, .. } => vec![rhs.as_ref()],
+    Expr::Binary { lhs, rhs, .. } | Expr::Logical { lhs, rhs, .. } => {
+      vec![lhs.as_ref(), rhs.as_ref()]
+    },
+    Expr::Assign { value, .. } => vec![value.as_ref()],
+    Expr::Ternary {
+      condition,
+      then_branch,
+      else_branch,
+    } => vec![
+      condition.as_ref(),
+      then_branch.as_ref(),
+      else_branch.as_ref(),
+    ],
+    Expr::Call {
+      callee, arguments, ..
+    } => std::iter::once(callee.as_ref())
+      .chain(arguments.iter())
+      .collect(),
+    Expr::Grouping(expr) => vec![expr.as_ref()],
+    Expr::Get { object, .. } => vec![object.as_ref()],
+    Expr::Set { object, value, .. } => vec![object.as_ref(), value.as_ref()],
+    Expr::Array { elements, .. } => elements.iter().collect(),
+    Expr::Template { expressions, .. } => expressions.iter().collect(),
+    Expr::Match {
+      scrutinee, arms, ..
+    } => std::iter::once(scrutinee.as_ref())
+      .chain(
+        arms
+          .iter()
+          .flat_map(|(pattern, value)| pattern.iter().chain(std::iter::once(value))),
+      )
+      .collect(),
+  }
+}
+
+impl fmt::Display for Metrics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "tokens: {}", self.tokens)?;
+    writeln!(f, "statements: {}", self.statements)?;
+    writeln!(f, "max expression depth: {}", self.max_expr_depth)?;
+    writeln!(f, "functions: {}", self.functions)?;
+    write!(f, "classes: {}", self.classes)
+  }
+}
@@ -0,0 +1,41 @@
+use std::{cell::RefCell, rc::Rc};
+
+/// Where the program output of the interpreter goes to.
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+  /// Write straight to the process stdout, used by the binary.
+  #[default]
+  Stdout,
+  /// Write straight to the process stderr, used by the binary for `eprint`.
+  Stderr,
+  /// Collect everything into a shared buffer, used for capturing the output of a run.
+  Buffer(Rc<RefCell<String>>),
+}
+
+impl Output {
+  /// Function that creates a sink backed by an empty buffer.
+  pub fn buffer() -> Self {
+    Output::Buffer(Rc::new(RefCell::new(String::new())))
+  }
+
+  /// Function that writes a line followed by a newline to the sink.
+  pub fn write_line(&self, line: &str) {
+    match self {
+      Output::Stdout => println!("{}", line),
+      Output::Stderr => eprintln!("{}", line),
+      Output::Buffer(buffer) => {
+        let mut buffer = buffer.borrow_mut();
+        buffer.push_str(line);
+        buffer.push('\n');
+      },
+    }
+  }
+
+  /// Function that returns everything written so far, always empty for `Stdout` and `Stderr`.
+  pub fn contents(&self) -> String {
+    match self {
+      Output::Stdout | Output::Stderr => String::new(),
+      Output::Buffer(buffer) => buffer.borrow().clone(),
+    }
+  }
+}
@@ -1,19 +1,117 @@
-use crate::interpreter::Interpreter;
+use crate::{
+  error::RunError,
+  input::Input,
+  interpreter::{Interpreter, DEFAULT_MAX_CALL_DEPTH},
+  metrics::Metrics,
+  output::Output,
+};
+use colored::Colorize;
 use diagnostic::{diagnostic::Diagnostic, diagnostic_code::DiagnosticCode, DiagnosticEngine};
 use parser::Parser;
-use scanner::Scanner;
-use semantic_analysis;
+use scanner::{
+  token::{types::TokenType, DEFAULT_FILE},
+  Scanner,
+};
 use std::{
   fs,
   io::{self, Write},
-  process,
+  time::{Duration, Instant},
 };
 
-pub struct Runner {}
+/// Path that makes `run_file` read the program from stdin, like `echo 'print(1);' | lox -`
+pub const STDIN_PATH: &str = "-";
+
+/// File name the diagnostics of a program read from stdin report
+pub const STDIN_FILE: &str = "<stdin>";
+
+pub struct Runner {
+  /// Print the parsed tree of every top-level statement and stop before interpreting
+  pub dump_ast: bool,
+  /// Print the scanned tokens as a JSON array and stop before parsing
+  pub dump_tokens_json: bool,
+  /// Print the size metrics of the parsed program and stop before interpreting
+  pub emit_metrics: bool,
+  /// Sink handed to the interpreter for the program output
+  pub output: Output,
+  /// Sink handed to the interpreter for what the program prints with `eprint`
+  pub error_output: Output,
+  /// Source handed to the interpreter for the `input` native
+  pub input: Input,
+  /// Accept a missing `;` after the last statement with a warning
+  pub lenient_eof: bool,
+  /// Warn about reading a `var` declared without an initializer before it's assigned
+  pub warn_uninit: bool,
+  /// Warn about lines indented with both tabs and spaces
+  pub warn_mixed_indent: bool,
+  /// Run without the natives that reach outside the program, like `sleep`
+  pub sandbox: bool,
+  /// Register the `print` and `eprint` natives, a program calling it fails on an undeclared variable otherwise
+  pub native_print: bool,
+  /// Read resolved variables through the slot cached for them in their frame
+  pub cache_lookups: bool,
+  /// How many calls can be nested before the program fails with a diagnostic
+  pub max_call_depth: usize,
+  /// Let the REPL keep reading lines until a pasted block is complete
+  pub interactive_paste: bool,
+  /// Write how long every phase took to the output after a successful run
+  pub time_phases: bool,
+  /// File name the diagnostics of a run report, set by `run_file`
+  file: String,
+  /// Incomplete REPL input waiting for more lines
+  repl_buffer: String,
+  /// Interpreter that keeps the REPL state between lines
+  interpreter: Interpreter,
+}
+
+/// What the REPL did with a line of input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplStatus {
+  /// The input so far is incomplete and was kept for the next line
+  NeedsMore,
+  /// The input was run
+  Evaluated,
+  /// The input had errors, which were printed
+  Failed,
+}
+
+/// The captured result of running a program file.
+#[derive(Debug)]
+pub struct RunOutput {
+  pub output: String,
+  /// What the program printed with `eprint`
+  pub errors: String,
+  pub diagnostics: Vec<Diagnostic>,
+  pub exit_code: i32,
+}
+
+impl Default for Runner {
+  fn default() -> Self {
+    Self::new()
+  }
+}
 
 impl Runner {
   pub fn new() -> Self {
-    Self {}
+    Self {
+      dump_ast: false,
+      dump_tokens_json: false,
+      emit_metrics: false,
+      output: Output::Stdout,
+      error_output: Output::Stderr,
+      input: Input::Stdin,
+      interactive_paste: false,
+      time_phases: false,
+      lenient_eof: false,
+      warn_uninit: false,
+      warn_mixed_indent: false,
+      sandbox: false,
+      native_print: true,
+      cache_lookups: true,
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+      file: DEFAULT_FILE.to_string(),
+      repl_buffer: String::new(),
+      interpreter: Interpreter::new(),
+    }
   }
 
   /// Function that starts the runtime env for the language takes stdin and puts stdout or stderr.
@@ -21,12 +119,15 @@ impl Runner {
     println!("Welcome to DuckLang 🦆");
     println!("Type `exit` to quit.\n");
 
-    let mut interputer = Interpreter::new();
-
     loop {
       engine.clear();
 
-      print!("> ");
+      // Continuation prompt while a pasted block is still open
+      if self.repl_buffer.is_empty() {
+        print!("> ");
+      } else {
+        print!("... ");
+      }
       io::stdout().flush().unwrap(); // Ensure the prompt shows immediately
 
       let mut line = String::new();
@@ -38,50 +139,143 @@ impl Runner {
         break;
       }
 
-      let input = line.trim();
+      let input = line.trim_end_matches(['\n', '\r']);
 
-      if input == "exit" {
+      if self.repl_buffer.is_empty() && input.trim() == "exit" {
         println!("Exiting...");
         break;
       }
 
-      // Scanning the buffer of string
-      let mut scanner = Scanner::new(input.to_string().clone());
+      self.repl_line(input, engine);
+    }
+  }
 
-      // Scan the tokens
-      scanner.scan(engine);
+  /// Function that feeds a single line to the REPL. With `interactive_paste` set, input that ends
+  /// before it's complete (an unclosed `{` or `(`) is kept until the following lines complete it
+  /// or a blank line forces the evaluation.
+  pub fn repl_line(&mut self, line: &str, engine: &mut DiagnosticEngine) -> ReplStatus {
+    let force = line.trim().is_empty();
+    if !force {
+      self.repl_buffer.push_str(line);
+      self.repl_buffer.push('\n');
+    }
 
-      // Check if there were scanning errors
-      if engine.has_errors() {
-        engine.print_all(&input);
-        continue;
-      }
+    let source = std::mem::take(&mut self.repl_buffer);
+    if source.trim().is_empty() {
+      return ReplStatus::Evaluated;
+    }
+
+    // Scanning the buffer of string
+    let mut scanner = self.scanner(source.clone(), "<repl>");
+
+    // Scan the tokens
+    scanner.scan(engine);
+
+    // Check if there were scanning errors
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return ReplStatus::Failed;
+    }
 
-      // Parse the tokens
-      let mut parser = Parser::new(scanner.tokens);
-      parser.parse(engine);
+    // Parse the tokens
+    let mut parser = Parser::new(scanner.tokens);
+    parser.lenient_eof = self.lenient_eof;
+    parser.parse(engine);
 
-      // Check if there were parsing errors
-      if engine.has_errors() {
-        engine.print_all(&input);
-        continue;
+    // Check if there were parsing errors, keep reading when the input just isn't complete yet
+    if engine.has_errors() {
+      if self.interactive_paste && parser.needs_more_input && !force {
+        engine.clear();
+        self.repl_buffer = source;
+        return ReplStatus::NeedsMore;
       }
 
-      // interputer.run(parser.ast, engine);
+      engine.print_all(&source);
+      return ReplStatus::Failed;
+    }
+
+    let mut resolver = semantic_analysis::resolver::Resolver::new()
+      .with_repl_mode(true)
+      .with_warn_uninit(self.warn_uninit);
+    resolver.run(&parser.ast, engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return ReplStatus::Failed;
+    }
+
+    let locals = resolver.get_locals().clone();
+    self.interpreter.output = self.output.clone();
+    self.interpreter.error_output = self.error_output.clone();
+    self.interpreter.input = self.input.clone();
+    self.interpreter.sandbox = self.sandbox;
+    self.interpreter.native_print = self.native_print;
+    self.interpreter.max_call_depth = self.max_call_depth;
+    self.interpreter.cache_lookups = self.cache_lookups;
+    self.interpreter.run(parser.ast, locals, engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return ReplStatus::Failed;
+    }
+
+    ReplStatus::Evaluated
+  }
 
-      if engine.has_errors() {
-        engine.print_all(&input);
-        continue;
+  /// Function that creates the scanner for the source with the runner's lint settings.
+  fn scanner(&self, source: String, file: &str) -> Scanner {
+    let mut scanner = Scanner::new(source).with_file(file);
+    scanner.warn_mixed_indent = self.warn_mixed_indent;
+    scanner
+  }
+
+  /// Function that reads the program at `path`, all of the runner input when it's `-`, and sets
+  /// the file name the diagnostics report
+  fn read_source(&mut self, path: &str) -> io::Result<String> {
+    if path == STDIN_PATH {
+      self.file = STDIN_FILE.to_string();
+      return self.input.read_to_end();
+    }
+
+    self.file = path.to_string();
+    fs::read_to_string(path)
+  }
+
+  /// Function that runs every file in order, each one with a fresh interpreter and its own
+  /// diagnostics, collecting all of them into `engine`. Every file runs even after one fails, the
+  /// first failure is returned.
+  pub fn run_files(
+    &mut self,
+    paths: &[String],
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(), RunError> {
+    let mut result = Ok(());
+
+    for path in paths {
+      // Kept out of the way of the machine readable JSON
+      if !self.dump_tokens_json {
+        println!("{}", format!("Running file: {}", path).cyan().bold());
+      }
+
+      let mut file_engine = DiagnosticEngine::new();
+      let file_result = self.run_file(path.clone(), &mut file_engine);
+      for diagnostic in file_engine.get_diagnostics() {
+        engine.emit(diagnostic.clone());
       }
 
-      // println!("{:?}", interputer);
+      if result.is_ok() {
+        result = file_result;
+      }
     }
+
+    result
   }
 
-  /// Function that runs the process of compiling file.
-  pub fn run_file(&mut self, path: String, engine: &mut DiagnosticEngine) {
+  /// Function that runs the process of compiling file, the error tells which stage failed and
+  /// carries the exit code for it.
+  pub fn run_file(&mut self, path: String, engine: &mut DiagnosticEngine) -> Result<(), RunError> {
     // Reading files to get the string buff
-    let source = match fs::read_to_string(&path) {
+    let source = match self.read_source(&path) {
       Ok(content) => content,
       Err(err) => {
         let diagnostic = Diagnostic::new(
@@ -92,27 +286,182 @@ impl Runner {
 
         engine.emit(diagnostic);
         engine.print_all("");
-        std::process::exit(66);
+        return Err(RunError::Io);
       },
     };
 
-    self.inturpret(source, engine);
+    if self.dump_tokens_json {
+      let tokens = self
+        .render_tokens_json(source, engine)
+        .ok_or(RunError::Compile)?;
+      println!("{}", tokens);
+      return Ok(());
+    }
+
+    if self.emit_metrics {
+      let metrics = self
+        .render_metrics(source, engine)
+        .ok_or(RunError::Compile)?;
+      println!("{}", metrics);
+      return Ok(());
+    }
+
+    if self.dump_ast {
+      let tree = self.render_ast(source, engine).ok_or(RunError::Compile)?;
+      print!("{}", tree);
+      return Ok(());
+    }
+
+    self.inturpret(source, engine)
   }
 
-  pub fn inturpret(&mut self, source: String, engine: &mut DiagnosticEngine) {
+  /// Function that runs a file like `run_file` but collects the program output, the diagnostics
+  /// and the exit code instead of writing the output to stdout and stderr.
+  pub fn run_file_capture(&mut self, path: String) -> RunOutput {
+    let mut engine = DiagnosticEngine::new();
+
+    let source = match self.read_source(&path) {
+      Ok(content) => content,
+      Err(err) => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::FileNotFound,
+          format!("could not read file: {}", path),
+        )
+        .with_help(format!("reason: {}", err));
+
+        engine.emit(diagnostic);
+        return RunOutput {
+          output: String::new(),
+          errors: String::new(),
+          diagnostics: engine.get_diagnostics().to_vec(),
+          exit_code: RunError::Io.exit_code(),
+        };
+      },
+    };
+
+    let sink = Output::buffer();
+    let error_sink = Output::buffer();
+    let previous = std::mem::replace(&mut self.output, sink.clone());
+    let previous_errors = std::mem::replace(&mut self.error_output, error_sink.clone());
+    let exit_code = match self.inturpret(source, &mut engine) {
+      Ok(()) => 0,
+      Err(error) => error.exit_code(),
+    };
+    self.output = previous;
+    self.error_output = previous_errors;
+
+    RunOutput {
+      output: sink.contents(),
+      errors: error_sink.contents(),
+      diagnostics: engine.get_diagnostics().to_vec(),
+      exit_code,
+    }
+  }
+
+  /// Function that scans the source and renders the tokens, without the trailing `Eof`, as a JSON
+  /// array of `{ kind, lexeme, line, column, length }` objects, returns `None` after printing the
+  /// diagnostics if the source could not be scanned.
+  pub fn render_tokens_json(
+    &mut self,
+    source: String,
+    engine: &mut DiagnosticEngine,
+  ) -> Option<String> {
+    let mut scanner = self.scanner(source.clone(), &self.file);
+    scanner.scan(engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return None;
+    }
+
+    let tokens = scanner
+      .tokens
+      .iter()
+      .filter(|token| token.token_type != TokenType::Eof)
+      .map(|token| format!("  {}", token.to_json()))
+      .collect::<Vec<_>>();
+
+    if tokens.is_empty() {
+      return Some("[]".to_string());
+    }
+
+    Some(format!("[\n{}\n]", tokens.join(",\n")))
+  }
+
+  /// Function that scans and parses the source and renders every top-level statement as a tree,
+  /// returns `None` after printing the diagnostics if the source could not be parsed.
+  pub fn render_ast(&mut self, source: String, engine: &mut DiagnosticEngine) -> Option<String> {
+    let mut scanner = self.scanner(source.clone(), &self.file);
+    scanner.scan(engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return None;
+    }
+
+    let mut parser = Parser::new(scanner.tokens);
+    parser.lenient_eof = self.lenient_eof;
+    parser.parse(engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return None;
+    }
+
+    Some(parser.ast.iter().map(|stmt| stmt.tree_string()).collect())
+  }
+
+  /// Function that scans and parses the source and counts its tokens, statements, expression depth
+  /// and declarations, returns `None` after printing the diagnostics if the source could not be
+  /// parsed.
+  pub fn render_metrics(
+    &mut self,
+    source: String,
+    engine: &mut DiagnosticEngine,
+  ) -> Option<Metrics> {
+    let mut scanner = self.scanner(source.clone(), &self.file);
+    scanner.scan(engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return None;
+    }
+
+    let tokens = scanner.tokens.clone();
+    let mut parser = Parser::new(scanner.tokens);
+    parser.lenient_eof = self.lenient_eof;
+    parser.parse(engine);
+
+    if engine.has_errors() {
+      engine.print_all(&source);
+      return None;
+    }
+
+    Some(Metrics::collect(&tokens, &parser.ast))
+  }
+
+  /// Function that runs the whole pipeline over the source, failing with `RunError::Compile` when
+  /// nothing could be run and `RunError::Runtime` when the program hit an error.
+  pub fn inturpret(
+    &mut self,
+    source: String,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<(), RunError> {
     println!("\n============== READ =================\n");
     println!("{}", source);
 
     // Scanning the buffer of string
-    let mut scanner = Scanner::new(source.clone());
+    let mut scanner = self.scanner(source.clone(), &self.file);
 
     // Scan the tokens
+    let started = Instant::now();
     scanner.scan(engine);
+    let scan_time = started.elapsed();
 
     // Check if there were scanning errors
     if engine.has_errors() {
       engine.print_all(&source);
-      return;
+      return Err(RunError::Compile);
     }
 
     println!("\n============= SCANNED ===============\n");
@@ -120,13 +469,16 @@ impl Runner {
     println!("ToLongVector(value..)\n");
 
     // Parse the tokens
+    let started = Instant::now();
     let mut parser = Parser::new(scanner.tokens);
+    parser.lenient_eof = self.lenient_eof;
     parser.parse(engine);
+    let parse_time = started.elapsed();
 
     // Check if there were parsing errors
     if engine.has_errors() {
       engine.print_all(&source);
-      return;
+      return Err(RunError::Compile);
     }
 
     println!("\n============== PARSED ===============\n");
@@ -134,25 +486,53 @@ impl Runner {
 
     println!("\n======== SEMANTIC ANALYSIS ==========\n");
 
-    let mut resolver = semantic_analysis::resolver::Resolver::new();
+    let started = Instant::now();
+    let mut resolver =
+      semantic_analysis::resolver::Resolver::new().with_warn_uninit(self.warn_uninit);
     resolver.run(&parser.ast, engine);
+    let resolve_time = started.elapsed();
 
-    // engine.print_all(&source);
     if engine.has_errors() {
-      return;
+      engine.print_all(&source);
+      return Err(RunError::Compile);
     }
 
     let locals = resolver.get_locals().clone();
     let mut interputer = Interpreter::new();
+    interputer.output = self.output.clone();
+    interputer.error_output = self.error_output.clone();
+    interputer.input = self.input.clone();
+    interputer.sandbox = self.sandbox;
+    interputer.native_print = self.native_print;
+    interputer.max_call_depth = self.max_call_depth;
+    interputer.cache_lookups = self.cache_lookups;
+    let started = Instant::now();
     interputer.run(parser.ast, locals, engine);
+    let interpret_time = started.elapsed();
 
     if engine.has_errors() {
       engine.print_all(&source);
-      return;
+      return Err(RunError::Runtime);
     }
     println!("\n============ INTERPRETED ============\n");
 
+    if engine.warning_count() > 0 {
+      engine.print_all(&source);
+    }
+
+    if self.time_phases {
+      let ms = |duration: Duration| format!("{:.3}ms", duration.as_secs_f64() * 1000.0);
+      self.output.write_line(&format!(
+        "scan: {}, parse: {}, resolve: {}, interpret: {}",
+        ms(scan_time),
+        ms(parse_time),
+        ms(resolve_time),
+        ms(interpret_time)
+      ));
+    }
+
     // If no errors, compilation succeeded
     println!("Compilation successful!");
+    Ok(())
   }
 }
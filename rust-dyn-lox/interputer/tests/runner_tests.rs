@@ -0,0 +1,1512 @@
+#[cfg(test)]
+mod tests {
+  use compiler::{
+    error::RunError,
+    input::Input,
+    metrics::Metrics,
+    output::Output,
+    runner::{ReplStatus, Runner},
+  };
+  use diagnostic::{diagnostic_code::DiagnosticCode, DiagnosticEngine};
+  use std::{cell::RefCell, rc::Rc};
+
+  #[test]
+  fn test_render_ast_contains_node_labels() {
+    let source = r#"
+var x = 1 + 2;
+if (x > 2) {
+  print(x);
+}
+"#;
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let tree = runner.render_ast(source.to_string(), &mut engine).unwrap();
+
+    for label in [
+      "VarDecl(x)",
+      "Binary(+)",
+      "Literal(1)",
+      "If",
+      "condition:",
+      "Call",
+      "Identifier(print)",
+    ] {
+      assert!(
+        tree.contains(label),
+        "Missing node label {:?} in\n{}",
+        label,
+        tree
+      );
+    }
+  }
+
+  #[test]
+  fn test_render_ast_parse_error() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let tree = runner.render_ast("var = 1;".to_string(), &mut engine);
+
+    assert!(tree.is_none());
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_run_file_capture_collects_output() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/capture.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+    assert_eq!(result.output, "Hello duck\nValue: 1\nValue: 2\nValue: 3\n");
+  }
+
+  #[test]
+  fn test_run_file_capture_runtime_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/capture_runtime_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert!(!result.diagnostics.is_empty());
+    assert_eq!(result.output, "before\n");
+  }
+
+  #[test]
+  fn test_run_file_capture_missing_file() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/does_not_exist.duck".to_string());
+
+    assert_eq!(result.exit_code, 66);
+    assert_eq!(result.diagnostics.len(), 1);
+    assert!(result.output.is_empty());
+  }
+
+  #[test]
+  fn test_template_interpolation() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/template.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "x=3\nduck2\n");
+  }
+
+  #[test]
+  fn test_template_nested_strings_and_escapes() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/template_nested.duck".to_string());
+
+    assert_eq!(result.exit_code, 0, "{:?}", result.diagnostics);
+    assert_eq!(result.output, "q s\n${x} = 1\n${x}\nn in 2\n");
+  }
+
+  #[test]
+  fn test_template_error_points_into_the_file() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/template_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!(span.file, "tests/files/template_error.duck");
+    // Parser labels carry the 0-based `to_span` line, the `}` after `x +` is at column 15
+    assert_eq!((span.line, span.column), (1, 14));
+  }
+
+  #[test]
+  fn test_render_tokens_json() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let json = runner
+      .render_tokens_json("var x;\n\"ab\";".to_string(), &mut engine)
+      .unwrap();
+
+    assert_eq!(json.matches("\"kind\"").count(), 5);
+    assert!(
+      json.contains(r#"{"kind": "Var", "lexeme": "var", "line": 1, "column": 1, "length": 3}"#)
+    );
+    assert!(json
+      .contains(r#"{"kind": "Identifier", "lexeme": "x", "line": 1, "column": 5, "length": 1}"#));
+    assert!(
+      json.contains(r#"{"kind": "SemiColon", "lexeme": ";", "line": 1, "column": 6, "length": 1}"#)
+    );
+    assert!(
+      json.contains(r#"{"kind": "String", "lexeme": "ab", "line": 2, "column": 1, "length": 4}"#)
+    );
+  }
+
+  #[test]
+  fn test_logical_operators_short_circuit() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/short_circuit.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "a\nd\ne\nf\ng\nh\nl\nm\nn\n");
+  }
+
+  #[test]
+  fn test_repl_interactive_paste_function() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+    let output = Output::buffer();
+    runner.interactive_paste = true;
+    runner.output = output.clone();
+
+    let lines = ["fun add(a, b) {", "  return a + b;", "}"];
+    assert_eq!(
+      runner.repl_line(lines[0], &mut engine),
+      ReplStatus::NeedsMore
+    );
+    assert_eq!(
+      runner.repl_line(lines[1], &mut engine),
+      ReplStatus::NeedsMore
+    );
+    assert_eq!(
+      runner.repl_line(lines[2], &mut engine),
+      ReplStatus::Evaluated
+    );
+    assert!(!engine.has_errors());
+
+    assert_eq!(
+      runner.repl_line("print(add(1, 2));", &mut engine),
+      ReplStatus::Evaluated
+    );
+    assert_eq!(output.contents(), "3\n");
+  }
+
+  #[test]
+  fn test_repl_closure_keeps_its_locals_across_lines() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+    let output = Output::buffer();
+    runner.output = output.clone();
+
+    for line in [
+      "fun counter() { var c = 0; fun inc() { { c = c + 1; } return c; } return inc; }",
+      "var next = counter();",
+      "{ var c = 10; print(next()); }",
+      "print(next());",
+    ] {
+      assert_eq!(runner.repl_line(line, &mut engine), ReplStatus::Evaluated);
+    }
+
+    assert!(!engine.has_errors());
+    assert_eq!(output.contents(), "1\n2\n");
+  }
+
+  #[test]
+  fn test_repl_blank_line_forces_evaluation() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+    runner.interactive_paste = true;
+
+    assert_eq!(
+      runner.repl_line("print((1 + 2)", &mut engine),
+      ReplStatus::NeedsMore
+    );
+    assert_eq!(runner.repl_line("", &mut engine), ReplStatus::Failed);
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_repl_top_level_return_help() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    assert_eq!(
+      runner.repl_line("return 5;", &mut engine),
+      ReplStatus::Failed
+    );
+
+    let help = engine.get_diagnostics()[0].help.clone().unwrap();
+    assert_eq!(
+      help,
+      "cannot return from the top level; did you mean to print `5`?"
+    );
+  }
+
+  #[test]
+  fn test_array_sort() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/array_sort.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output,
+      "[1, 2, 3]\n[1, 2, 3]\n[\"apple\", \"fig\", \"pear\"]\n[5, 4, 3, 2, 1]\n[10, 20, 30]\n"
+    );
+  }
+
+  #[test]
+  fn test_array_sort_mixed_types() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/array_sort_mixed.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeMismatch);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column), (1, 21));
+  }
+
+  #[test]
+  fn test_array_unknown_method_is_a_diagnostic() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/array_unknown_method.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeMismatch);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (2, 3, 4));
+  }
+
+  #[test]
+  fn test_equality_never_coerces_across_types() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/equality.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output.lines().collect::<Vec<_>>(),
+      ["false", "false", "false", "false", "false", "true", "true", "true", "true", "false"]
+    );
+  }
+
+  #[test]
+  fn test_warn_uninit_read_before_assignment() {
+    let mut runner = Runner::new();
+    runner.warn_uninit = true;
+
+    let result = runner.run_file_capture("tests/files/uninit_read.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "nil\n");
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::UninitializedVariable
+    );
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!(
+      (span.file.as_str(), span.line, span.column, span.length),
+      ("tests/files/uninit_read.duck", 2, 7, 1)
+    );
+  }
+
+  #[test]
+  fn test_warn_uninit_assigned_before_read() {
+    let mut runner = Runner::new();
+    runner.warn_uninit = true;
+
+    let result = runner.run_file_capture("tests/files/uninit_assigned.duck".to_string());
+
+    assert_eq!(result.output, "1\n");
+    assert!(result.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_warn_uninit_skips_reads_in_functions_declared_before_the_assignment() {
+    let mut runner = Runner::new();
+    runner.warn_uninit = true;
+
+    let result = runner.run_file_capture("tests/files/uninit_captured.duck".to_string());
+
+    assert_eq!(result.output, "1\n");
+    assert!(result.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_uninit_read_is_silent_without_the_flag() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/uninit_read.duck".to_string());
+
+    assert!(result.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_sleep_returns_nil() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/sleep.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "nil\nnil\n");
+    assert!(result.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_sleep_rejects_negative_milliseconds() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/sleep_negative.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeError);
+  }
+
+  #[test]
+  fn test_sleep_rejects_a_non_number() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/sleep_not_a_number.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics.len(), 1);
+    let diagnostic = &result.diagnostics[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::TypeError);
+    let span = &diagnostic.labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 7, 6));
+  }
+
+  #[test]
+  fn test_sleep_is_disabled_in_sandbox() {
+    let mut runner = Runner::new();
+    runner.sandbox = true;
+
+    let result = runner.run_file_capture("tests/files/sleep.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert!(result.output.is_empty());
+  }
+
+  #[test]
+  fn test_do_while_runs_body_before_condition() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/do_while.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "1\n3\n");
+  }
+
+  #[test]
+  fn test_calling_a_literal_variable_fails_to_resolve() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/call_literal.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::InvalidFunctionCall
+    );
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (2, 1, 1));
+  }
+
+  #[test]
+  fn test_calling_a_function_variable_resolves() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/call_function_var.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "called\n");
+  }
+
+  #[test]
+  fn test_calling_a_variable_of_unknown_type_resolves() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/call_unknown_var.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "inner\n");
+  }
+
+  #[test]
+  fn test_literal_in_a_sibling_block_does_not_shadow_the_call() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/call_sibling_block.duck".to_string());
+
+    assert_eq!(result.exit_code, 0, "{:?}", result.diagnostics);
+    assert_eq!(result.output, "1\n");
+  }
+
+  #[test]
+  fn test_print_truncates_deeply_nested_arrays() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/nested_array_print.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "[[[[[[[[[...]]]]]]]]]\n");
+  }
+
+  #[test]
+  fn test_print_truncates_long_arrays() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/long_array_print.duck".to_string());
+
+    let expected = (0..100)
+      .map(|i| i.to_string())
+      .collect::<Vec<_>>()
+      .join(", ");
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, format!("[{}, ...]\n", expected));
+  }
+
+  #[test]
+  fn test_break_and_continue_in_loops() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/loop_control.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "3\n13\n0\n2\n3\n");
+  }
+
+  #[test]
+  fn test_break_ends_a_loop_whose_body_is_not_a_block() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/loop_control_bare.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+    assert_eq!(result.output, "while\nfor\ndo\n3\nlabeled\n");
+  }
+
+  #[test]
+  fn test_break_outside_loop_is_an_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/break_outside_loop.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::BreakOutsideLoop);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (2, 3, 5));
+  }
+
+  #[test]
+  fn test_break_after_a_loop_points_at_the_break() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/break_after_loop.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::BreakOutsideLoop);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (3, 1, 5));
+  }
+
+  #[test]
+  fn test_continue_outside_loop_is_an_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/continue_outside_loop.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::ContinueOutsideLoop
+    );
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 1, 8));
+  }
+
+  #[test]
+  fn test_render_metrics() {
+    let source = std::fs::read_to_string("tests/files/metrics.duck").unwrap();
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let metrics = runner.render_metrics(source, &mut engine).unwrap();
+
+    assert_eq!(
+      metrics,
+      Metrics {
+        tokens: 49,
+        statements: 9,
+        max_expr_depth: 5,
+        functions: 2,
+        classes: 1,
+      }
+    );
+  }
+
+  #[test]
+  fn test_switch_runs_matched_or_default_case() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/switch.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "two\nmany\ndone\n");
+  }
+
+  #[test]
+  fn test_conditions_share_one_truthiness() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/truthiness.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output,
+      "a\nb\na\nif 0\nelse nil\n1\n0\nfallback\nfalse\ntrue\n\nx\nfunction\n"
+    );
+  }
+
+  /// Function that runs the `lox` binary over a fixture and returns its stdout
+  fn run_binary(args: &[&str]) -> String {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_compiler"))
+      .args(args)
+      .env("NO_COLOR", "1")
+      .output()
+      .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+  }
+
+  #[test]
+  fn test_parsing_does_not_print_the_tree() {
+    let stdout = run_binary(&["tests/files/capture.duck"]);
+
+    assert!(!stdout.contains("└── "), "{}", stdout);
+    assert!(stdout.contains("Compilation successful!"));
+  }
+
+  #[test]
+  fn test_dump_ast_still_prints_the_tree() {
+    let stdout = run_binary(&["--dump-ast", "tests/files/capture.duck"]);
+
+    assert!(stdout.contains("└── "), "{}", stdout);
+  }
+
+  #[test]
+  fn test_cached_lookups_match_lookups_by_name() {
+    let mut cached = Runner::new();
+    let mut uncached = Runner::new();
+    uncached.cache_lookups = false;
+
+    let with_cache = cached.run_file_capture("tests/files/cached_lookups.duck".to_string());
+    let without_cache = uncached.run_file_capture("tests/files/cached_lookups.duck".to_string());
+
+    assert_eq!(with_cache.exit_code, 0);
+    assert_eq!(with_cache.output, "20\n65\n19\n14\n25\n");
+    assert_eq!(with_cache.output, without_cache.output);
+  }
+
+  #[test]
+  fn test_env_slot_lookup_matches_lookup_by_name() {
+    use compiler::{env::Env, lox_value::LoxValue};
+    use std::{cell::RefCell, rc::Rc};
+
+    let outer = Rc::new(RefCell::new(Env::new()));
+    outer
+      .borrow_mut()
+      .define("a".to_string(), LoxValue::Number(1.0));
+    outer
+      .borrow_mut()
+      .define("b".to_string(), LoxValue::Number(2.0));
+    let mut inner = Env::new().with_enclosing(outer.clone());
+    inner.define("b".to_string(), LoxValue::Number(3.0));
+
+    let slot = inner.slot_at(1, "b").unwrap();
+    assert_eq!(
+      inner.get_slot_at(1, slot, "b").map(|v| v.to_string()),
+      inner.get_at(1, "b").map(|v| v.to_string())
+    );
+    assert_eq!(
+      inner.get_slot_at(0, 0, "b").map(|v| v.to_string()),
+      Some("3".to_string())
+    );
+
+    // Redefining keeps the slot, a slot holding another name is a miss
+    outer
+      .borrow_mut()
+      .define("b".to_string(), LoxValue::Number(4.0));
+    assert_eq!(
+      inner.get_slot_at(1, slot, "b").map(|v| v.to_string()),
+      Some("4".to_string())
+    );
+    assert!(inner.get_slot_at(1, slot, "a").is_none());
+  }
+
+  /// Run with `cargo test --release -- --ignored bench_` to compare the cached and uncached lookups
+  #[test]
+  #[ignore]
+  fn bench_hot_loop_identifier_lookups() {
+    for cache_lookups in [true, false] {
+      let mut runner = Runner::new();
+      runner.cache_lookups = cache_lookups;
+
+      let start = std::time::Instant::now();
+      let result = runner.run_file_capture("tests/files/hot_loop.duck".to_string());
+      let elapsed = start.elapsed();
+
+      assert_eq!(result.output, "1000000\n");
+      println!("cache_lookups = {}: {:?}", cache_lookups, elapsed);
+    }
+  }
+
+  #[test]
+  fn test_power_is_right_associative() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/power.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "512\n18\n4\n");
+  }
+
+  #[test]
+  fn test_power_of_a_string_is_an_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/power_string.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::InvalidOperator);
+  }
+
+  #[test]
+  fn test_immediately_invoked_function_returns_its_value() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/iife.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "42\n");
+  }
+
+  #[test]
+  fn test_immediately_invoked_function_closes_over_outer_variables() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/iife_closure.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "15\n6\n");
+  }
+
+  /// Function that runs the source through an interpreter that keeps its globals between calls
+  fn evaluate(
+    interpreter: &mut compiler::interpreter::Interpreter,
+    source: &str,
+    engine: &mut DiagnosticEngine,
+  ) -> compiler::lox_value::LoxValue {
+    let mut scanner = scanner::Scanner::new(source.to_string());
+    scanner.scan(engine);
+    let mut parser = parser::Parser::new(scanner.tokens);
+    parser.parse(engine);
+    let mut resolver = semantic_analysis::resolver::Resolver::new().with_repl_mode(true);
+    resolver.run(&parser.ast, engine);
+
+    let locals = resolver.get_locals().clone();
+    interpreter.run(parser.ast, locals, engine)
+  }
+
+  #[test]
+  fn test_pure_evaluation_returns_the_last_value() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+    let sink = Output::buffer();
+    interpreter.output = sink.clone();
+    interpreter.native_print = false;
+
+    evaluate(&mut interpreter, "var x = 2 * 21;", &mut engine);
+    let value = evaluate(&mut interpreter, "x;", &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(value.to_string(), "42");
+    assert_eq!(sink.contents(), "");
+  }
+
+  #[test]
+  fn test_logical_rhs_only_runs_when_the_lhs_does_not_decide() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+    interpreter.native_print = false;
+
+    evaluate(
+      &mut interpreter,
+      "var calls = 0; fun sideEffect() { calls = calls + 1; return true; }",
+      &mut engine,
+    );
+    let and = evaluate(&mut interpreter, "false and sideEffect();", &mut engine);
+    let or = evaluate(&mut interpreter, "true or sideEffect();", &mut engine);
+    let calls = evaluate(&mut interpreter, "calls;", &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(and.to_string(), "false");
+    assert_eq!(or.to_string(), "true");
+    assert_eq!(calls.to_string(), "0");
+
+    let both = evaluate(&mut interpreter, "true and sideEffect();", &mut engine);
+    let calls = evaluate(&mut interpreter, "calls;", &mut engine);
+
+    assert_eq!(both.to_string(), "true");
+    assert_eq!(calls.to_string(), "1");
+  }
+
+  #[test]
+  fn test_print_is_undeclared_without_native_print() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+    interpreter.native_print = false;
+
+    evaluate(&mut interpreter, "print(1);", &mut engine);
+
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::UndeclaredVariable
+    );
+  }
+
+  #[test]
+  fn test_var_bound_lambda_can_call_itself() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/recursive_lambda.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "120\n");
+  }
+
+  #[test]
+  fn test_diagnostics_report_the_file_that_was_run() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/power_string.duck".to_string());
+
+    assert_eq!(
+      result.diagnostics[0].labels[0].span.file,
+      "tests/files/power_string.duck"
+    );
+  }
+
+  #[test]
+  fn test_math_natives() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/math.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "7\n3\n4\n");
+  }
+
+  #[test]
+  fn test_math_natives_reject_non_numbers() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/math_type_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeError);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 11, 3));
+  }
+
+  #[test]
+  fn test_rounding_natives() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/rounding.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "3\n4\n3\n-3\n");
+  }
+
+  #[test]
+  fn test_rounding_natives_reject_non_numbers() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/rounding_type_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeError);
+  }
+
+  #[test]
+  fn test_compound_assignment_appends_strings_and_arrays() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/compound_assign.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "ab\n[1, 2]\n[1]\n4\n");
+  }
+
+  #[test]
+  fn test_compound_assignment_of_array_and_number_fails() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/compound_assign_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::InvalidOperator);
+  }
+
+  #[test]
+  fn test_num_and_str_convert_values() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/convert.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "43\n3.5!\nniltrue[1, 2]\n");
+  }
+
+  #[test]
+  fn test_num_of_a_non_numeric_string_fails() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/convert_invalid.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    let diagnostic = &result.diagnostics[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::InvalidNumber);
+    let span = &diagnostic.labels[0].span;
+    // The label covers the string with its quotes
+    assert_eq!((span.line, span.column, span.length), (1, 11, 5));
+  }
+
+  #[test]
+  fn test_input_reads_lines_until_nil() {
+    let mut runner = Runner::new();
+    runner.input = Input::buffer("duck\nbye\n");
+
+    let result = runner.run_file_capture("tests/files/input.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "hello duck\nbye\nnil\n");
+  }
+
+  #[test]
+  fn test_input_returns_the_line_as_a_string() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+    interpreter.input = Input::buffer("  quack  \r\n");
+
+    let value = evaluate(&mut interpreter, "input();", &mut engine);
+
+    assert!(!engine.has_errors());
+    assert!(
+      matches!(value, compiler::lox_value::LoxValue::String(ref line) if line == "  quack  ")
+    );
+  }
+
+  #[test]
+  fn test_return_unwinds_through_nested_loops() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/return_in_loop.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+    // The loops stop at the first match instead of treating the return as a break
+    assert_eq!(result.output, "[1, 6, 17]\nnil\n3\n");
+  }
+
+  #[test]
+  fn test_locals_resolve_per_use_in_nested_scopes() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/nested_locals.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "hi from A!\n6\n1\n2\n");
+  }
+
+  #[test]
+  fn test_mixed_addition_warns_about_implicit_conversion() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/implicit_conversion.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "1x\ny2\n3\nab\n");
+    let warnings = result
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| diagnostic.code == DiagnosticCode::ImplicitConversion)
+      .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 2);
+    let span = &warnings[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 9, 1));
+    assert!(warnings[0].notes[0].contains("str(...)"));
+  }
+
+  #[test]
+  fn test_same_type_addition_does_not_warn() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/no_implicit_conversion.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_functions_are_equal_by_identity() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/function_equality.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output,
+      "true\ntrue\nfalse\ntrue\ntrue\nfalse\ntrue\nfalse\n"
+    );
+  }
+
+  #[test]
+  fn test_typeof_names_the_operand_type() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/typeof.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output,
+      "number\nstring\nnil\nbool\nfunction\nstring\ntrue\n"
+    );
+  }
+
+  #[test]
+  fn test_binary_operator_error_underlines_both_operands() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/binary_operands_span.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    let span = &result.diagnostics[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 7, 10));
+  }
+
+  #[test]
+  fn test_property_access_on_values_is_a_type_mismatch() {
+    for (source, name) in [
+      ("1.foo;", "foo"),
+      ("\"x\".bar;", "bar"),
+      ("nil.baz;", "baz"),
+    ] {
+      let mut engine = DiagnosticEngine::new();
+      let mut interpreter = compiler::interpreter::Interpreter::new();
+
+      evaluate(&mut interpreter, source, &mut engine);
+
+      let diagnostic = &engine.get_diagnostics()[0];
+      assert_eq!(diagnostic.code, DiagnosticCode::TypeMismatch, "{source}");
+      assert!(diagnostic
+        .message
+        .contains("Only instances have properties"));
+      let label = diagnostic.labels[0].message.as_deref().unwrap();
+      assert!(label.contains(&format!("'{}'", name)), "{label}");
+      assert_eq!(diagnostic.labels[0].span.length, name.len());
+    }
+  }
+
+  #[test]
+  fn test_property_assignment_on_a_value_is_a_type_mismatch() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/property_on_value.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeMismatch);
+    assert!(result.diagnostics[0]
+      .message
+      .contains("Only instances have fields"));
+  }
+
+  #[test]
+  fn test_run_file_reports_a_type_error_as_a_runtime_failure() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let result = runner.run_file("tests/files/type_error.duck".to_string(), &mut engine);
+
+    assert_eq!(result, Err(RunError::Runtime));
+    assert_eq!(RunError::Runtime.exit_code(), 70);
+  }
+
+  #[test]
+  fn test_run_file_reports_a_parse_error_as_a_compile_failure() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let result = runner.run_file("tests/files/parse_error.duck".to_string(), &mut engine);
+
+    assert_eq!(result, Err(RunError::Compile));
+    assert_eq!(RunError::Compile.exit_code(), 65);
+  }
+
+  #[test]
+  fn test_run_file_reports_a_missing_file_as_an_io_failure() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let result = runner.run_file("tests/files/missing.duck".to_string(), &mut engine);
+
+    assert_eq!(result, Err(RunError::Io));
+    assert_eq!(RunError::Io.exit_code(), 66);
+  }
+
+  #[test]
+  fn test_run_file_succeeds_on_a_valid_program() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+
+    let result = runner.run_file("tests/files/power.duck".to_string(), &mut engine);
+
+    assert_eq!(result, Ok(()));
+  }
+
+  #[test]
+  fn test_labeled_break_leaves_the_outer_loop() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/labeled_break.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "[2, 3]\n3\n1\n");
+  }
+
+  #[test]
+  fn test_unknown_label_is_a_compile_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/unknown_label.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::UnknownLabel);
+  }
+
+  #[test]
+  fn test_anonymous_functions_are_values() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/anonymous_function.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "42\n1\ncalled\n");
+  }
+
+  #[test]
+  fn test_named_function_value_is_only_visible_in_its_body() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/named_lambda.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.output, "120\nhi\n");
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::UndeclaredVariable
+    );
+  }
+
+  #[test]
+  fn test_print_returns_the_printed_value() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+    let sink = Output::buffer();
+    interpreter.output = sink.clone();
+
+    let value = evaluate(&mut interpreter, "print(\"hi\");", &mut engine);
+
+    assert!(!engine.has_errors());
+    assert!(matches!(value, compiler::lox_value::LoxValue::String(ref s) if s == "hi"));
+    assert_eq!(sink.contents(), "hi\n");
+  }
+
+  #[test]
+  fn test_eprint_writes_to_the_error_output() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/print_value.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "hi\ntrue\n1 2\n1 2!\n");
+    assert_eq!(result.errors, "oops 3\n");
+  }
+
+  #[test]
+  fn test_clock_millis_returns_milliseconds() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/clock_millis.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+    assert_eq!(result.output, "number\ntrue\ntrue\nnil\n");
+  }
+
+  #[test]
+  fn test_infinite_recursion_reports_the_depth_limit() {
+    // The default limit needs more stack than a test thread gets in a debug build
+    let result = std::thread::Builder::new()
+      .stack_size(256 * 1024 * 1024)
+      .spawn(|| Runner::new().run_file_capture("tests/files/infinite_recursion.duck".to_string()))
+      .unwrap()
+      .join()
+      .unwrap();
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::MaxRecursionDepth
+    );
+    assert_eq!(result.diagnostics[0].labels[0].span.line, 2);
+    assert_eq!(result.output, "still running\n");
+  }
+
+  #[test]
+  fn test_max_call_depth_is_configurable() {
+    let mut runner = Runner::new();
+    runner.max_call_depth = 10;
+
+    let result = runner.run_file_capture("tests/files/infinite_recursion.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert!(result.diagnostics[0]
+      .message
+      .contains("Maximum recursion depth of 10 exceeded"));
+  }
+
+  #[test]
+  fn test_run_files_runs_every_file_and_reports_the_failure() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+    let paths = [
+      "tests/files/capture.duck",
+      "tests/files/missing.duck",
+      "tests/files/typeof.duck",
+    ]
+    .map(String::from);
+
+    let result = runner.run_files(&paths, &mut engine);
+
+    assert_eq!(result, Err(RunError::Io));
+    assert_ne!(result.unwrap_err().exit_code(), 0);
+    assert_eq!(engine.error_count(), 1);
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::FileNotFound
+    );
+  }
+
+  #[test]
+  fn test_run_files_succeeds_when_every_file_does() {
+    let mut engine = DiagnosticEngine::new();
+    let mut runner = Runner::new();
+    let paths = ["tests/files/capture.duck", "tests/files/typeof.duck"].map(String::from);
+
+    assert_eq!(runner.run_files(&paths, &mut engine), Ok(()));
+    assert!(!engine.has_errors());
+  }
+
+  #[test]
+  fn test_dash_path_reads_the_program_from_the_input() {
+    let mut runner = Runner::new();
+    runner.input = Input::buffer("var x = 20;\nprint(x + 1);\nprint(y);\n");
+
+    let result = runner.run_file_capture("-".to_string());
+
+    assert_eq!(result.output, "21\n");
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].labels[0].span.file, "<stdin>");
+  }
+
+  #[test]
+  fn test_time_phases_reports_every_phase_after_the_output() {
+    let mut runner = Runner::new();
+    runner.time_phases = true;
+
+    let result = runner.run_file_capture("tests/files/typeof.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    let timings = result.output.lines().last().unwrap();
+    for phase in ["scan: ", ", parse: ", ", resolve: ", ", interpret: "] {
+      assert!(timings.contains(phase), "{timings}");
+    }
+    assert!(timings.ends_with("ms"), "{timings}");
+  }
+
+  #[test]
+  fn test_time_phases_stays_quiet_when_compilation_fails() {
+    let mut runner = Runner::new();
+    runner.time_phases = true;
+
+    let result = runner.run_file_capture("tests/files/parse_error.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    assert!(!result.output.contains("scan: "), "{}", result.output);
+  }
+
+  #[test]
+  fn test_duplicate_declaration_in_one_scope_is_an_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/duplicate_declaration.duck".to_string());
+
+    assert_eq!(result.exit_code, 65);
+    let diagnostic = &result.diagnostics[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::DuplicateDeclaration);
+    let lines = diagnostic
+      .labels
+      .iter()
+      .map(|label| (label.style, label.span.line, label.span.column))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      lines,
+      vec![
+        (diagnostic::diagnostic::LabelStyle::Primary, 3, 7),
+        (diagnostic::diagnostic::LabelStyle::Secondary, 2, 7),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_redeclaring_in_a_nested_scope_shadows() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/nested_shadowing.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert!(result.diagnostics.is_empty());
+    assert_eq!(result.output, "2\n1\n");
+  }
+
+  /// Function that builds the global, middle and inner frames, each defining `x` as its depth
+  fn three_deep_env() -> (
+    Rc<RefCell<compiler::env::Env>>,
+    Rc<RefCell<compiler::env::Env>>,
+    compiler::env::Env,
+  ) {
+    use compiler::{env::Env, lox_value::LoxValue};
+
+    let global = Rc::new(RefCell::new(Env::new()));
+    global
+      .borrow_mut()
+      .define("x".to_string(), LoxValue::Number(2.0));
+    let middle = Rc::new(RefCell::new(Env::new().with_enclosing(global.clone())));
+    middle
+      .borrow_mut()
+      .define("x".to_string(), LoxValue::Number(1.0));
+    let mut inner = Env::new().with_enclosing(middle.clone());
+    inner.define("x".to_string(), LoxValue::Number(0.0));
+
+    (global, middle, inner)
+  }
+
+  #[test]
+  fn test_env_get_at_skips_the_shadowing_frames() {
+    let (_global, _middle, inner) = three_deep_env();
+
+    for distance in 0..3 {
+      assert_eq!(
+        inner.get_at(distance, "x").unwrap().to_string(),
+        distance.to_string()
+      );
+    }
+    assert!(inner.get_at(3, "x").is_none());
+    assert!(inner.get_at(1, "y").is_none());
+  }
+
+  #[test]
+  fn test_env_assign_at_writes_only_the_frame_at_the_distance() {
+    use compiler::lox_value::LoxValue;
+
+    let (global, middle, mut inner) = three_deep_env();
+
+    assert!(inner.assign_at(2, "x", LoxValue::Number(20.0)));
+    assert!(!inner.assign_at(3, "x", LoxValue::Number(30.0)));
+
+    assert_eq!(global.borrow().get_at(0, "x").unwrap().to_string(), "20");
+    assert_eq!(middle.borrow().get_at(0, "x").unwrap().to_string(), "1");
+    assert_eq!(inner.get_at(0, "x").unwrap().to_string(), "0");
+  }
+
+  #[test]
+  fn test_bitwise_operators_on_integers() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/bitwise.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "2\n7\n5\n16\n64\ntrue\n8\n");
+  }
+
+  #[test]
+  fn test_bitwise_operator_rejects_fractions() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/bitwise_fraction.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeMismatch);
+    assert!(result.diagnostics[0].message.contains("2.5"));
+  }
+
+  #[test]
+  fn test_bitwise_operator_rejects_numbers_outside_i64() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/bitwise_out_of_range.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert!(result.output.is_empty());
+    assert_eq!(result.diagnostics[0].code, DiagnosticCode::TypeMismatch);
+    assert_eq!(
+      result.diagnostics[0].help.as_deref(),
+      Some("Bitwise operands must fit in a 64-bit integer")
+    );
+  }
+
+  #[test]
+  fn test_type_error_label_covers_the_string_quotes() {
+    let mut engine = DiagnosticEngine::new();
+    let mut interpreter = compiler::interpreter::Interpreter::new();
+
+    evaluate(&mut interpreter, r#"1 - "a\"b";"#, &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::TypeError);
+    let operand = &diagnostic.labels[1].span;
+    assert_eq!((operand.line, operand.column, operand.length), (1, 5, 6));
+  }
+
+  #[test]
+  fn test_continue_in_for_still_runs_the_increment() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/for_continue.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.output, "5\n4\n3\n0\n1\n2\n");
+  }
+
+  #[test]
+  fn test_match_picks_the_first_matching_arm() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/match.duck".to_string());
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.output,
+      "one\ntwo\nminus one\ngreeting\nnothing\nother\nsum!\n"
+    );
+  }
+
+  #[test]
+  fn test_match_without_a_matching_arm_is_an_error() {
+    let mut runner = Runner::new();
+
+    let result = runner.run_file_capture("tests/files/match_non_exhaustive.duck".to_string());
+
+    assert_eq!(result.exit_code, 70);
+    assert_eq!(
+      result.diagnostics[0].code,
+      DiagnosticCode::NonExhaustiveMatch
+    );
+    assert!(result.diagnostics[0].message.contains('3'));
+  }
+
+  /// Function that runs the resolver alone over `source`, for lints on programs that shouldn't
+  /// be run
+  fn resolve(source: &str, engine: &mut DiagnosticEngine) {
+    let mut scanner = scanner::Scanner::new(source.to_string());
+    scanner.scan(engine);
+    let mut parser = parser::Parser::new(scanner.tokens);
+    parser.parse(engine);
+    semantic_analysis::resolver::Resolver::new().run(&parser.ast, engine);
+  }
+
+  #[test]
+  fn test_duplicate_parameter_gets_its_own_help() {
+    let mut engine = DiagnosticEngine::new();
+
+    resolve("fun h(p, p) {}", &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::DuplicateDeclaration);
+    assert_eq!(
+      diagnostic.help.as_deref(),
+      Some("Each parameter needs its own name. Rename one of them.")
+    );
+    assert_eq!(
+      (
+        diagnostic.labels[0].span.line,
+        diagnostic.labels[0].span.column
+      ),
+      (1, 10)
+    );
+  }
+
+  #[test]
+  fn test_top_level_return_points_at_the_return() {
+    let mut engine = DiagnosticEngine::new();
+
+    resolve("var a = 1;\nreturn 1;", &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::ReturnNotInFunction);
+    let span = &diagnostic.labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (2, 1, 6));
+  }
+
+  #[test]
+  fn test_while_false_body_is_unreachable() {
+    let mut engine = DiagnosticEngine::new();
+
+    resolve("while (false) { print(1); }", &mut engine);
+
+    let warnings: Vec<_> = engine.warnings().collect();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, DiagnosticCode::UnreachableCode);
+    let span = &warnings[0].labels[0].span;
+    assert_eq!((span.line, span.column, span.length), (1, 8, 5));
+  }
+
+  #[test]
+  fn test_while_true_without_break_gets_a_note() {
+    let mut engine = DiagnosticEngine::new();
+
+    resolve("while (true) { while (true) { break; } }", &mut engine);
+
+    let diagnostics = engine.get_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::Info);
+    assert_eq!(diagnostics[0].labels[0].span.column, 8);
+    assert!(!engine.has_errors());
+    assert_eq!(engine.warning_count(), 0);
+  }
+
+  #[test]
+  fn test_while_true_with_a_reachable_exit_is_quiet() {
+    for source in [
+      "while (true) { break; }",
+      "while (true) { if (clock() > 0) { break; } }",
+      "fun f() { while (true) { return 1; } }",
+      "outer: while (true) { while (true) { break outer; } }",
+      "while (true) { switch (1) { case 1: break; } }",
+      "for (;;) { }",
+    ] {
+      let mut engine = DiagnosticEngine::new();
+
+      resolve(source, &mut engine);
+
+      assert!(engine.get_diagnostics().is_empty(), "{source}");
+    }
+  }
+}
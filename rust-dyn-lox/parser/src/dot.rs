@@ -0,0 +1,340 @@
+use std::fmt::Write;
+
+use scanner::token::Token;
+
+use crate::{expr::Expr, stmt::Stmt};
+
+/// Collects the nodes and edges of a Graphviz `digraph` and hands out the node ids.
+struct DotWriter {
+  out: String,
+  next_id: usize,
+}
+
+impl DotWriter {
+  fn new() -> Self {
+    Self {
+      out: String::from("digraph AST {\n  node [shape=box];\n"),
+      next_id: 0,
+    }
+  }
+
+  /// Function that adds a node with the given label and returns its id
+  fn node(&mut self, label: &str) -> usize {
+    let id = self.next_id;
+    self.next_id += 1;
+    // Writing into a `String` never fails
+    let _ = writeln!(self.out, "  n{} [label=\"{}\"];", id, escape(label));
+    id
+  }
+
+  /// Function that adds an edge, the label names the role of the child when it has one
+  fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+    let _ = match label {
+      Some(label) => writeln!(
+        self.out,
+        "  n{} -> n{} [label=\"{}\"];",
+        from,
+        to,
+        escape(label)
+      ),
+      None => writeln!(self.out, "  n{} -> n{};", from, to),
+    };
+  }
+
+  fn finish(mut self) -> String {
+    self.out.push_str("}\n");
+    self.out
+  }
+}
+
+/// Function that escapes the characters that would end or break a quoted DOT label.
+fn escape(label: &str) -> String {
+  label
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+impl Stmt {
+  /// Function that renders the statement as Graphviz `digraph` text
+  pub fn to_dot(&self) -> String {
+    let mut writer = DotWriter::new();
+    self.write_dot(&mut writer);
+    writer.finish()
+  }
+
+  fn write_dot(&self, w: &mut DotWriter) -> usize {
+    match self {
+      Stmt::Expr(expr) => {
+        let id = w.node("ExprStmt");
+        let child = expr.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Stmt::VarDecl(name, value) => {
+        let id = w.node(&format!("VarDecl({})", name.lexeme));
+        if let Some(value) = value {
+          let child = value.write_dot(w);
+          w.edge(id, child, None);
+        }
+        id
+      },
+      Stmt::Block(stmts) => {
+        let id = w.node("Block");
+        write_stmts(w, id, stmts, None);
+        id
+      },
+      Stmt::If(condition, then_branch, else_branch) => {
+        let id = w.node("If");
+        let child = condition.write_dot(w);
+        w.edge(id, child, Some("condition"));
+        let child = then_branch.write_dot(w);
+        w.edge(id, child, Some("then"));
+        if let Some(else_branch) = else_branch {
+          let child = else_branch.write_dot(w);
+          w.edge(id, child, Some("else"));
+        }
+        id
+      },
+      Stmt::While(condition, body, increment, label) => {
+        let id = w.node(&format!("While{}", dot_label(label)));
+        let child = condition.write_dot(w);
+        w.edge(id, child, Some("condition"));
+        let child = body.write_dot(w);
+        w.edge(id, child, Some("body"));
+        if let Some(increment) = increment {
+          let child = increment.write_dot(w);
+          w.edge(id, child, Some("increment"));
+        }
+        id
+      },
+      Stmt::DoWhile(body, condition, label) => {
+        let id = w.node(&format!("DoWhile{}", dot_label(label)));
+        let child = body.write_dot(w);
+        w.edge(id, child, Some("body"));
+        let child = condition.write_dot(w);
+        w.edge(id, child, Some("condition"));
+        id
+      },
+      Stmt::Switch(discriminant, cases) => {
+        let id = w.node("Switch");
+        let child = discriminant.write_dot(w);
+        w.edge(id, child, Some("discriminant"));
+        for (value, stmts) in cases {
+          let case = match value {
+            Some(value) => {
+              let case = w.node("Case");
+              let child = value.write_dot(w);
+              w.edge(case, child, Some("value"));
+              case
+            },
+            None => w.node("Default"),
+          };
+          w.edge(id, case, None);
+          write_stmts(w, case, stmts, None);
+        }
+        id
+      },
+      Stmt::Fun(name, params, body) => {
+        let id = w.node(&format!("Fun({}, [{}])", name, join(params)));
+        let child = body.write_dot(w);
+        w.edge(id, child, Some("body"));
+        id
+      },
+      Stmt::Class(name, superclass, methods, static_methods) => {
+        let id = match superclass {
+          Some(superclass) => w.node(&format!("Class({} < {})", name, superclass)),
+          None => w.node(&format!("Class({})", name)),
+        };
+        write_stmts(w, id, methods, None);
+        write_stmts(w, id, static_methods, Some("static"));
+        id
+      },
+      Stmt::Return(_, value) => {
+        let id = w.node("Return");
+        if let Some(value) = value {
+          let child = value.write_dot(w);
+          w.edge(id, child, None);
+        }
+        id
+      },
+      Stmt::Break(_, label) => w.node(&format!("Break{}", dot_label(label))),
+      Stmt::Continue(_, label) => w.node(&format!("Continue{}", dot_label(label))),
+    }
+  }
+}
+
+impl Expr {
+  /// Function that renders the expression as Graphviz `digraph` text
+  pub fn to_dot(&self) -> String {
+    let mut writer = DotWriter::new();
+    self.write_dot(&mut writer);
+    writer.finish()
+  }
+
+  fn write_dot(&self, w: &mut DotWriter) -> usize {
+    match self {
+      Expr::Literal(token) => w.node(&format!("Literal({})", token.lexeme)),
+      Expr::Identifier(token) => w.node(&format!("Identifier({})", token.lexeme)),
+      Expr::Unary { operator, rhs } => {
+        let id = w.node(&format!("Unary({})", operator.lexeme));
+        let child = rhs.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Expr::Binary { lhs, operator, rhs } => {
+        let id = w.node(&format!("Binary({})", operator.lexeme));
+        let child = lhs.write_dot(w);
+        w.edge(id, child, None);
+        let child = rhs.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Expr::Logical { lhs, operator, rhs } => {
+        let id = w.node(&format!("Logical({})", operator.lexeme));
+        let child = lhs.write_dot(w);
+        w.edge(id, child, None);
+        let child = rhs.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Expr::Assign { name, value } => {
+        let id = w.node(&format!("Assign({})", name.lexeme));
+        let child = value.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Expr::Ternary {
+        condition,
+        then_branch,
+        else_branch,
+      } => {
+        let id = w.node("Ternary");
+        let child = condition.write_dot(w);
+        w.edge(id, child, Some("condition"));
+        let child = then_branch.write_dot(w);
+        w.edge(id, child, Some("then"));
+        let child = else_branch.write_dot(w);
+        w.edge(id, child, Some("else"));
+        id
+      },
+      Expr::Call {
+        callee, arguments, ..
+      } => {
+        let id = w.node("Call");
+        let child = callee.write_dot(w);
+        w.edge(id, child, Some("callee"));
+        for argument in arguments {
+          let child = argument.write_dot(w);
+          w.edge(id, child, Some("argument"));
+        }
+        id
+      },
+      Expr::Grouping(expr) => {
+        let id = w.node("Grouping");
+        let child = expr.write_dot(w);
+        w.edge(id, child, None);
+        id
+      },
+      Expr::Get { object, name } => {
+        let id = w.node(&format!("Get({})", name.lexeme));
+        let child = object.write_dot(w);
+        w.edge(id, child, Some("object"));
+        id
+      },
+      Expr::Set {
+        object,
+        name,
+        value,
+      } => {
+        let id = w.node(&format!("Set({})", name.lexeme));
+        let child = object.write_dot(w);
+        w.edge(id, child, Some("object"));
+        let child = value.write_dot(w);
+        w.edge(id, child, Some("value"));
+        id
+      },
+      Expr::This(_) => w.node("This"),
+      Expr::Super(_, name) => w.node(&format!("Super({})", name.lexeme)),
+      Expr::Array { elements, .. } => {
+        let id = w.node("Array");
+        for element in elements {
+          let child = element.write_dot(w);
+          w.edge(id, child, None);
+        }
+        id
+      },
+      Expr::Template {
+        parts, expressions, ..
+      } => {
+        let id = w.node("Template");
+        for (i, part) in parts.iter().enumerate() {
+          let child = w.node(&format!("Text({:?})", part));
+          w.edge(id, child, None);
+          if let Some(expr) = expressions.get(i) {
+            let child = expr.write_dot(w);
+            w.edge(id, child, None);
+          }
+        }
+        id
+      },
+      Expr::Lambda {
+        name, params, body, ..
+      } => {
+        let id = match name {
+          Some(name) => w.node(&format!("Lambda({}, [{}])", name.lexeme, join(params))),
+          None => w.node(&format!("Lambda([{}])", join(params))),
+        };
+        let child = body.write_dot(w);
+        w.edge(id, child, Some("body"));
+        id
+      },
+      Expr::Match {
+        scrutinee, arms, ..
+      } => {
+        let id = w.node("Match");
+        let child = scrutinee.write_dot(w);
+        w.edge(id, child, Some("scrutinee"));
+        for (pattern, value) in arms {
+          let arm = match pattern {
+            Some(pattern) => {
+              let arm = w.node("Arm");
+              let child = pattern.write_dot(w);
+              w.edge(arm, child, Some("pattern"));
+              arm
+            },
+            None => w.node("Wildcard"),
+          };
+          w.edge(id, arm, None);
+          let child = value.write_dot(w);
+          w.edge(arm, child, Some("value"));
+        }
+        id
+      },
+    }
+  }
+}
+
+/// Function that adds the statements as children of `parent`, in order.
+fn write_stmts(w: &mut DotWriter, parent: usize, stmts: &[Stmt], label: Option<&str>) {
+  for stmt in stmts {
+    let child = stmt.write_dot(w);
+    w.edge(parent, child, label);
+  }
+}
+
+/// Function that renders the loop label of a node, nothing when it has none.
+fn dot_label(label: &Option<Token>) -> String {
+  label
+    .as_ref()
+    .map_or(String::new(), |label| format!("({})", label.lexeme))
+}
+
+/// Function that renders the parameters of a function node.
+fn join(params: &[Expr]) -> String {
+  params
+    .iter()
+    .map(|param| format!("{}", param))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use scanner::token::Token;
 
+use crate::stmt::Stmt;
+
 #[derive(Debug, Clone)]
 pub enum Expr {
   Literal(Token),
@@ -15,6 +17,12 @@ pub enum Expr {
     operator: Token,
     rhs: Box<Expr>,
   },
+  /// `and`/`or`, kept apart from `Binary` because the rhs only runs when the lhs does not decide
+  Logical {
+    lhs: Box<Expr>,
+    operator: Token,
+    rhs: Box<Expr>,
+  },
   Assign {
     name: Token, // must be IDENTIFIER
     value: Box<Expr>,
@@ -41,6 +49,31 @@ pub enum Expr {
   },
   This(Token),
   Super(Token, Token),
+  /// Array literal, `bracket` is the opening `[`
+  Array {
+    bracket: Token,
+    elements: Vec<Expr>,
+  },
+  /// String with `${expr}` interpolations, `parts` always holds one more entry than `expressions`
+  Template {
+    token: Token,
+    parts: Vec<String>,
+    expressions: Vec<Expr>,
+  },
+  /// Function used as a value, `keyword` is the `fun` and `body` is the block, the optional
+  /// `name` is only visible inside the body
+  Lambda {
+    keyword: Token,
+    name: Option<Token>,
+    params: Vec<Expr>,
+    body: Box<Stmt>,
+  },
+  /// `match (scrutinee) { 1 => a, _ => b }`, the arms in source order, the `_` arm has no pattern
+  Match {
+    keyword: Token,
+    scrutinee: Box<Expr>,
+    arms: Vec<(Option<Expr>, Expr)>,
+  },
 }
 
 impl fmt::Display for Expr {
@@ -50,6 +83,7 @@ impl fmt::Display for Expr {
       Expr::Identifier(token) => write!(f, "{}", token.lexeme),
       Expr::Unary { operator, rhs } => write!(f, "({} {})", operator.lexeme, rhs),
       Expr::Binary { lhs, operator, rhs } => write!(f, "⚙️ ({} {} {})", lhs, operator.lexeme, rhs),
+      Expr::Logical { lhs, operator, rhs } => write!(f, "⚙️ ({} {} {})", lhs, operator.lexeme, rhs),
       Expr::Grouping(expr) => write!(f, "({})", expr),
       Expr::Assign { name, value } => write!(f, "({} = {})", name.lexeme, value),
       Expr::Ternary {
@@ -78,51 +112,109 @@ impl fmt::Display for Expr {
       } => {
         write!(f, "({}.{} = {})", object, name.lexeme, value)
       },
-      Expr::This(token) => {
+      Expr::This(_token) => {
         write!(f, "this")
       },
-      Expr::Super(token, name) => {
+      Expr::Super(_token, name) => {
         write!(f, "super.{}", name.lexeme)
       },
+      Expr::Array { elements, .. } => {
+        let elements = elements
+          .iter()
+          .map(|e| format!("{}", e))
+          .collect::<Vec<_>>()
+          .join(", ");
+        write!(f, "[{}]", elements)
+      },
+      Expr::Template {
+        parts, expressions, ..
+      } => {
+        write!(f, "\"")?;
+        for (i, part) in parts.iter().enumerate() {
+          write!(f, "{}", part)?;
+          if let Some(expr) = expressions.get(i) {
+            write!(f, "${{{}}}", expr)?;
+          }
+        }
+        write!(f, "\"")
+      },
+      Expr::Lambda {
+        name, params, body, ..
+      } => {
+        let params = params
+          .iter()
+          .map(|p| format!("{}", p))
+          .collect::<Vec<_>>()
+          .join(", ");
+        match name {
+          Some(name) => write!(f, "Lambda({}, [{}], {})", name.lexeme, params, body),
+          None => write!(f, "Lambda([{}], {})", params, body),
+        }
+      },
+      Expr::Match {
+        scrutinee, arms, ..
+      } => {
+        let arms = arms
+          .iter()
+          .map(|(pattern, value)| match pattern {
+            Some(pattern) => format!("{} => {}", pattern, value),
+            None => format!("_ => {}", value),
+          })
+          .collect::<Vec<_>>()
+          .join(", ");
+        write!(f, "match ({}) {{ {} }}", scrutinee, arms)
+      },
     }
   }
 }
 
 // In expr.rs
 impl Expr {
-  pub(crate) fn build_tree(&self, prefix: &str, is_last: bool) {
+  pub(crate) fn build_tree(
+    &self,
+    out: &mut impl fmt::Write,
+    prefix: &str,
+    is_last: bool,
+  ) -> fmt::Result {
     let connector = if is_last { "└── " } else { "├── " };
     let extension = if is_last { "    " } else { "│   " };
 
     match self {
       Expr::Literal(token) => {
-        println!("{}{}Literal({})", prefix, connector, token.lexeme);
+        writeln!(out, "{}{}Literal({})", prefix, connector, token.lexeme)?;
       },
 
       Expr::Identifier(token) => {
-        println!("{}{}Identifier({})", prefix, connector, token.lexeme);
+        writeln!(out, "{}{}Identifier({})", prefix, connector, token.lexeme)?;
       },
 
       Expr::Binary { lhs, operator, rhs } => {
-        println!("{}{}Binary({})", prefix, connector, operator.lexeme);
+        writeln!(out, "{}{}Binary({})", prefix, connector, operator.lexeme)?;
         let new_prefix = format!("{}{}", prefix, extension);
-        lhs.build_tree(&new_prefix, false);
-        rhs.build_tree(&new_prefix, true);
+        lhs.build_tree(out, &new_prefix, false)?;
+        rhs.build_tree(out, &new_prefix, true)?;
+      },
+
+      Expr::Logical { lhs, operator, rhs } => {
+        writeln!(out, "{}{}Logical({})", prefix, connector, operator.lexeme)?;
+        let new_prefix = format!("{}{}", prefix, extension);
+        lhs.build_tree(out, &new_prefix, false)?;
+        rhs.build_tree(out, &new_prefix, true)?;
       },
 
       Expr::Unary { operator, rhs } => {
-        println!("{}{}Unary({})", prefix, connector, operator.lexeme);
-        rhs.build_tree(&format!("{}{}", prefix, extension), true);
+        writeln!(out, "{}{}Unary({})", prefix, connector, operator.lexeme)?;
+        rhs.build_tree(out, &format!("{}{}", prefix, extension), true)?;
       },
 
       Expr::Grouping(expr) => {
-        println!("{}{}Grouping", prefix, connector);
-        expr.build_tree(&format!("{}{}", prefix, extension), true);
+        writeln!(out, "{}{}Grouping", prefix, connector)?;
+        expr.build_tree(out, &format!("{}{}", prefix, extension), true)?;
       },
 
       Expr::Assign { name, value } => {
-        println!("{}{}Assign({})", prefix, connector, name.lexeme);
-        value.build_tree(&format!("{}{}", prefix, extension), true);
+        writeln!(out, "{}{}Assign({})", prefix, connector, name.lexeme)?;
+        value.build_tree(out, &format!("{}{}", prefix, extension), true)?;
       },
 
       Expr::Call {
@@ -130,17 +222,17 @@ impl Expr {
         paren: _,
         arguments,
       } => {
-        println!("{}{}Call", prefix, connector);
+        writeln!(out, "{}{}Call", prefix, connector)?;
         let new_prefix = format!("{}{}", prefix, extension);
 
-        println!("{}├── callee:", new_prefix);
-        callee.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── callee:", new_prefix)?;
+        callee.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
         if !arguments.is_empty() {
-          println!("{}└── arguments:", new_prefix);
+          writeln!(out, "{}└── arguments:", new_prefix)?;
           let arg_prefix = format!("{}    ", new_prefix);
           for (i, arg) in arguments.iter().enumerate() {
-            arg.build_tree(&arg_prefix, i == arguments.len() - 1);
+            arg.build_tree(out, &arg_prefix, i == arguments.len() - 1)?;
           }
         }
       },
@@ -150,23 +242,23 @@ impl Expr {
         then_branch,
         else_branch,
       } => {
-        println!("{}{}Ternary", prefix, connector);
+        writeln!(out, "{}{}Ternary", prefix, connector)?;
         let new_prefix = format!("{}{}", prefix, extension);
 
-        println!("{}├── condition:", new_prefix);
-        condition.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── condition:", new_prefix)?;
+        condition.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
-        println!("{}├── then:", new_prefix);
-        then_branch.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── then:", new_prefix)?;
+        then_branch.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
-        println!("{}└── else:", new_prefix);
-        else_branch.build_tree(&format!("{}    ", new_prefix), true);
+        writeln!(out, "{}└── else:", new_prefix)?;
+        else_branch.build_tree(out, &format!("{}    ", new_prefix), true)?;
       },
       Expr::Get { object, name } => {
-        println!("{}{}Get({})", prefix, connector, name.lexeme);
+        writeln!(out, "{}{}Get({})", prefix, connector, name.lexeme)?;
         let new_prefix = format!("{}{}", prefix, extension);
-        println!("{}└── object:", new_prefix);
-        object.build_tree(&format!("{}    ", new_prefix), true);
+        writeln!(out, "{}└── object:", new_prefix)?;
+        object.build_tree(out, &format!("{}    ", new_prefix), true)?;
       },
 
       Expr::Set {
@@ -174,21 +266,102 @@ impl Expr {
         name,
         value,
       } => {
-        println!("{}{}Set({})", prefix, connector, name.lexeme);
+        writeln!(out, "{}{}Set({})", prefix, connector, name.lexeme)?;
         let new_prefix = format!("{}{}", prefix, extension);
 
-        println!("{}├── object:", new_prefix);
-        object.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── object:", new_prefix)?;
+        object.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
-        println!("{}└── value:", new_prefix);
-        value.build_tree(&format!("{}    ", new_prefix), true);
+        writeln!(out, "{}└── value:", new_prefix)?;
+        value.build_tree(out, &format!("{}    ", new_prefix), true)?;
+      },
+      Expr::This(_token) => {
+        writeln!(out, "{}{}This", prefix, connector)?;
+      },
+      Expr::Super(_token, _name) => {
+        writeln!(out, "{}{}Super", prefix, connector)?;
+      },
+      Expr::Array { elements, .. } => {
+        writeln!(out, "{}{}Array", prefix, connector)?;
+        let new_prefix = format!("{}{}", prefix, extension);
+        for (i, element) in elements.iter().enumerate() {
+          element.build_tree(out, &new_prefix, i == elements.len() - 1)?;
+        }
       },
-      Expr::This(token) => {
-        println!("{}{}This", prefix, connector);
+      Expr::Template {
+        parts, expressions, ..
+      } => {
+        writeln!(out, "{}{}Template", prefix, connector)?;
+        let new_prefix = format!("{}{}", prefix, extension);
+        for (i, part) in parts.iter().enumerate() {
+          let is_last_part = i == parts.len() - 1;
+          writeln!(
+            out,
+            "{}{}Text({:?})",
+            new_prefix,
+            if is_last_part {
+              "└── "
+            } else {
+              "├── "
+            },
+            part
+          )?;
+          if let Some(expr) = expressions.get(i) {
+            expr.build_tree(out, &new_prefix, false)?;
+          }
+        }
+      },
+      Expr::Lambda {
+        name, params, body, ..
+      } => {
+        let params = params
+          .iter()
+          .map(|p| format!("{}", p))
+          .collect::<Vec<_>>()
+          .join(", ");
+
+        match name {
+          Some(name) => writeln!(
+            out,
+            "{}{}Lambda({}, [{}])",
+            prefix, connector, name.lexeme, params
+          )?,
+          None => writeln!(out, "{}{}Lambda([{}])", prefix, connector, params)?,
+        }
+        let new_prefix = format!("{}{}", prefix, extension);
+        writeln!(out, "{}└── body:", new_prefix)?;
+        body.build_tree(out, &format!("{}    ", new_prefix), true)?;
       },
-      Expr::Super(token, name) => {
-        println!("{}{}Super", prefix, connector);
+      Expr::Match {
+        scrutinee, arms, ..
+      } => {
+        writeln!(out, "{}{}Match", prefix, connector)?;
+        let new_prefix = format!("{}{}", prefix, extension);
+
+        writeln!(out, "{}├── scrutinee:", new_prefix)?;
+        scrutinee.build_tree(out, &format!("{}│   ", new_prefix), true)?;
+
+        for (i, (pattern, value)) in arms.iter().enumerate() {
+          let is_last_arm = i == arms.len() - 1;
+          let (arm_connector, arm_extension) = if is_last_arm {
+            ("└── ", "    ")
+          } else {
+            ("├── ", "│   ")
+          };
+          let arm_prefix = format!("{}{}", new_prefix, arm_extension);
+
+          match pattern {
+            Some(pattern) => {
+              writeln!(out, "{}{}arm:", new_prefix, arm_connector)?;
+              pattern.build_tree(out, &arm_prefix, false)?;
+            },
+            None => writeln!(out, "{}{}wildcard arm:", new_prefix, arm_connector)?,
+          }
+          value.build_tree(out, &arm_prefix, true)?;
+        }
       },
     }
+
+    Ok(())
   }
 }
@@ -25,11 +25,14 @@
 *                | continue_stmt
 *                | print_stmt
 *                | while_stmt
+*                | labeled_stmt
 *                | block ;
 *
-* break_stmt     → "break" ";" ;
+* labeled_stmt   → IDENTIFIER ":" ( for_stmt | while_stmt | do_while_stmt ) ;
 *
-* continue_stmt  → "continue" ";" ;
+* break_stmt     → "break" IDENTIFIER? ";" ;
+*
+* continue_stmt  → "continue" IDENTIFIER? ";" ;
 *
 * return_stmt    → "return" expr? ";" ;
 *
@@ -51,46 +54,65 @@
 * assignment     → (call ".")? IDENTIFIER "=" assignment
 *                | ternary ;
 *
-* ternary        → logical_or ( "?" expr ":" ternary )? ;
+* ternary        → logical_or ( "?" assignment ":" ternary )? ;
 *
 * logical_or     → logical_and ( "or" logical_and )* ;
 *
-* logical_and    → equality ( "and" equality )* ;
+* logical_and    → bit_or ( "and" bit_or )* ;
+*
+* bit_or         → bit_xor ( "|" bit_xor )* ;
+*
+* bit_xor        → bit_and ( "^" bit_and )* ;
+*
+* bit_and        → equality ( "&" equality )* ;
 *
 * equality       → comparison ( ( "!=" | "==" ) comparison )* ;
 *
-* comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
+* comparison     → shift ( ( ">" | ">=" | "<" | "<=" ) shift )* ;
+*
+* shift          → term ( ( "<<" | ">>" ) term )* ;
 *
 * term           → factor ( ( "-" | "+" ) factor )* ;
 *
 * factor         → unary ( ( "/" | "*" | "%" ) unary )* ;
 *
-* unary          → ( "!" | "-" ) unary
+* unary          → ( "!" | "-" | "typeof" ) unary
 *                | call ;
 *
 * call           → primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
 *
 * arguments      → expr ( "," expr )* ;
 *
-* primary        → NUMBER | STRING | IDENTIFIER
+* primary        → NUMBER | STRING | TEMPLATE | IDENTIFIER
 *                | "true" | "false" | "nil" | "this" | ( "super" "." IDENTIFIER )
-*                | "(" expr ")" ;
+*                | "(" expr ")" | "[" arguments? "]" | match ;
+*
+* match          → "match" "(" expr ")" "{" ( arm ( "," arm )* ","? )? "}" ;
+*
+* arm            → ( "-"? literal | "_" ) "=>" assignment ;
 *
 */
 
+use std::{collections::HashMap, ops::Range};
+
 use diagnostic::{
   diagnostic::{Diagnostic, Label, Span},
   diagnostic_code::DiagnosticCode,
   DiagnosticEngine,
 };
-use scanner::token::{
-  types::{Literal, TokenType},
-  Token,
+use scanner::{
+  token::{
+    types::{Literal, TokenType},
+    Token,
+  },
+  unescape, Scanner,
 };
 
 use crate::{expr::Expr, stmt::Stmt};
 
+pub mod dot;
 pub mod expr;
+pub mod source;
 pub mod stmt;
 
 pub struct Parser {
@@ -100,6 +122,36 @@ pub struct Parser {
   pub current: usize,
   /// List of exprs
   pub ast: Vec<Stmt>,
+  /// Node id of every statement in `ast`, at the same index
+  pub ast_ids: Vec<NodeId>,
+  /// Set when parsing ran into the end of the tokens, more input could complete the source
+  pub needs_more_input: bool,
+  /// Accept a missing `;` after the last statement of the file with a warning
+  pub lenient_eof: bool,
+  /// Print the tree of every statement to stdout as soon as it's parsed
+  pub debug_print: bool,
+  /// Warn about a `,` right before the `)` of an argument or parameter list, which is accepted
+  pub warn_trailing_comma: bool,
+  /// Byte range in the source of every node built so far, from its first to its last token
+  node_spans: HashMap<NodeId, Range<usize>>,
+  /// Every node built so far, keyed by the id it was given
+  nodes: HashMap<NodeId, Node>,
+  /// Id handed to the next recorded node
+  next_node_id: NodeId,
+}
+
+/// Most arguments a call and parameters a function can have
+pub const MAX_ARGUMENTS: usize = 255;
+
+/// Id of a parsed statement or expression, handed out in the order the nodes are completed so a
+/// node always comes after its children
+pub type NodeId = usize;
+
+/// A statement or expression recorded under a `NodeId`
+#[derive(Debug, Clone)]
+pub enum Node {
+  Stmt(Box<Stmt>),
+  Expr(Expr),
 }
 
 impl Parser {
@@ -109,23 +161,94 @@ impl Parser {
       tokens,
       current: 0,
       ast: Vec::new(),
+      ast_ids: Vec::new(),
+      needs_more_input: false,
+      lenient_eof: false,
+      debug_print: false,
+      warn_trailing_comma: true,
+      node_spans: HashMap::new(),
+      nodes: HashMap::new(),
+      next_node_id: 0,
     }
   }
 
+  /// Function that parses the tokens into `self.ast`, the trees are only printed with
+  /// `debug_print` set
   pub fn parse(&mut self, engine: &mut DiagnosticEngine) {
-    while !self.is_eof() {
+    while !self.is_eof() && !engine.should_abort() {
+      let start = self.current;
       match self.parse_program(engine) {
         Ok(stmt) => {
-          stmt.print_tree();
+          if self.debug_print {
+            stmt.print_tree();
+          }
+          // A statement is recorded after all of its children
+          self.ast_ids.push(self.next_node_id - 1);
           self.ast.push(stmt);
         },
-        Err(_) => self.synchronize(),
+        Err(_) => self.synchronize(start),
       }
     }
   }
+
+  /// Function that parses the tokens without printing anything and returns the tree together
+  /// with the diagnostics, for embedding the parser in tests and tooling
+  pub fn into_ast(mut self) -> (Vec<Stmt>, Vec<Diagnostic>) {
+    let mut engine = DiagnosticEngine::new();
+    self.debug_print = false;
+    self.parse(&mut engine);
+
+    (self.ast, engine.get_diagnostics().to_vec())
+  }
 }
 
 impl Parser {
+  /// Function that returns the byte range of a node, from the start of its first token to the end
+  /// of its last one
+  pub fn node_span(&self, id: NodeId) -> Option<Range<usize>> {
+    self.node_spans.get(&id).cloned()
+  }
+
+  /// Function that returns the statement or expression recorded under `id`
+  pub fn node(&self, id: NodeId) -> Option<&Node> {
+    self.nodes.get(&id)
+  }
+
+  /// Function that returns the smallest node whose span holds the byte `offset`, the innermost
+  /// node wins when a wrapper covers exactly the same tokens
+  pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+    self
+      .node_spans
+      .iter()
+      .filter(|(_, span)| span.contains(&offset))
+      .min_by_key(|(id, span)| (span.len(), **id))
+      .map(|(id, _)| *id)
+  }
+
+  /// Function that records `node` as made of the tokens from `start` to the last consumed one,
+  /// every call hands out a fresh id even when no tokens were consumed
+  fn record_node(&mut self, start: usize, node: Node) -> NodeId {
+    let id = self.next_node_id;
+    self.next_node_id += 1;
+
+    if self.current > start {
+      let range = self.tokens[start].range.start..self.tokens[self.current - 1].range.end;
+      self.node_spans.insert(id, range);
+      self.nodes.insert(id, node);
+    }
+    id
+  }
+
+  /// Function that records a copy of an expression node
+  fn record_expr(&mut self, start: usize, expr: &Expr) -> NodeId {
+    self.record_node(start, Node::Expr(expr.clone()))
+  }
+
+  /// Function that records a copy of a statement node
+  fn record_stmt(&mut self, start: usize, stmt: &Stmt) -> NodeId {
+    self.record_node(start, Node::Stmt(Box::new(stmt.clone())))
+  }
+
   fn parse_program(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
     self.parse_declaration(engine)
   }
@@ -136,12 +259,20 @@ impl Parser {
       return Err(());
     }
 
-    match self.current_token().token_type {
-      TokenType::Var => self.parse_var_stmt(engine),
-      TokenType::Fun => self.parse_fun_stmt(engine),
-      TokenType::Class => self.parse_class_stmt(engine),
-      _ => self.parse_stmt(engine),
-    }
+    let start = self.current;
+    let stmt = match self.current_token().token_type {
+      TokenType::Var => self.parse_var_stmt(engine)?,
+      // A nameless `fun` is an anonymous function expression, like `fun () { ... }();`
+      TokenType::Fun if self.peek(1).token_type != TokenType::LeftParen => {
+        self.parse_fun_stmt(engine)?
+      },
+      TokenType::Class => self.parse_class_stmt(engine)?,
+      // `parse_stmt` records its own node
+      _ => return self.parse_stmt(engine),
+    };
+
+    self.record_stmt(start, &stmt);
+    Ok(stmt)
   }
 
   fn parse_class_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
@@ -201,40 +332,115 @@ impl Parser {
 
   fn parse_fun_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
     self.expect(TokenType::Fun, engine)?;
-    let fn_name = if !matches!(self.current_token().token_type, TokenType::LeftParen) {
-      Some(self.parse_primary(engine)?)
+    let name = self.parse_primary(engine)?;
+
+    self.advance(); // consume the "("
+    let params = if matches!(self.current_token().token_type, TokenType::RightParen) {
+      vec![]
+    } else {
+      self.parse_parameters(engine)?
+    };
+
+    self.advance(); // consume the ")"
+    let body = self.parse_block_stmt(engine)?;
+
+    Ok(Stmt::Fun(name, params, Box::new(body)))
+  }
+
+  /// Function that parses a function used as a value: "fun" IDENTIFIER? "(" parameters? ")" block
+  fn parse_lambda(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let keyword = self.current_token();
+    self.expect(TokenType::Fun, engine)?;
+
+    let name = if matches!(self.current_token().token_type, TokenType::Identifier) {
+      Some(self.expect(TokenType::Identifier, engine)?)
     } else {
       None
     };
+    self.expect(TokenType::LeftParen, engine)?;
 
-    self.advance(); // consume the "("
     let params = if matches!(self.current_token().token_type, TokenType::RightParen) {
       vec![]
     } else {
-      let params = self.parse_parameters(engine)?;
-      params
+      self.parse_parameters(engine)?
     };
 
-    self.advance(); // consume the ")"
+    self.expect(TokenType::RightParen, engine)?;
     let body = self.parse_block_stmt(engine)?;
 
-    match fn_name {
-      Some(name) => Ok(Stmt::Fun(name, params, Box::new(body))),
+    Ok(Expr::Lambda {
+      keyword,
+      name,
+      params,
+      body: Box::new(body),
+    })
+  }
 
-      None => {
-        let uuid = uuid::Uuid::now_v7();
-        Ok(Stmt::Fun(
-          Expr::Identifier(Token {
-            token_type: TokenType::Identifier,
-            lexeme: uuid.to_string().split_once('-').unwrap().0.to_string(),
-            literal: Literal::Nil,
-            position: (0, 0),
-          }),
-          params,
-          Box::new(body),
-        ))
+  /// Function that parses `match (expr) { pattern => value, ... }`, the `_` arm matches anything
+  fn parse_match(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let keyword = self.expect(TokenType::Match, engine)?;
+    self.expect(TokenType::LeftParen, engine)?;
+    let scrutinee = self.parse_expr(engine)?;
+    self.expect(TokenType::RightParen, engine)?;
+    self.expect(TokenType::LeftBrace, engine)?;
+
+    let mut arms = Vec::new();
+    while !self.is_eof() && !self.matches_token(TokenType::RightBrace) {
+      let pattern = if self.matches_token(TokenType::Underscore) {
+        self.advance();
+        None
+      } else {
+        Some(self.parse_match_pattern(engine)?)
+      };
+      self.expect(TokenType::FatArrow, engine)?;
+      let value = self.parse_assignment(engine)?;
+      arms.push((pattern, value));
+
+      if !self.matches_token(TokenType::Comma) {
+        break;
+      }
+      self.advance(); // consume ','
+    }
+
+    self.expect(TokenType::RightBrace, engine)?;
+    Ok(Expr::Match {
+      keyword,
+      scrutinee: Box::new(scrutinee),
+      arms,
+    })
+  }
+
+  /// Function that parses the pattern of a match arm, only literals and negative numbers are
+  /// patterns
+  fn parse_match_pattern(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let first = self.current_token();
+    let pattern = self.parse_unary(engine)?;
+
+    let is_literal = match &pattern {
+      Expr::Literal(_) => true,
+      Expr::Unary { operator, rhs } => {
+        operator.token_type == TokenType::Minus
+          && matches!(&**rhs, Expr::Literal(token) if token.token_type == TokenType::Number)
       },
+      _ => false,
+    };
+
+    if !is_literal {
+      let diagnostic = Diagnostic::new(
+        DiagnosticCode::UnexpectedToken,
+        "Match patterns must be literals".to_string(),
+      )
+      .with_label(Label::primary(
+        Self::token_span(&first),
+        Some("expected a number, string, boolean, nil or '_' here".to_string()),
+      ))
+      .with_help("compare against other values with `if` or a ternary".to_string());
+
+      engine.emit(diagnostic);
+      return Err(());
     }
+
+    Ok(pattern)
   }
 
   fn parse_parameters(&mut self, engine: &mut DiagnosticEngine) -> Result<Vec<Expr>, ()> {
@@ -265,73 +471,95 @@ impl Parser {
     check_iditifer(&expr, self, engine);
     args.push(expr);
 
-    if args.len() >= 255 {
-      let diagnostic = Diagnostic::new(
-        DiagnosticCode::WrongNumberOfArguments,
-        "Too many arguments".to_string(),
-      )
-      .with_label(Label::primary(
-        self.current_token().to_span(),
-        Some("too many arguments".to_string()),
-      ));
-      engine.emit(diagnostic);
-
-      return Err(());
-    }
-
     // Parse remaining arguments separated by commas
     while !self.is_eof() && self.matches_token(TokenType::Comma) {
+      let comma = self.current_token();
       self.advance(); // consume ","
 
       // Check for trailing comma: foo(1, 2, )
       if self.matches_token(TokenType::RightParen) {
-        // Could emit a warning here about trailing comma
+        self.trailing_comma(&comma, engine);
         break;
       }
 
+      let first = self.current_token();
       let expr = self.parse_primary(engine)?;
       check_iditifer(&expr, self, engine);
       args.push(expr);
+      self.too_many_arguments(args.len(), &first, "parameters", engine);
     }
 
     Ok(args)
   }
 
   fn parse_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
-    match self.current_token().token_type {
-      TokenType::For => self.parse_for_stmt(engine),
+    let start = self.current;
+    let stmt = match self.current_token().token_type {
+      TokenType::Identifier
+        if self.peek(1).token_type == TokenType::Colon
+          && matches!(
+            self.peek(2).token_type,
+            TokenType::For | TokenType::While | TokenType::Do
+          ) =>
+      {
+        self.parse_labeled_stmt(engine)
+      },
+      TokenType::For => self.parse_for_stmt(None, engine),
       TokenType::Break => self.parse_break_stmt(engine),
       TokenType::Continue => self.parse_continue_stmt(engine),
       TokenType::If => self.parse_if_stmt(engine),
       TokenType::LeftBrace => self.parse_block_stmt(engine),
       TokenType::Return => self.parse_return_stmt(engine),
-      TokenType::While => self.parse_while_stmt(engine),
+      TokenType::While => self.parse_while_stmt(None, engine),
+      TokenType::Do => self.parse_do_while_stmt(None, engine),
+      TokenType::Switch => self.parse_switch_stmt(engine),
       _ => self.parse_expr_stmt(engine),
+    }?;
+
+    self.record_stmt(start, &stmt);
+    Ok(stmt)
+  }
+
+  /// Function that parses `label: while (...) ...`, the label names the loop for the `break` and
+  /// `continue` statements nested in it
+  fn parse_labeled_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
+    let label = self.expect(TokenType::Identifier, engine)?;
+    self.expect(TokenType::Colon, engine)?;
+
+    match self.current_token().token_type {
+      TokenType::For => self.parse_for_stmt(Some(label), engine),
+      TokenType::While => self.parse_while_stmt(Some(label), engine),
+      _ => self.parse_do_while_stmt(Some(label), engine),
     }
   }
 
+  /// Function that consumes the optional label after `break` or `continue`
+  fn parse_loop_label(&mut self) -> Option<Token> {
+    if !self.matches_token(TokenType::Identifier) {
+      return None;
+    }
+
+    let label = self.current_token();
+    self.advance();
+    Some(label)
+  }
+
   fn parse_break_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
     let token = self.current_token();
     self.expect(TokenType::Break, engine)?;
+    let label = self.parse_loop_label();
     self.expect(TokenType::SemiColon, engine)?;
 
-    // Ok(Stmt::Block(Box::new(vec![Stmt::Break(
-    //   self.current_token(),
-    // )])))
-
-    Ok(Stmt::Break(token))
+    Ok(Stmt::Break(token, label))
   }
 
   fn parse_continue_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
     let token = self.current_token();
     self.expect(TokenType::Continue, engine)?;
+    let label = self.parse_loop_label();
     self.expect(TokenType::SemiColon, engine)?;
 
-    // Ok(Stmt::Block(Box::new(vec![Stmt::Continue(
-    //   self.current_token(),
-    // )])))
-
-    Ok(Stmt::Continue(token))
+    Ok(Stmt::Continue(token, label))
   }
 
   fn parse_return_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
@@ -339,20 +567,24 @@ impl Parser {
     self.expect(TokenType::Return, engine)?;
 
     if matches!(self.current_token().token_type, TokenType::SemiColon) {
-      return Ok(Stmt::Return(self.current_token(), None));
+      let semicolon = self.current_token();
+      self.advance();
+      return Ok(Stmt::Return(semicolon, None));
     }
 
     let value = self.parse_expr(engine)?;
 
     if !matches!(self.current_token().token_type, TokenType::SemiColon) {
+      let value_end = self.tokens[self.current - 1].clone();
       let diagnostic = Diagnostic::new(
         DiagnosticCode::MissingSemicolon,
         "Expected ';' after return value".to_string(),
       )
       .with_label(Label::primary(
-        self.current_token().to_span(),
-        Some("semicolon missing here".to_string()),
-      ));
+        Self::token_span(&value_end),
+        Some("expected ';' after this".to_string()),
+      ))
+      .with_suggestion(self.insertion_after_prev(), ";".to_string());
 
       engine.emit(diagnostic);
       return Err(());
@@ -363,27 +595,20 @@ impl Parser {
   }
 
   fn parse_var_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
-    self.expect(TokenType::Var, engine)?;
+    let keyword = self.expect(TokenType::Var, engine)?;
 
     // Check for identifier
     if !matches!(self.current_token().token_type, TokenType::Identifier) {
-      let mut span = self.current_token().to_span();
-      span.line += 1;
-      span.column -= 1;
       let diagnostic = Diagnostic::new(
         DiagnosticCode::ExpectedIdentifier,
         "Expected identifier after 'var'".to_string(),
       )
       .with_label(Label::primary(
-        span.clone(),
+        Self::token_span(&self.current_token()),
         Some("expected variable name here".to_string()),
       ))
       .with_label(Label::secondary(
-        Span {
-          length: 3,
-          column: 0,
-          ..span
-        },
+        Self::token_span(&keyword),
         Some("'var' keyword here".to_string()),
       ));
 
@@ -396,7 +621,7 @@ impl Parser {
 
     if matches!(self.current_token().token_type, TokenType::SemiColon) {
       self.advance(); // consume ;
-      return Ok(Stmt::VarDecl(identifier, None));
+      Ok(Stmt::VarDecl(identifier, None))
     } else if matches!(self.current_token().token_type, TokenType::Equal) {
       self.advance(); // consume =
                       // TODO: parse the caller in the declaration
@@ -405,29 +630,23 @@ impl Parser {
       let expr;
 
       if matches!(self.current_token().token_type, TokenType::Identifier)
-        && self.tokens[self.current + 1].token_type == TokenType::LeftParen
+        && self.peek(1).token_type == TokenType::LeftParen
       {
         let callee = self.parse_call(engine)?;
         expr = callee;
-      } else if matches!(self.current_token().token_type, TokenType::Fun) {
-        is_function = true;
-        let fun = self.parse_fun_stmt(engine)?;
-
-        if let Stmt::Fun(name, _, _) = &fun {
-          expr = name.clone();
-        } else {
-          return Err(());
-        }
-        self.ast.push(fun);
       } else {
         expr = self.parse_expr(engine)?;
+        // Like a declared one, a function value doesn't need the `;` after its body
+        is_function = matches!(expr, Expr::Lambda { .. });
       }
 
       if matches!(self.current_token().token_type, TokenType::SemiColon) || is_function {
-        if !is_function {
+        if matches!(self.current_token().token_type, TokenType::SemiColon) {
           self.advance(); // consume ;
         }
-        return Ok(Stmt::VarDecl(identifier, Some(expr)));
+        Ok(Stmt::VarDecl(identifier, Some(expr)))
+      } else if self.lenient_missing_semicolon(engine) {
+        Ok(Stmt::VarDecl(identifier, Some(expr)))
       } else {
         // Missing semicolon diagnostic
         let diagnostic = Diagnostic::new(
@@ -440,15 +659,13 @@ impl Parser {
         ));
 
         engine.emit(diagnostic);
-        return Err(());
+        Err(())
       }
+    } else if self.lenient_missing_semicolon(engine) {
+      Ok(Stmt::VarDecl(identifier, None))
     } else {
       // Expected = or ;
       let token = self.current_token();
-      let mut span = token.to_span();
-      span.length = 1;
-      span.column = identifier.position.1;
-      let len = identifier.lexeme.len();
       let diagnostic = Diagnostic::new(
         DiagnosticCode::UnexpectedToken,
         format!(
@@ -457,26 +674,25 @@ impl Parser {
         ),
       )
       .with_label(Label::primary(
-        span,
+        Self::token_span(&token),
         Some("expected '=' or ';' here".to_string()),
       ))
       .with_label(Label::secondary(
-        Span {
-          length: len,
-          column: identifier.position.1 - len,
-          ..token.to_span()
-        },
-        // identifier.to_span(),
+        Self::token_span(&identifier),
         Some("variable declared here".to_string()),
       ));
 
       engine.emit(diagnostic);
-      return Err(());
+      Err(())
     }
   }
 
-  fn parse_for_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
-    self.expect(TokenType::For, engine)?;
+  fn parse_for_stmt(
+    &mut self,
+    label: Option<Token>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<Stmt, ()> {
+    let keyword = self.expect(TokenType::For, engine)?;
     self.expect(TokenType::LeftParen, engine)?;
 
     // Parse initializer
@@ -510,21 +726,25 @@ impl Parser {
     };
 
     // Parse body
-    let mut body = self.parse_stmt(engine)?;
-
-    // Desugar: add increment to body
-    if let Some(inc) = increment {
-      body = Stmt::Block(Box::new(vec![body, Stmt::Expr(inc)]));
-    }
+    let body = self.parse_stmt(engine)?;
 
     // Desugar: wrap in while loop
-    let condition_expr = condition.unwrap_or(Expr::Literal(Token::new(
-      TokenType::True,
-      "true".to_string(),
-      Literal::Boolean,
-      (0, 0),
-    )));
-    body = Stmt::While(Box::new(condition_expr), Box::new(body));
+    let condition_expr = condition.unwrap_or(Expr::Literal(
+      Token::new(
+        TokenType::True,
+        "true".to_string(),
+        Literal::Boolean,
+        (0, 0),
+      )
+      .with_file(keyword.file),
+    ));
+    // Desugar: the increment runs after the body, `continue` included
+    let body = Stmt::While(
+      Box::new(condition_expr),
+      Box::new(body),
+      increment.map(Box::new),
+      label,
+    );
 
     // Desugar: add initializer
     if let Some(init) = initializer {
@@ -534,14 +754,111 @@ impl Parser {
     }
   }
 
-  fn parse_while_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
+  fn parse_while_stmt(
+    &mut self,
+    label: Option<Token>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<Stmt, ()> {
     self.expect(TokenType::While, engine)?;
     self.expect(TokenType::LeftParen, engine)?;
     let condition = self.parse_expr(engine)?;
     self.expect(TokenType::RightParen, engine)?;
     let stmt = self.parse_stmt(engine)?;
 
-    Ok(Stmt::While(Box::new(condition), Box::new(stmt)))
+    Ok(Stmt::While(
+      Box::new(condition),
+      Box::new(stmt),
+      None,
+      label,
+    ))
+  }
+
+  fn parse_do_while_stmt(
+    &mut self,
+    label: Option<Token>,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<Stmt, ()> {
+    self.expect(TokenType::Do, engine)?;
+    let stmt = self.parse_stmt(engine)?;
+    self.expect(TokenType::While, engine)?;
+    self.expect(TokenType::LeftParen, engine)?;
+    let condition = self.parse_expr(engine)?;
+    self.expect(TokenType::RightParen, engine)?;
+    self.expect(TokenType::SemiColon, engine)?;
+
+    Ok(Stmt::DoWhile(Box::new(stmt), Box::new(condition), label))
+  }
+
+  /// Function that parses `switch (expr) { case value: ... default: ... }`, the statements of a
+  /// case run up to the next `case`, `default` or the closing `}` and never fall through.
+  fn parse_switch_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
+    self.expect(TokenType::Switch, engine)?;
+    self.expect(TokenType::LeftParen, engine)?;
+    let discriminant = self.parse_expr(engine)?;
+    self.expect(TokenType::RightParen, engine)?;
+    self.expect(TokenType::LeftBrace, engine)?;
+
+    let mut cases = Vec::new();
+    let mut default: Option<Token> = None;
+    let mut duplicate_default = false;
+
+    while !self.is_eof() && !self.matches_token(TokenType::RightBrace) {
+      let token = self.current_token();
+      let value = match token.token_type {
+        TokenType::Case => {
+          self.advance();
+          Some(self.parse_expr(engine)?)
+        },
+        TokenType::Default => {
+          self.advance();
+          if let Some(first) = &default {
+            let diagnostic = Diagnostic::new(
+              DiagnosticCode::DuplicateDefault,
+              "Multiple 'default' cases in one switch".to_string(),
+            )
+            .with_label(Label::primary(
+              Self::token_span(&token),
+              Some("second 'default' here".to_string()),
+            ))
+            .with_label(Label::secondary(
+              Self::token_span(first),
+              Some("first 'default' here".to_string()),
+            ))
+            .with_help("a switch can only have one default case".to_string());
+
+            engine.emit(diagnostic);
+            duplicate_default = true;
+          } else {
+            default = Some(token);
+          }
+          None
+        },
+        _ => {
+          self.error_expected_token(TokenType::Case, token, engine);
+          return Err(());
+        },
+      };
+      self.expect(TokenType::Colon, engine)?;
+
+      let mut stmts = Vec::new();
+      while !self.is_eof()
+        && !self.matches_token(TokenType::Case)
+        && !self.matches_token(TokenType::Default)
+        && !self.matches_token(TokenType::RightBrace)
+      {
+        stmts.push(self.parse_declaration(engine)?);
+      }
+
+      cases.push((value, stmts));
+    }
+
+    self.expect(TokenType::RightBrace, engine)?;
+
+    if duplicate_default {
+      return Err(());
+    }
+
+    Ok(Stmt::Switch(Box::new(discriminant), cases))
   }
 
   fn parse_if_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
@@ -589,10 +906,74 @@ impl Parser {
 
   fn parse_expr_stmt(&mut self, engine: &mut DiagnosticEngine) -> Result<Stmt, ()> {
     let expr = self.parse_expr(engine)?;
-    self.expect(TokenType::SemiColon, engine)?;
+    if !self.lenient_missing_semicolon(engine) {
+      self.expect(TokenType::SemiColon, engine)?;
+    }
     Ok(Stmt::Expr(expr))
   }
 
+  /// Function that accepts the missing `;` of the last statement when `lenient_eof` is set,
+  /// returns `false` when the `;` is still required
+  fn lenient_missing_semicolon(&mut self, engine: &mut DiagnosticEngine) -> bool {
+    if !self.lenient_eof || !self.is_eof() {
+      return false;
+    }
+
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::MissingTrailingSemicolon,
+      "Missing ';' at the end of the file".to_string(),
+    )
+    .with_label(Label::primary(
+      Self::token_span(&self.tokens[self.current.saturating_sub(1)]),
+      Some("add ';' after this".to_string()),
+    ));
+    engine.emit(diagnostic);
+
+    true
+  }
+
+  /// Function that reports the list growing past `MAX_ARGUMENTS` once, at the first token of the
+  /// entry that went over, the rest of the list is still parsed
+  fn too_many_arguments(
+    &mut self,
+    count: usize,
+    first: &Token,
+    what: &str,
+    engine: &mut DiagnosticEngine,
+  ) {
+    if count != MAX_ARGUMENTS + 1 {
+      return;
+    }
+
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::WrongNumberOfArguments,
+      format!("Can't have more than {} {}", MAX_ARGUMENTS, what),
+    )
+    .with_label(Label::primary(
+      Self::token_span(first),
+      Some(format!("this is the {}th", MAX_ARGUMENTS + 1)),
+    ));
+    engine.emit(diagnostic);
+  }
+
+  /// Function that reports the `,` closing an argument or parameter list, the list is accepted
+  /// either way
+  fn trailing_comma(&mut self, comma: &Token, engine: &mut DiagnosticEngine) {
+    if !self.warn_trailing_comma {
+      return;
+    }
+
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::TrailingComma,
+      "Trailing comma before ')'".to_string(),
+    )
+    .with_label(Label::primary(
+      Self::token_span(comma),
+      Some("remove this ','".to_string()),
+    ));
+    engine.emit(diagnostic);
+  }
+
   // Helper method to check if current token matches a type
   fn matches_token(&self, token_type: TokenType) -> bool {
     !self.is_eof() && self.tokens[self.current].token_type == token_type
@@ -607,6 +988,7 @@ impl Parser {
 
   // Function that handles ,
   fn parse_comma(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let mut lhs = self.parse_assignment(engine)?;
 
     while !self.is_eof() {
@@ -623,6 +1005,7 @@ impl Parser {
             operator: token,
             rhs: Box::new(rhs),
           };
+          self.record_expr(start, &lhs);
         },
         _ => break,
       }
@@ -633,6 +1016,7 @@ impl Parser {
 
   /// Function that handles the assignments (=)
   fn parse_assignment(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let lhs = self.parse_ternary(engine)?;
 
     if !self.is_eof() && matches!(self.current_token().token_type, TokenType::Equal) {
@@ -640,20 +1024,73 @@ impl Parser {
 
       let rhs = self.parse_assignment(engine)?;
 
-      if let Expr::Identifier(name) = lhs {
-        return Ok(Expr::Assign {
-          name: name,
+      let expr = if let Expr::Identifier(name) = lhs {
+        Expr::Assign {
+          name,
           value: Box::new(rhs),
-        });
+        }
       } else if let Expr::Get { object, name } = lhs {
-        return Ok(Expr::Set {
+        Expr::Set {
           name,
           object,
           value: Box::new(rhs),
-        });
+        }
       } else {
         self.error_unexpected_token(engine, "in assignment, left side must be an identifier");
         return Err(());
+      };
+      self.record_expr(start, &expr);
+      return Ok(expr);
+    }
+
+    if !self.is_eof() {
+      let token = self.current_token();
+      let operator = match token.token_type {
+        TokenType::PlusEqual => Some((TokenType::Plus, "+")),
+        TokenType::MinusEqual => Some((TokenType::Minus, "-")),
+        TokenType::MultiplyEqual => Some((TokenType::Multiply, "*")),
+        TokenType::DivideEqual => Some((TokenType::Divide, "/")),
+        _ => None,
+      };
+
+      if let Some((token_type, lexeme)) = operator {
+        self.advance();
+        let rhs = self.parse_assignment(engine)?;
+
+        // Desugar `target op= value` into `target = target op value`
+        let operator = Token {
+          token_type,
+          lexeme: lexeme.to_string(),
+          ..token
+        };
+        let value = |lhs: Expr| {
+          Box::new(Expr::Binary {
+            lhs: Box::new(lhs),
+            operator,
+            rhs: Box::new(rhs),
+          })
+        };
+
+        let expr = match lhs {
+          Expr::Identifier(name) => Expr::Assign {
+            value: value(Expr::Identifier(name.clone())),
+            name,
+          },
+          Expr::Get { object, name } => Expr::Set {
+            value: value(Expr::Get {
+              object: object.clone(),
+              name: name.clone(),
+            }),
+            name,
+            object,
+          },
+          _ => {
+            self.error_unexpected_token(engine, "in assignment, left side must be an identifier");
+            return Err(());
+          },
+        };
+        self.record_expr(start, &expr);
+        return Ok(expr);
       }
     }
 
@@ -662,13 +1099,36 @@ impl Parser {
 
   /// Function that handles the ternary (?:)
   fn parse_ternary(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let condition = self.parse_logic_or(engine)?;
 
     if !self.is_eof() && matches!(self.current_token().token_type, TokenType::Question) {
       let question_token = self.current_token();
       self.advance(); // consume the (?)
 
-      let then_branch = self.parse_expr(engine)?;
+      // The comma operator binds looser than the ternary, so it can't appear unparenthesized here
+      let then_branch = self.parse_assignment(engine)?;
+
+      if self.matches_token(TokenType::Comma) {
+        let comma = self.current_token();
+
+        let error = Diagnostic::new(
+          DiagnosticCode::UnexpectedToken,
+          "Comma operator is not allowed in the then-branch of a ternary".to_string(),
+        )
+        .with_label(Label::primary(
+          Self::token_span(&comma),
+          Some("expected ':' instead of ','".to_string()),
+        ))
+        .with_label(Label::secondary(
+          Self::token_span(&question_token),
+          Some("ternary started here".to_string()),
+        ))
+        .with_help("wrap the branch in parentheses, like `a ? (b, c) : d`".to_string());
+
+        engine.emit(error);
+        return Err(());
+      }
 
       if self.is_eof() || !matches!(self.current_token().token_type, TokenType::Colon) {
         let current_token = self.current_token();
@@ -699,45 +1159,111 @@ impl Parser {
       self.advance(); // consume the (:)
       let else_branch = self.parse_ternary(engine)?;
 
-      return Ok(Expr::Ternary {
+      let expr = Expr::Ternary {
         condition: Box::new(condition),
         then_branch: Box::new(then_branch),
         else_branch: Box::new(else_branch),
-      });
+      };
+      self.record_expr(start, &expr);
+      return Ok(expr);
     }
 
     Ok(condition)
   }
 
   fn parse_logic_or(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let mut lhs = self.parse_logic_and(engine)?;
 
     while !self.is_eof() && matches!(self.current_token().token_type, TokenType::Or) {
       let token = self.current_token();
-      self.advance(); // consume the &&
+      self.advance(); // consume the ||
       let rhs = self.parse_logic_and(engine)?;
-      lhs = Expr::Binary {
+      lhs = Expr::Logical {
         lhs: Box::new(lhs),
         operator: token,
         rhs: Box::new(rhs),
-      }
+      };
+      self.record_expr(start, &lhs);
     }
 
     Ok(lhs)
   }
 
   fn parse_logic_and(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
-    let mut lhs = self.parse_equality(engine)?;
+    let start = self.current;
+    let mut lhs = self.parse_bit_or(engine)?;
 
     while !self.is_eof() && matches!(self.current_token().token_type, TokenType::And) {
       let token = self.current_token();
       self.advance(); // consume the &&
+      let rhs = self.parse_bit_or(engine)?;
+      lhs = Expr::Logical {
+        lhs: Box::new(lhs),
+        operator: token,
+        rhs: Box::new(rhs),
+      };
+      self.record_expr(start, &lhs);
+    }
+
+    Ok(lhs)
+  }
+
+  /// Function that handles the bitwise or (|)
+  fn parse_bit_or(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
+    let mut lhs = self.parse_bit_xor(engine)?;
+
+    while !self.is_eof() && matches!(self.current_token().token_type, TokenType::Pipe) {
+      let token = self.current_token();
+      self.advance(); // consume the |
+      let rhs = self.parse_bit_xor(engine)?;
+      lhs = Expr::Binary {
+        lhs: Box::new(lhs),
+        operator: token,
+        rhs: Box::new(rhs),
+      };
+      self.record_expr(start, &lhs);
+    }
+
+    Ok(lhs)
+  }
+
+  /// Function that handles the bitwise xor (^)
+  fn parse_bit_xor(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
+    let mut lhs = self.parse_bit_and(engine)?;
+
+    while !self.is_eof() && matches!(self.current_token().token_type, TokenType::Caret) {
+      let token = self.current_token();
+      self.advance(); // consume the ^
+      let rhs = self.parse_bit_and(engine)?;
+      lhs = Expr::Binary {
+        lhs: Box::new(lhs),
+        operator: token,
+        rhs: Box::new(rhs),
+      };
+      self.record_expr(start, &lhs);
+    }
+
+    Ok(lhs)
+  }
+
+  /// Function that handles the bitwise and (&)
+  fn parse_bit_and(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
+    let mut lhs = self.parse_equality(engine)?;
+
+    while !self.is_eof() && matches!(self.current_token().token_type, TokenType::Ampersand) {
+      let token = self.current_token();
+      self.advance(); // consume the &
       let rhs = self.parse_equality(engine)?;
       lhs = Expr::Binary {
         lhs: Box::new(lhs),
         operator: token,
         rhs: Box::new(rhs),
-      }
+      };
+      self.record_expr(start, &lhs);
     }
 
     Ok(lhs)
@@ -745,6 +1271,7 @@ impl Parser {
 
   /// Function that handles the terms (==|!=)
   fn parse_equality(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let mut lhs = self.parse_comparison(engine)?;
 
     while !self.is_eof() {
@@ -761,6 +1288,7 @@ impl Parser {
             operator: token,
             rhs: Box::new(rhs),
           };
+          self.record_expr(start, &lhs);
         },
         _ => break,
       }
@@ -771,7 +1299,8 @@ impl Parser {
 
   /// Function that handles the terms (<|<=|>=|>)
   fn parse_comparison(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
-    let mut lhs = self.parse_term(engine)?;
+    let start = self.current;
+    let mut lhs = self.parse_shift(engine)?;
 
     while !self.is_eof() {
       let token = self.current_token();
@@ -780,6 +1309,34 @@ impl Parser {
         TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
           self.advance();
 
+          let rhs = self.parse_shift(engine)?;
+
+          lhs = Expr::Binary {
+            lhs: Box::new(lhs),
+            operator: token,
+            rhs: Box::new(rhs),
+          };
+          self.record_expr(start, &lhs);
+        },
+        _ => break,
+      }
+    }
+
+    Ok(lhs)
+  }
+
+  /// Function that handles the shifts (<<|>>)
+  fn parse_shift(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
+    let mut lhs = self.parse_term(engine)?;
+
+    while !self.is_eof() {
+      let token = self.current_token();
+
+      match token.token_type {
+        TokenType::LessLess | TokenType::GreaterGreater => {
+          self.advance();
+
           let rhs = self.parse_term(engine)?;
 
           lhs = Expr::Binary {
@@ -787,6 +1344,7 @@ impl Parser {
             operator: token,
             rhs: Box::new(rhs),
           };
+          self.record_expr(start, &lhs);
         },
         _ => break,
       }
@@ -797,6 +1355,7 @@ impl Parser {
 
   /// Function that handles the terms (+|-)
   fn parse_term(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let mut lhs = self.parse_factor(engine)?;
 
     while !self.is_eof() {
@@ -813,6 +1372,7 @@ impl Parser {
             operator: token,
             rhs: Box::new(rhs),
           };
+          self.record_expr(start, &lhs);
         },
         _ => break,
       }
@@ -823,7 +1383,8 @@ impl Parser {
 
   /// Function that handles the factors (*|/)
   fn parse_factor(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
-    let mut lhs = self.parse_unary(engine)?;
+    let start = self.current;
+    let mut lhs = self.parse_power(engine)?;
 
     while !self.is_eof() {
       let token = self.current_token();
@@ -832,13 +1393,14 @@ impl Parser {
         TokenType::Divide | TokenType::Multiply | TokenType::Modulus => {
           self.advance();
 
-          let rhs = self.parse_unary(engine)?;
+          let rhs = self.parse_power(engine)?;
 
           lhs = Expr::Binary {
             lhs: Box::new(lhs),
             operator: token,
             rhs: Box::new(rhs),
           };
+          self.record_expr(start, &lhs);
         },
         _ => break,
       }
@@ -847,20 +1409,45 @@ impl Parser {
     Ok(lhs)
   }
 
-  /// Parse unary: ( "!" | "-" ) unary | call
+  /// Parse power: unary ( "**" power )?
+  fn parse_power(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
+    let lhs = self.parse_unary(engine)?;
+
+    let token = self.current_token();
+    if token.token_type != TokenType::StarStar {
+      return Ok(lhs);
+    }
+    self.advance();
+
+    // Recursing on the right makes `2 ** 3 ** 2` group as `2 ** (3 ** 2)`
+    let rhs = self.parse_power(engine)?;
+
+    let expr = Expr::Binary {
+      lhs: Box::new(lhs),
+      operator: token,
+      rhs: Box::new(rhs),
+    };
+    self.record_expr(start, &expr);
+    Ok(expr)
+  }
 
+  /// Parse unary: ( "!" | "-" | "typeof" ) unary | call
   fn parse_unary(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let start = self.current;
     let token = self.current_token();
 
     match token.token_type {
-      TokenType::Bang | TokenType::Minus => {
+      TokenType::Bang | TokenType::Minus | TokenType::Typeof => {
         self.advance();
         let rhs = self.parse_unary(engine)?;
 
-        return Ok(Expr::Unary {
+        let expr = Expr::Unary {
           operator: token,
           rhs: Box::new(rhs),
-        });
+        };
+        self.record_expr(start, &expr);
+        Ok(expr)
       },
       _ => self.parse_call(engine), // Changed from parse_primary
     }
@@ -869,7 +1456,9 @@ impl Parser {
   /// Parse call: primary ( "(" arguments? ")" )*
   fn parse_call(&mut self, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
     // Start with a primary expression (the callee)
+    let start = self.current;
     let mut expr = self.parse_primary(engine)?;
+    self.record_expr(start, &expr);
 
     // Loop to handle chained calls: foo()()()
     while !self.is_eof() {
@@ -911,21 +1500,38 @@ impl Parser {
             paren,
             arguments: args,
           };
+          self.record_expr(start, &expr);
         },
 
         TokenType::Dot => {
+          let dot = self.current_token();
           self.advance(); // consume the "."
           let name = self.current_token();
           if name.token_type != TokenType::Identifier {
-            eprintln!("Expected property name after '.'");
+            let diagnostic = Diagnostic::new(
+              DiagnosticCode::ExpectedIdentifier,
+              "Expected property name after '.'".to_string(),
+            )
+            .with_label(Label::primary(
+              Self::token_span(&name),
+              Some("expected property name here".to_string()),
+            ))
+            .with_label(Label::secondary(
+              Self::token_span(&dot),
+              Some("'.' here".to_string()),
+            ))
+            .with_help("property names must be identifiers, like `point.x`".to_string());
+
+            engine.emit(diagnostic);
             return Err(());
           }
           self.advance(); // consume identifier
 
           expr = Expr::Get {
             object: Box::new(expr),
-            name: name,
+            name,
           };
+          self.record_expr(start, &expr);
         },
         _ => break, // No more calls
       }
@@ -941,31 +1547,20 @@ impl Parser {
     // Parse first argument
     args.push(self.parse_assignment(engine)?);
 
-    if args.len() >= 255 {
-      let diagnostic = Diagnostic::new(
-        DiagnosticCode::WrongNumberOfArguments,
-        "Too many arguments".to_string(),
-      )
-      .with_label(Label::primary(
-        self.current_token().to_span(),
-        Some("too many arguments".to_string()),
-      ));
-      engine.emit(diagnostic);
-
-      return Err(());
-    }
-
     // Parse remaining arguments separated by commas
     while !self.is_eof() && self.matches_token(TokenType::Comma) {
+      let comma = self.current_token();
       self.advance(); // consume ","
 
       // Check for trailing comma: foo(1, 2, )
       if self.matches_token(TokenType::RightParen) {
-        // Could emit a warning here about trailing comma
+        self.trailing_comma(&comma, engine);
         break;
       }
 
+      let first = self.current_token();
       args.push(self.parse_assignment(engine)?);
+      self.too_many_arguments(args.len(), &first, "arguments", engine);
     }
 
     Ok(args)
@@ -986,12 +1581,37 @@ impl Parser {
       | TokenType::False
       | TokenType::Nil => {
         self.advance();
-        return Ok(Expr::Literal(token));
+        Ok(Expr::Literal(token))
+      },
+
+      TokenType::Template => {
+        self.advance();
+        self.parse_template(token, engine)
       },
 
       TokenType::This => {
         self.advance();
-        return Ok(Expr::This(token));
+        Ok(Expr::This(token))
+      },
+
+      TokenType::LeftBracket => {
+        self.advance(); // consume '['
+        let mut elements = Vec::new();
+
+        while !self.matches_token(TokenType::RightBracket) {
+          elements.push(self.parse_assignment(engine)?);
+
+          if !self.matches_token(TokenType::Comma) {
+            break;
+          }
+          self.advance(); // consume ','
+        }
+
+        self.expect(TokenType::RightBracket, engine)?;
+        Ok(Expr::Array {
+          bracket: token,
+          elements,
+        })
       },
 
       TokenType::Super => {
@@ -999,16 +1619,21 @@ impl Parser {
         self.expect(TokenType::Dot, engine)?;
         let method = self.parse_primary(engine)?;
         if let Expr::Identifier(name) = method {
-          return Ok(Expr::Super(token, name));
+          Ok(Expr::Super(token, name))
         } else {
-          return Err(());
+          Err(())
         }
       },
       TokenType::Identifier => {
         self.advance();
-        return Ok(Expr::Identifier(token));
+        Ok(Expr::Identifier(token))
       },
 
+      // A function used as a value keeps the scope it's written in, so it can close over locals
+      TokenType::Fun => self.parse_lambda(engine),
+
+      TokenType::Match => self.parse_match(engine),
+
       TokenType::LeftParen => {
         let opening_paren_token = self.current_token();
         self.advance(); // consume '('
@@ -1020,10 +1645,10 @@ impl Parser {
 
           // For EOF, use the PREVIOUS token's end position
           let error_span = if self.is_eof() {
+            self.needs_more_input = true;
             let prev_token = &self.tokens[self.current - 1];
             Span {
-              // TODO: add the real file name
-              file: "asdfa".to_string(),
+              file: prev_token.file.to_string(),
               line: prev_token.position.0,
               column: prev_token.position.1 + prev_token.lexeme.len(),
               length: 1,
@@ -1050,7 +1675,7 @@ impl Parser {
         }
 
         self.advance(); // consume ')'
-        return Ok(Expr::Grouping(Box::new(expr)));
+        Ok(Expr::Grouping(Box::new(expr)))
       },
 
       TokenType::SemiColon => {
@@ -1058,23 +1683,6 @@ impl Parser {
         Err(())
       },
 
-      TokenType::Fun => {
-        let fun = self.parse_fun_stmt(engine)?;
-        let token;
-        if let Stmt::Fun(name, _, _) = &fun {
-          token = name.clone();
-        } else {
-          return Err(());
-        }
-
-        self.ast.push(fun);
-        if let Expr::Identifier(name) = token {
-          return Ok(Expr::Identifier(name));
-        } else {
-          return Err(());
-        }
-      },
-
       _ => {
         let mut token = self.current_token();
         token.position.1 = 0;
@@ -1088,11 +1696,112 @@ impl Parser {
         ));
         engine.emit(diagnostic);
 
-        return Err(());
+        Err(())
       },
     }
   }
 
+  /// Function that splits a template string into its literal parts and parses every `${expr}`,
+  /// the split runs on the raw text so only the literal parts have their escapes decoded
+  fn parse_template(&mut self, token: Token, engine: &mut DiagnosticEngine) -> Result<Expr, ()> {
+    let mut parts = Vec::new();
+    let mut expressions = Vec::new();
+    let raw = token.lexeme.clone();
+    let bytes = raw.as_bytes();
+    let mut part_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+      match bytes[i] {
+        b'\\' => i += 2,
+        b'$' if bytes.get(i + 1) == Some(&b'{') => {
+          parts.push(unescape(&raw[part_start..i]));
+
+          // The scanner already reported the unbalanced `${`
+          let body_start = i + 2;
+          let body_end = interpolation_end(bytes, body_start).ok_or(())?;
+          expressions.push(self.parse_template_expr(
+            &token,
+            body_start,
+            &raw[body_start..body_end],
+            engine,
+          )?);
+
+          i = body_end + 1;
+          part_start = i;
+        },
+        _ => i += 1,
+      }
+    }
+    parts.push(unescape(&raw[part_start.min(raw.len())..]));
+
+    Ok(Expr::Template {
+      token,
+      parts,
+      expressions,
+    })
+  }
+
+  /// Function that scans and parses the source of a single `${expr}` with its own parser, the
+  /// scanner starts at the spot the fragment sits in the file so its spans point there
+  fn parse_template_expr(
+    &mut self,
+    token: &Token,
+    body_start: usize,
+    source: &str,
+    engine: &mut DiagnosticEngine,
+  ) -> Result<Expr, ()> {
+    let mut scanner = Scanner::new(source.to_string()).with_file(&token.file);
+    // The token column sits after the closing quote, the fragment starts after the opening one
+    let token_column = token
+      .position
+      .1
+      .saturating_sub(token.lexeme.chars().count() + 2);
+    scanner.line = token.position.0;
+    scanner.column = token_column + 1 + token.lexeme[..body_start].chars().count();
+    scanner.scan(engine);
+
+    let offset = token.range.start + 1 + body_start;
+    for inner in scanner.tokens.iter_mut() {
+      inner.range = inner.range.start + offset..inner.range.end + offset;
+    }
+
+    let mut parser = Parser::new(scanner.tokens);
+    if parser.is_eof() {
+      let diagnostic = Diagnostic::new(
+        DiagnosticCode::ExpectedExpression,
+        "Expected expr inside string interpolation".to_string(),
+      )
+      .with_label(Label::primary(
+        token.source_span(),
+        Some("empty `${}` in this string".to_string()),
+      ));
+      engine.emit(diagnostic);
+      return Err(());
+    }
+
+    let expr = parser.parse_expr(engine)?;
+
+    if !parser.is_eof() {
+      let found = parser.current_token();
+      let diagnostic = Diagnostic::new(
+        DiagnosticCode::UnexpectedToken,
+        format!(
+          "Unexpected token '{}' in string interpolation",
+          found.lexeme
+        ),
+      )
+      .with_label(Label::primary(
+        found.source_span(),
+        Some("expected '}' here".to_string()),
+      ));
+      engine.emit(diagnostic);
+      return Err(());
+    }
+
+    Ok(expr)
+  }
+
   ///  Function that moves the pointer one step
   fn advance(&mut self) {
     if !self.is_eof() {
@@ -1102,25 +1811,57 @@ impl Parser {
 
   /// Function that gets the currnete token
   fn current_token(&mut self) -> Token {
-    self.tokens[self.current].clone()
+    self.peek(0)
   }
 
-  /// Function that returns bool indicating the EOF state
+  /// Function that returns the token `n` positions past the current one without advancing, past
+  /// the end of the stream it's an EOF token synthesized after the last one
+  pub fn peek(&self, n: usize) -> Token {
+    match self.tokens.get(self.current + n) {
+      Some(token) => token.clone(),
+      None => {
+        let mut eof = Token::new(TokenType::Eof, String::new(), Literal::Nil, (0, 0));
+        if let Some(last) = self.tokens.last() {
+          eof.position = last.position;
+          eof.file = last.file.clone();
+          eof.range = last.range.end..last.range.end;
+        }
+        eof
+      },
+    }
+  }
+
+  /// Function that returns bool indicating the EOF state, an empty stream is already at its end
   fn is_eof(&self) -> bool {
-    self.current == (self.tokens.len() - 1)
+    self.current + 1 >= self.tokens.len()
   }
 
-  /// Function that consume the code until there's valid tokens to start a new expr
-  fn synchronize(&mut self) {
-    self.advance();
+  /// Function that consume the code until there's valid tokens to start a new statement, that's
+  /// right after a `;` or at a statement keyword. The statement that failed began at `start`, at
+  /// least one token is skipped when it failed on its very first token
+  fn synchronize(&mut self, start: usize) {
+    if self.current == start {
+      self.advance();
+    }
 
     while !self.is_eof() {
-      let token = self.current_token();
-      match token.token_type {
-        TokenType::SemiColon => {
-          self.advance();
-          break;
-        },
+      if self.current > start
+        && matches!(
+          self.tokens[self.current - 1].token_type,
+          TokenType::SemiColon
+        )
+      {
+        break;
+      }
+
+      match self.current_token().token_type {
+        TokenType::Var
+        | TokenType::Fun
+        | TokenType::Class
+        | TokenType::If
+        | TokenType::While
+        | TokenType::For
+        | TokenType::Return => break,
         _ => self.advance(),
       }
     }
@@ -1162,6 +1903,7 @@ impl Parser {
   }
 
   fn error_eof(&mut self, engine: &mut DiagnosticEngine) {
+    self.needs_more_input = true;
     let token = self.current_token();
     let diagnostic = Diagnostic::new(
       DiagnosticCode::UnexpectedEof,
@@ -1175,6 +1917,24 @@ impl Parser {
     engine.emit(diagnostic);
   }
 
+  /// Function that returns the `source_span` of the token, at least one column wide
+  fn token_span(token: &Token) -> Span {
+    let mut span = token.source_span();
+    // The end of the input has no lexeme but still needs a caret
+    span.length = span.length.max(1);
+    span
+  }
+
+  /// Function that returns the empty span right after the previous token, where a missing token
+  /// would be inserted
+  fn insertion_after_prev(&self) -> Span {
+    let prev = self.tokens[self.current.saturating_sub(1)].clone();
+    let mut span = Self::token_span(&prev);
+    span.column = prev.position.1 + 1;
+    span.length = 0;
+    span
+  }
+
   fn span_prev(&mut self) -> Span {
     if self.current > 0 {
       let token = self.current_token();
@@ -1183,7 +1943,7 @@ impl Parser {
       prev_token.lexeme = token.lexeme;
       prev_token.to_span()
     } else {
-      self.tokens[0].to_span()
+      self.current_token().to_span()
     }
   }
 }
@@ -1209,8 +1969,12 @@ impl Parser {
 
   /// Error for when we expect a token but hit EOF
   fn error_expected_token_eof(&mut self, expected: TokenType, engine: &mut DiagnosticEngine) {
+    self.needs_more_input = true;
     let token = self.current_token();
-    let last_token = &self.tokens[self.current - 1];
+    let last_token = match self.current.checked_sub(1) {
+      Some(previous) => &self.tokens[previous],
+      None => &token,
+    };
 
     let error_span = Span {
       file: last_token.to_span().file.clone(),
@@ -1221,14 +1985,11 @@ impl Parser {
 
     let diagnostic = Diagnostic::new(
       DiagnosticCode::UnexpectedEof,
-      format!(
-        "Expected '{}', but reached end of file",
-        expected.to_string()
-      ),
+      format!("Expected '{}', but reached end of file", expected),
     )
     .with_label(Label::primary(
       error_span,
-      Some(format!("expected '{}' here", expected.to_string())),
+      Some(format!("expected '{}' here", expected)),
     ))
     .with_label(Label::secondary(
       last_token.to_span(),
@@ -1257,8 +2018,6 @@ impl Parser {
     engine.emit(diagnostic);
   }
 }
-/// Helper function to convert TokenType to a readable string
-
 /// Provides contextual help based on what was expected vs found
 fn get_token_help(expected: &TokenType, found: &Token) -> String {
   match (expected, &found.token_type) {
@@ -1279,3 +2038,41 @@ fn get_token_help(expected: &TokenType, found: &Token) -> String {
     _ => String::new(),
   }
 }
+
+/// Function that returns the index of the `}` closing the interpolation whose body starts at `i`,
+/// the strings nested in the body are skipped so their braces don't count
+fn interpolation_end(raw: &[u8], mut i: usize) -> Option<usize> {
+  let mut depth = 1;
+  while i < raw.len() {
+    match raw[i] {
+      b'{' => depth += 1,
+      b'}' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i);
+        }
+      },
+      quote @ (b'"' | b'\'' | b'`') => {
+        i = string_end(raw, i + 1, quote)?;
+        continue;
+      },
+      _ => {},
+    }
+    i += 1;
+  }
+  None
+}
+
+/// Function that returns the index past the quote closing the string whose body starts at `i`
+fn string_end(raw: &[u8], mut i: usize, quote: u8) -> Option<usize> {
+  while i < raw.len() {
+    match raw[i] {
+      b'\\' => i += 1,
+      b'$' if raw.get(i + 1) == Some(&b'{') => i = interpolation_end(raw, i + 2)?,
+      byte if byte == quote => return Some(i + 1),
+      _ => {},
+    }
+    i += 1;
+  }
+  None
+}
@@ -0,0 +1,471 @@
+use scanner::token::{types::TokenType, Token};
+
+use crate::{expr::Expr, stmt::Stmt};
+
+/// Indentation of one nesting level
+const INDENT: &str = "  ";
+
+/// How tightly an expression binds, from the loosest to the tightest. A child that binds looser
+/// than its position allows is wrapped in parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+  Comma,
+  Assignment,
+  Ternary,
+  Or,
+  And,
+  BitOr,
+  BitXor,
+  BitAnd,
+  Equality,
+  Comparison,
+  Shift,
+  Term,
+  Factor,
+  Power,
+  Unary,
+  Call,
+  Primary,
+}
+
+impl Precedence {
+  /// Function that returns the level of a binary operator
+  fn of_operator(token_type: &TokenType) -> Self {
+    match token_type {
+      TokenType::Comma => Self::Comma,
+      TokenType::Or => Self::Or,
+      TokenType::And => Self::And,
+      TokenType::Pipe => Self::BitOr,
+      TokenType::Caret => Self::BitXor,
+      TokenType::Ampersand => Self::BitAnd,
+      TokenType::EqualEqual | TokenType::BangEqual => Self::Equality,
+      TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+        Self::Comparison
+      },
+      TokenType::LessLess | TokenType::GreaterGreater => Self::Shift,
+      TokenType::Plus | TokenType::Minus => Self::Term,
+      TokenType::StarStar => Self::Power,
+      _ => Self::Factor,
+    }
+  }
+
+  /// Function that returns the level right above this one, used for the right operand of a
+  /// left-associative operator
+  fn next(self) -> Self {
+    match self {
+      Self::Comma => Self::Assignment,
+      Self::Assignment => Self::Ternary,
+      Self::Ternary => Self::Or,
+      Self::Or => Self::And,
+      Self::And => Self::BitOr,
+      Self::BitOr => Self::BitXor,
+      Self::BitXor => Self::BitAnd,
+      Self::BitAnd => Self::Equality,
+      Self::Equality => Self::Comparison,
+      Self::Comparison => Self::Shift,
+      Self::Shift => Self::Term,
+      Self::Term => Self::Factor,
+      Self::Factor => Self::Power,
+      Self::Power => Self::Unary,
+      Self::Unary => Self::Call,
+      Self::Call | Self::Primary => Self::Primary,
+    }
+  }
+}
+
+impl Stmt {
+  /// Function that prints the statement back as Lox source, nested statements are indented by
+  /// two spaces and there's no newline after the last line
+  pub fn to_source(&self) -> String {
+    self.source(0)
+  }
+
+  /// Function that prints the statement at `depth`, the first line isn't indented since the
+  /// caller decides what comes before it
+  fn source(&self, depth: usize) -> String {
+    match self {
+      Stmt::Expr(expr) => format!("{};", expr.source(Precedence::Comma, depth)),
+      Stmt::VarDecl(name, None) => format!("var {};", name.lexeme),
+      Stmt::VarDecl(name, Some(value)) => format!(
+        "var {} = {};",
+        name.lexeme,
+        value.source(Precedence::Comma, depth)
+      ),
+      // A `for` with an initializer is desugared to a block holding it and the loop
+      Stmt::Block(stmts) => match stmts.as_slice() {
+        [init, Stmt::While(condition, body_stmt, Some(increment), label)]
+          if matches!(init, Stmt::VarDecl(..) | Stmt::Expr(_)) =>
+        {
+          for_loop(Some(init), condition, increment, body_stmt, label, depth)
+        },
+        stmts => block(stmts, depth),
+      },
+      Stmt::If(condition, then_branch, else_branch) => {
+        let mut out = format!(
+          "if ({}){}",
+          condition.source(Precedence::Comma, depth),
+          body(then_branch, depth)
+        );
+        if let Some(else_branch) = else_branch {
+          out.push_str(&separator(then_branch, depth));
+          match **else_branch {
+            Stmt::If(..) => out.push_str(&format!("else {}", else_branch.source(depth))),
+            _ => out.push_str(&format!("else{}", body(else_branch, depth))),
+          }
+        }
+        out
+      },
+      Stmt::While(condition, body_stmt, None, label) => format!(
+        "{}while ({}){}",
+        label_prefix(label),
+        condition.source(Precedence::Comma, depth),
+        body(body_stmt, depth)
+      ),
+      Stmt::While(condition, body_stmt, Some(increment), label) => {
+        for_loop(None, condition, increment, body_stmt, label, depth)
+      },
+      Stmt::DoWhile(body_stmt, condition, label) => format!(
+        "{}do{}{}while ({});",
+        label_prefix(label),
+        body(body_stmt, depth),
+        separator(body_stmt, depth),
+        condition.source(Precedence::Comma, depth)
+      ),
+      Stmt::Switch(discriminant, cases) => {
+        let mut out = format!(
+          "switch ({}) {{\n",
+          discriminant.source(Precedence::Comma, depth)
+        );
+        for (value, stmts) in cases {
+          out.push_str(&indent(depth + 1));
+          match value {
+            Some(value) => out.push_str(&format!(
+              "case {}:\n",
+              value.source(Precedence::Comma, depth + 1)
+            )),
+            None => out.push_str("default:\n"),
+          }
+          for stmt in stmts {
+            out.push_str(&format!(
+              "{}{}\n",
+              indent(depth + 2),
+              stmt.source(depth + 2)
+            ));
+          }
+        }
+        out.push_str(&indent(depth));
+        out.push('}');
+        out
+      },
+      Stmt::Fun(name, params, body_stmt) => {
+        format!("fun {}", function(name, params, body_stmt, depth))
+      },
+      Stmt::Class(name, superclass, methods, static_methods) => {
+        let mut out = format!("class {}", name.source(Precedence::Primary, depth));
+        if let Some(superclass) = superclass {
+          out.push_str(&format!(
+            " < {}",
+            superclass.source(Precedence::Primary, depth)
+          ));
+        }
+        out.push_str(" {\n");
+
+        let methods = methods.iter().map(|method| ("", method));
+        let static_methods = static_methods.iter().map(|method| ("static ", method));
+        for (prefix, method) in methods.chain(static_methods) {
+          if let Stmt::Fun(name, params, body_stmt) = method {
+            out.push_str(&format!(
+              "{}{}{}\n",
+              indent(depth + 1),
+              prefix,
+              function(name, params, body_stmt, depth + 1)
+            ));
+          }
+        }
+        out.push_str(&indent(depth));
+        out.push('}');
+        out
+      },
+      Stmt::Return(_, None) => "return;".to_string(),
+      Stmt::Return(_, Some(value)) => {
+        format!("return {};", value.source(Precedence::Comma, depth))
+      },
+      Stmt::Break(_, label) => format!("break{};", label_suffix(label)),
+      Stmt::Continue(_, label) => format!("continue{};", label_suffix(label)),
+    }
+  }
+}
+
+impl Expr {
+  /// Function that prints the expression back as Lox source, parentheses are added where the
+  /// tree nests a looser expression inside a tighter one without a grouping
+  pub fn to_source(&self) -> String {
+    self.source(Precedence::Comma, 0)
+  }
+
+  /// Function that prints the expression where at least `min` is expected, `depth` is the
+  /// indentation of the statement holding it, for the body of an anonymous function
+  fn source(&self, min: Precedence, depth: usize) -> String {
+    let (precedence, text) = match self {
+      Expr::Literal(token) => {
+        let text = match token.token_type {
+          TokenType::String => format!("\"{}\"", escape(&token.lexeme)),
+          _ => token.lexeme.clone(),
+        };
+        (Precedence::Primary, text)
+      },
+      Expr::Identifier(token) => (Precedence::Primary, token.lexeme.clone()),
+      Expr::Unary { operator, rhs } => {
+        let rhs = rhs.source(Precedence::Unary, depth);
+        // `- -x` mustn't collapse into the `--` token
+        let space = operator.token_type == TokenType::Typeof
+          || (operator.token_type == TokenType::Minus && rhs.starts_with('-'));
+        let text = if space {
+          format!("{} {}", operator.lexeme, rhs)
+        } else {
+          format!("{}{}", operator.lexeme, rhs)
+        };
+        (Precedence::Unary, text)
+      },
+      Expr::Binary { lhs, operator, rhs } | Expr::Logical { lhs, operator, rhs } => {
+        let precedence = Precedence::of_operator(&operator.token_type);
+        // `**` is the one right-associative operator, with its left operand one level up
+        let (lhs_min, rhs_min) = if precedence == Precedence::Power {
+          (Precedence::Unary, Precedence::Power)
+        } else {
+          (precedence, precedence.next())
+        };
+        let lhs = lhs.source(lhs_min, depth);
+        let rhs = rhs.source(rhs_min, depth);
+        let text = if operator.token_type == TokenType::Comma {
+          format!("{}, {}", lhs, rhs)
+        } else {
+          format!("{} {} {}", lhs, operator.lexeme, rhs)
+        };
+        (precedence, text)
+      },
+      Expr::Assign { name, value } => (
+        Precedence::Assignment,
+        format!(
+          "{} = {}",
+          name.lexeme,
+          value.source(Precedence::Assignment, depth)
+        ),
+      ),
+      Expr::Ternary {
+        condition,
+        then_branch,
+        else_branch,
+      } => (
+        Precedence::Ternary,
+        format!(
+          "{} ? {} : {}",
+          condition.source(Precedence::Or, depth),
+          then_branch.source(Precedence::Assignment, depth),
+          else_branch.source(Precedence::Ternary, depth)
+        ),
+      ),
+      Expr::Call {
+        callee, arguments, ..
+      } => (
+        Precedence::Call,
+        format!(
+          "{}({})",
+          callee.source(Precedence::Call, depth),
+          list(arguments, depth)
+        ),
+      ),
+      Expr::Grouping(expr) => (
+        Precedence::Primary,
+        format!("({})", expr.source(Precedence::Comma, depth)),
+      ),
+      Expr::Get { object, name } => (
+        Precedence::Call,
+        format!("{}.{}", object.source(Precedence::Call, depth), name.lexeme),
+      ),
+      Expr::Set {
+        object,
+        name,
+        value,
+      } => (
+        Precedence::Assignment,
+        format!(
+          "{}.{} = {}",
+          object.source(Precedence::Call, depth),
+          name.lexeme,
+          value.source(Precedence::Assignment, depth)
+        ),
+      ),
+      Expr::This(_) => (Precedence::Primary, "this".to_string()),
+      Expr::Super(_, name) => (Precedence::Primary, format!("super.{}", name.lexeme)),
+      Expr::Array { elements, .. } => (Precedence::Primary, format!("[{}]", list(elements, depth))),
+      Expr::Template {
+        parts, expressions, ..
+      } => {
+        let mut text = String::from("\"");
+        for (i, part) in parts.iter().enumerate() {
+          text.push_str(&escape(part));
+          if let Some(expr) = expressions.get(i) {
+            text.push_str(&format!("${{{}}}", expr.source(Precedence::Comma, depth)));
+          }
+        }
+        text.push('"');
+        (Precedence::Primary, text)
+      },
+      Expr::Lambda {
+        name, params, body, ..
+      } => {
+        let name = name
+          .as_ref()
+          .map_or(String::new(), |name| name.lexeme.clone());
+        (
+          Precedence::Primary,
+          format!(
+            "fun {}({}){}",
+            name,
+            list(params, depth),
+            self::body(body, depth)
+          ),
+        )
+      },
+      Expr::Match {
+        scrutinee, arms, ..
+      } => {
+        let mut text = format!(
+          "match ({}) {{\n",
+          scrutinee.source(Precedence::Comma, depth)
+        );
+        for (pattern, value) in arms {
+          let pattern = pattern.as_ref().map_or("_".to_string(), |pattern| {
+            pattern.source(Precedence::Unary, depth + 1)
+          });
+          text.push_str(&format!(
+            "{}{} => {},\n",
+            indent(depth + 1),
+            pattern,
+            value.source(Precedence::Assignment, depth + 1)
+          ));
+        }
+        text.push_str(&indent(depth));
+        text.push('}');
+        (Precedence::Primary, text)
+      },
+    };
+
+    if precedence < min {
+      format!("({})", text)
+    } else {
+      text
+    }
+  }
+}
+
+/// Function that prints the `for` a `while` with an increment was desugared from
+fn for_loop(
+  init: Option<&Stmt>,
+  condition: &Expr,
+  increment: &Expr,
+  body_stmt: &Stmt,
+  label: &Option<Token>,
+  depth: usize,
+) -> String {
+  format!(
+    "{}for ({} {}; {}){}",
+    label_prefix(label),
+    init.map_or(";".to_string(), |init| init.source(depth)),
+    condition.source(Precedence::Comma, depth),
+    increment.source(Precedence::Comma, depth),
+    body(body_stmt, depth)
+  )
+}
+
+/// Function that prints `name(params) { ... }`, shared by functions and methods
+fn function(name: &Expr, params: &[Expr], body_stmt: &Stmt, depth: usize) -> String {
+  format!(
+    "{}({}){}",
+    name.source(Precedence::Primary, depth),
+    list(params, depth),
+    body(body_stmt, depth)
+  )
+}
+
+/// Function that prints the body of a compound statement, a block opens on the same line and
+/// any other statement goes on its own line one level deeper
+fn body(stmt: &Stmt, depth: usize) -> String {
+  match stmt {
+    Stmt::Block(stmts) => format!(" {}", block(stmts, depth)),
+    stmt => format!("\n{}{}", indent(depth + 1), stmt.source(depth + 1)),
+  }
+}
+
+/// Function that prints what follows a body before the next keyword, like the `else` of an `if`
+fn separator(stmt: &Stmt, depth: usize) -> String {
+  match stmt {
+    Stmt::Block(_) => " ".to_string(),
+    _ => format!("\n{}", indent(depth)),
+  }
+}
+
+/// Function that prints the statements between braces, one per line.
+fn block(stmts: &[Stmt], depth: usize) -> String {
+  if stmts.is_empty() {
+    return "{}".to_string();
+  }
+
+  let mut out = String::from("{\n");
+  for stmt in stmts {
+    out.push_str(&format!(
+      "{}{}\n",
+      indent(depth + 1),
+      stmt.source(depth + 1)
+    ));
+  }
+  out.push_str(&indent(depth));
+  out.push('}');
+  out
+}
+
+/// Function that prints the comma separated entries of an argument, parameter or array list.
+fn list(exprs: &[Expr], depth: usize) -> String {
+  exprs
+    .iter()
+    .map(|expr| expr.source(Precedence::Assignment, depth))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Function that prints the `label: ` before a loop, nothing when it has none.
+fn label_prefix(label: &Option<Token>) -> String {
+  label
+    .as_ref()
+    .map_or(String::new(), |label| format!("{}: ", label.lexeme))
+}
+
+/// Function that prints the ` label` after a `break` or `continue`, nothing when it has none.
+fn label_suffix(label: &Option<Token>) -> String {
+  label
+    .as_ref()
+    .map_or(String::new(), |label| format!(" {}", label.lexeme))
+}
+
+fn indent(depth: usize) -> String {
+  INDENT.repeat(depth)
+}
+
+/// Function that escapes a decoded string so the scanner reads it back the same.
+fn escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for char in text.chars() {
+    match char {
+      '\\' => out.push_str("\\\\"),
+      '"' => out.push_str("\\\""),
+      '\n' => out.push_str("\\n"),
+      '\t' => out.push_str("\\t"),
+      '\r' => out.push_str("\\r"),
+      '\0' => out.push_str("\\0"),
+      char => out.push(char),
+    }
+  }
+  // A literal `${` is escaped so it doesn't read back as an interpolation
+  out.replace("${", "\\${")
+}
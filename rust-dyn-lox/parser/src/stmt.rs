@@ -8,12 +8,18 @@ pub enum Stmt {
   VarDecl(Token, Option<Expr>),
   Block(Box<Vec<Stmt>>),
   If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
-  While(Box<Expr>, Box<Stmt>),
+  /// Condition, body and the increment of a desugared `for`, which also runs after a `continue`,
+  /// the label names the loop for a `break` or `continue` of a nested loop
+  While(Box<Expr>, Box<Stmt>, Option<Box<Expr>>, Option<Token>),
+  DoWhile(Box<Stmt>, Box<Expr>, Option<Token>),
+  /// Discriminant and the cases in source order, the `default` case has no value
+  Switch(Box<Expr>, Vec<(Option<Expr>, Vec<Stmt>)>),
   Fun(Expr, Vec<Expr>, Box<Stmt>),
   Class(Expr, Option<Expr>, Box<Vec<Stmt>>, Box<Vec<Stmt>>),
   Return(Token, Option<Expr>),
-  Break(Token),
-  Continue(Token),
+  /// The keyword and the label of the loop it targets, the innermost loop when there's none
+  Break(Token, Option<Token>),
+  Continue(Token, Option<Token>),
 }
 
 impl fmt::Display for Stmt {
@@ -46,8 +52,37 @@ impl fmt::Display for Stmt {
         "IfStmt(cond: {}, then: {}, else: <nil>)",
         condition, then_branch
       ),
-      Stmt::While(condition, body) => {
-        write!(f, "WhileStmt(cond: {}, body: {})", condition, body)
+      Stmt::While(condition, body, increment, label) => {
+        if let Some(label) = label {
+          write!(f, "{}: ", label.lexeme)?;
+        }
+        match increment {
+          Some(increment) => write!(
+            f,
+            "WhileStmt(cond: {}, body: {}, inc: {})",
+            condition, body, increment
+          ),
+          None => write!(f, "WhileStmt(cond: {}, body: {})", condition, body),
+        }
+      },
+      Stmt::DoWhile(body, condition, label) => {
+        if let Some(label) = label {
+          write!(f, "{}: ", label.lexeme)?;
+        }
+        write!(f, "DoWhileStmt(body: {}, cond: {})", body, condition)
+      },
+      Stmt::Switch(discriminant, cases) => {
+        write!(f, "SwitchStmt({}, [", discriminant)?;
+        for (i, (value, stmts)) in cases.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          match value {
+            Some(value) => write!(f, "case {}: {} stmts", value, stmts.len())?,
+            None => write!(f, "default: {} stmts", stmts.len())?,
+          }
+        }
+        write!(f, "])")
       },
       Stmt::Fun(name, params, body) => {
         write!(f, "Fun({}, [", name)?;
@@ -65,14 +100,38 @@ impl fmt::Display for Stmt {
       Stmt::Return(token, None) => {
         write!(f, "Return({})", token.lexeme)
       },
-      Stmt::Break(token) => {
+      Stmt::Break(token, None) => {
         write!(f, "Break({})", token.lexeme)
       },
-      Stmt::Continue(token) => {
+      Stmt::Break(token, Some(label)) => {
+        write!(f, "Break({}, {})", token.lexeme, label.lexeme)
+      },
+      Stmt::Continue(token, None) => {
         write!(f, "Continue({})", token.lexeme)
       },
-      Stmt::Class(name, superclass, stmts, static_methods) => {
-        write!(f, "Class({}, [...])", name)
+      Stmt::Continue(token, Some(label)) => {
+        write!(f, "Continue({}, {})", token.lexeme, label.lexeme)
+      },
+      Stmt::Class(name, superclass, methods, static_methods) => {
+        write!(f, "Class({}", name)?;
+        if let Some(superclass) = superclass {
+          write!(f, " < {}", superclass)?;
+        }
+        write!(f, ", [")?;
+        for (i, method) in methods.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", method)?;
+        }
+        write!(f, "], static [")?;
+        for (i, method) in static_methods.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", method)?;
+        }
+        write!(f, "])")
       },
     }
   }
@@ -81,72 +140,170 @@ impl fmt::Display for Stmt {
 impl Stmt {
   /// Beautiful ASCII tree output
   pub fn print_tree(&self) {
-    self.build_tree("", true);
+    print!("{}", self.tree_string());
   }
 
-  fn build_tree(&self, prefix: &str, is_last: bool) {
+  /// Function that renders the ASCII tree into a `String` instead of stdout
+  pub fn tree_string(&self) -> String {
+    let mut out = String::new();
+    // Writing into a `String` never fails
+    let _ = self.build_tree(&mut out, "", true);
+    out
+  }
+
+  pub(crate) fn build_tree(
+    &self,
+    out: &mut impl fmt::Write,
+    prefix: &str,
+    is_last: bool,
+  ) -> fmt::Result {
     let connector = if is_last { "└── " } else { "├── " };
     let extension = if is_last { "    " } else { "│   " };
 
     match self {
       Stmt::Expr(expr) => {
-        println!("{}{}ExprStmt", prefix, connector);
-        expr.build_tree(&format!("{}{}", prefix, extension), true);
+        writeln!(out, "{}{}ExprStmt", prefix, connector)?;
+        expr.build_tree(out, &format!("{}{}", prefix, extension), true)?;
       },
 
       Stmt::VarDecl(name, value) => {
-        println!("{}{}VarDecl({})", prefix, connector, name.lexeme);
+        writeln!(out, "{}{}VarDecl({})", prefix, connector, name.lexeme)?;
         if let Some(expr) = value {
-          expr.build_tree(&format!("{}{}", prefix, extension), true);
+          expr.build_tree(out, &format!("{}{}", prefix, extension), true)?;
         } else {
-          println!("{}{}└── <uninitialized>", prefix, extension);
+          writeln!(out, "{}{}└── <uninitialized>", prefix, extension)?;
         }
       },
 
       Stmt::Block(stmts) => {
-        println!("{}{}Block", prefix, connector);
+        writeln!(out, "{}{}Block", prefix, connector)?;
         let new_prefix = format!("{}{}", prefix, extension);
         for (i, stmt) in stmts.iter().enumerate() {
-          stmt.build_tree(&new_prefix, i == stmts.len() - 1);
+          stmt.build_tree(out, &new_prefix, i == stmts.len() - 1)?;
         }
       },
 
       Stmt::If(condition, then_branch, else_branch) => {
-        println!("{}{}If", prefix, connector);
+        writeln!(out, "{}{}If", prefix, connector)?;
         let new_prefix = format!("{}{}", prefix, extension);
 
         // Condition
-        println!("{}├── condition:", new_prefix);
-        condition.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── condition:", new_prefix)?;
+        condition.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
         // Then branch
         let has_else = else_branch.is_some();
-        println!(
+        writeln!(
+          out,
           "{}{}then:",
           new_prefix,
           if has_else { "├── " } else { "└── " }
-        );
+        )?;
         then_branch.build_tree(
+          out,
           &format!("{}{}", new_prefix, if has_else { "│   " } else { "    " }),
           true,
-        );
+        )?;
 
         // Else branch
         if let Some(else_stmt) = else_branch {
-          println!("{}└── else:", new_prefix);
-          else_stmt.build_tree(&format!("{}    ", new_prefix), true);
+          writeln!(out, "{}└── else:", new_prefix)?;
+          else_stmt.build_tree(out, &format!("{}    ", new_prefix), true)?;
         }
       },
 
-      Stmt::While(condition, body) => {
-        println!("{}{}While", prefix, connector);
+      Stmt::While(condition, body, increment, label) => {
+        writeln!(out, "{}{}While{}", prefix, connector, tree_label(label))?;
         let new_prefix = format!("{}{}", prefix, extension);
 
-        println!("{}├── condition:", new_prefix);
-        condition.build_tree(&format!("{}│   ", new_prefix), true);
+        writeln!(out, "{}├── condition:", new_prefix)?;
+        condition.build_tree(out, &format!("{}│   ", new_prefix), true)?;
+
+        match increment {
+          Some(increment) => {
+            writeln!(out, "{}├── body:", new_prefix)?;
+            body.build_tree(out, &format!("{}│   ", new_prefix), true)?;
 
-        println!("{}└── body:", new_prefix);
-        body.build_tree(&format!("{}    ", new_prefix), true);
+            writeln!(out, "{}└── increment:", new_prefix)?;
+            increment.build_tree(out, &format!("{}    ", new_prefix), true)?;
+          },
+          None => {
+            writeln!(out, "{}└── body:", new_prefix)?;
+            body.build_tree(out, &format!("{}    ", new_prefix), true)?;
+          },
+        }
+      },
+
+      Stmt::DoWhile(body, condition, label) => {
+        writeln!(out, "{}{}DoWhile{}", prefix, connector, tree_label(label))?;
+        let new_prefix = format!("{}{}", prefix, extension);
+
+        writeln!(out, "{}├── body:", new_prefix)?;
+        body.build_tree(out, &format!("{}│   ", new_prefix), true)?;
+
+        writeln!(out, "{}└── condition:", new_prefix)?;
+        condition.build_tree(out, &format!("{}    ", new_prefix), true)?;
+      },
+
+      Stmt::Switch(discriminant, cases) => {
+        writeln!(out, "{}{}Switch", prefix, connector)?;
+        let new_prefix = format!("{}{}", prefix, extension);
+
+        let has_cases = !cases.is_empty();
+        writeln!(
+          out,
+          "{}{}discriminant:",
+          new_prefix,
+          if has_cases {
+            "├── "
+          } else {
+            "└── "
+          }
+        )?;
+        discriminant.build_tree(
+          out,
+          &format!("{}{}", new_prefix, if has_cases { "│   " } else { "    " }),
+          true,
+        )?;
+
+        for (i, (value, stmts)) in cases.iter().enumerate() {
+          let is_last_case = i == cases.len() - 1;
+          let case_prefix = format!(
+            "{}{}",
+            new_prefix,
+            if is_last_case { "    " } else { "│   " }
+          );
+
+          match value {
+            Some(value) => {
+              writeln!(
+                out,
+                "{}{}case:",
+                new_prefix,
+                if is_last_case {
+                  "└── "
+                } else {
+                  "├── "
+                }
+              )?;
+              value.build_tree(out, &case_prefix, stmts.is_empty())?;
+            },
+            None => writeln!(
+              out,
+              "{}{}default:",
+              new_prefix,
+              if is_last_case {
+                "└── "
+              } else {
+                "├── "
+              }
+            )?,
+          }
+
+          for (j, stmt) in stmts.iter().enumerate() {
+            stmt.build_tree(out, &case_prefix, j == stmts.len() - 1)?;
+          }
+        }
       },
 
       Stmt::Fun(name, params, body) => {
@@ -159,36 +316,67 @@ impl Stmt {
           .collect::<Vec<_>>()
           .join(", ");
 
-        println!("{}{}Fun({}, [{}])", prefix, connector, name, params_str);
+        writeln!(
+          out,
+          "{}{}Fun({}, [{}])",
+          prefix, connector, name, params_str
+        )?;
         let new_prefix = format!("{}{}", prefix, extension);
-        println!("{}└── body:", new_prefix);
-        body.build_tree(&format!("{}    ", new_prefix), true);
+        writeln!(out, "{}└── body:", new_prefix)?;
+        body.build_tree(out, &format!("{}    ", new_prefix), true)?;
       },
 
       Stmt::Return(_, value) => {
-        println!("{}{}Return", prefix, connector);
+        writeln!(out, "{}{}Return", prefix, connector)?;
         if let Some(expr) = value {
-          expr.build_tree(&format!("{}{}", prefix, extension), true);
+          expr.build_tree(out, &format!("{}{}", prefix, extension), true)?;
         } else {
-          println!("{}{}└── <nil>", prefix, extension);
+          writeln!(out, "{}{}└── <nil>", prefix, extension)?;
         }
       },
 
-      Stmt::Break(_) => {
-        println!("{}{}Break", prefix, connector);
+      Stmt::Break(_, label) => {
+        writeln!(out, "{}{}Break{}", prefix, connector, tree_label(label))?;
       },
 
-      Stmt::Continue(_) => {
-        println!("{}{}Continue", prefix, connector);
+      Stmt::Continue(_, label) => {
+        writeln!(out, "{}{}Continue{}", prefix, connector, tree_label(label))?;
       },
 
       Stmt::Class(name, superclass, methods, static_methods) => {
-        println!("{}{}Class({})", prefix, connector, name);
+        match superclass {
+          Some(superclass) => writeln!(
+            out,
+            "{}{}Class({} < {})",
+            prefix, connector, name, superclass
+          )?,
+          None => writeln!(out, "{}{}Class({})", prefix, connector, name)?,
+        }
         let new_prefix = format!("{}{}", prefix, extension);
+
+        // Instance methods hang off the class, the static ones are grouped after them
+        let has_statics = !static_methods.is_empty();
         for (i, method) in methods.iter().enumerate() {
-          method.build_tree(&new_prefix, i == methods.len() - 1);
+          method.build_tree(out, &new_prefix, !has_statics && i == methods.len() - 1)?;
+        }
+
+        if has_statics {
+          writeln!(out, "{}└── static:", new_prefix)?;
+          let static_prefix = format!("{}    ", new_prefix);
+          for (i, method) in static_methods.iter().enumerate() {
+            method.build_tree(out, &static_prefix, i == static_methods.len() - 1)?;
+          }
         }
       },
     }
+
+    Ok(())
   }
 }
+
+/// Function that renders the loop label of a tree node, nothing when it has none
+fn tree_label(label: &Option<Token>) -> String {
+  label
+    .as_ref()
+    .map_or(String::new(), |label| format!(" ({})", label.lexeme))
+}
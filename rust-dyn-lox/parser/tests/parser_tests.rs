@@ -0,0 +1,835 @@
+#[cfg(test)]
+mod tests {
+  use diagnostic::{diagnostic_code::DiagnosticCode, DiagnosticEngine};
+  use parser::{expr::Expr, stmt::Stmt, Node, Parser};
+  use scanner::{token::types::TokenType, Scanner};
+
+  fn parse(source: &str, lenient_eof: bool, engine: &mut DiagnosticEngine) -> Parser {
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.scan(engine);
+
+    let mut parser = Parser::new(scanner.tokens);
+    parser.lenient_eof = lenient_eof;
+    parser.parse(engine);
+    parser
+  }
+
+  #[test]
+  fn test_lenient_eof_accepts_missing_semicolon() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("print(1)", true, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(engine.warning_count(), 1);
+    assert_eq!(parser.ast.len(), 1);
+  }
+
+  #[test]
+  fn test_lenient_eof_warning_points_at_the_last_token() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("\nprint(1)", true, &mut engine);
+
+    assert_eq!(engine.warning_count(), 1);
+    assert_eq!(label_spans(&engine), vec![(2, 8, 1)]);
+  }
+
+  #[test]
+  fn test_lenient_eof_accepts_missing_semicolon_after_var() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("var a = 1;\nvar b = a + 1", true, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 2);
+  }
+
+  #[test]
+  fn test_missing_semicolon_at_eof_is_an_error_by_default() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("print(1)", false, &mut engine);
+
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_lenient_eof_still_requires_inner_semicolons() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("print(1)\nprint(2)", true, &mut engine);
+
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_switch_parses_cases_in_order() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      "switch (x) { case 1: print(1); print(2); default: print(3); }",
+      false,
+      &mut engine,
+    );
+
+    assert!(!engine.has_errors());
+    match &parser.ast[0] {
+      parser::stmt::Stmt::Switch(_, cases) => {
+        assert_eq!(cases.len(), 2);
+        assert!(cases[0].0.is_some());
+        assert_eq!(cases[0].1.len(), 2);
+        assert!(cases[1].0.is_none());
+      },
+      stmt => panic!("expected a switch, got {}", stmt),
+    }
+  }
+
+  #[test]
+  fn test_switch_duplicate_default_is_an_error() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      "switch (x) { default: print(1); default: print(2); }",
+      false,
+      &mut engine,
+    );
+
+    assert!(parser.ast.is_empty());
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::DuplicateDefault
+    );
+  }
+
+  #[test]
+  fn test_duplicate_default_labels_point_at_both_defaults() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse(
+      "var x = 1;\nswitch (x) {\n  default: print(1);\n  default: print(2);\n}",
+      false,
+      &mut engine,
+    );
+
+    assert_eq!(label_spans(&engine), vec![(4, 3, 7), (3, 3, 7)]);
+  }
+
+  #[test]
+  fn test_into_ast_returns_tree_and_diagnostics() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("var a = 1;\nprint(a + 2);".to_string());
+    scanner.scan(&mut engine);
+
+    let (ast, diagnostics) = Parser::new(scanner.tokens).into_ast();
+
+    assert_eq!(ast.len(), 2);
+    assert!(diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_into_ast_collects_parse_errors() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("var = 1;".to_string());
+    scanner.scan(&mut engine);
+
+    let (ast, diagnostics) = Parser::new(scanner.tokens).into_ast();
+
+    assert!(ast.is_empty());
+    assert!(!diagnostics.is_empty());
+  }
+
+  #[test]
+  fn test_class_tree_groups_static_methods() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      "class Point { init(x) { this.x = x; } static origin() { return Point(0); } }",
+      false,
+      &mut engine,
+    );
+
+    assert!(!engine.has_errors());
+    let tree = parser.ast[0].tree_string();
+    let lines = tree.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines[0], "└── Class(Point)");
+    assert_eq!(lines[1], "    ├── Fun(init, [x])");
+
+    let static_line = lines
+      .iter()
+      .position(|line| *line == "    └── static:")
+      .expect("missing the static group");
+    assert_eq!(lines[static_line + 1], "        └── Fun(origin, [])");
+  }
+
+  #[test]
+  fn test_class_display_marks_static_methods() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      "class Point { init(x) { this.x = x; } static origin() { return Point(0); } }",
+      false,
+      &mut engine,
+    );
+
+    assert!(!engine.has_errors());
+    let display = parser.ast[0].to_string();
+    let statics = display
+      .find("static [")
+      .expect("missing the static methods");
+
+    assert!(display.starts_with("Class(Point, [Fun(init, [x]"));
+    assert!(display[statics..].starts_with("static [Fun(origin, []"));
+  }
+
+  #[test]
+  fn test_method_call_tree_shows_callee_property_and_arguments() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("foo.bar(1, 2);\nfoo.baz = 3;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let call = parser.ast[0].tree_string();
+    let set = parser.ast[1].tree_string();
+
+    for label in [
+      "Call",
+      "callee:",
+      "Get(bar)",
+      "Identifier(foo)",
+      "arguments:",
+      "├── Literal(1)",
+      "└── Literal(2)",
+    ] {
+      assert!(call.contains(label), "missing {label} in\n{call}");
+    }
+    for label in ["Set(baz)", "object:", "value:", "Literal(3)"] {
+      assert!(set.contains(label), "missing {label} in\n{set}");
+    }
+  }
+
+  #[test]
+  fn test_to_dot_links_var_decl_to_binary() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("var x = 1 + 2;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let dot = parser.ast[0].to_dot();
+
+    assert!(dot.starts_with("digraph AST {"));
+    assert!(dot.contains("n0 [label=\"VarDecl(x)\"];"));
+    assert!(dot.contains("n1 [label=\"Binary(+)\"];"));
+    assert!(dot.contains("n0 -> n1;"));
+    assert!(dot.contains("n1 -> n2;") && dot.contains("n1 -> n3;"));
+  }
+
+  #[test]
+  fn test_to_dot_escapes_quotes_in_lexemes() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(r#"print("say \"hi\"");"#, false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let dot = parser.ast[0].to_dot();
+
+    assert!(dot.contains(r#"[label="Literal(say \"hi\")"];"#), "{dot}");
+  }
+
+  #[test]
+  fn test_node_at_finds_the_smallest_enclosing_node() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("a + b * c;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let identifier = parser.node_at(4).expect("no node at `b`");
+    assert_eq!(parser.node_span(identifier), Some(4..5));
+
+    let binary = parser.node_at(6).expect("no node at `*`");
+    assert_eq!(parser.node_span(binary), Some(4..9));
+
+    let stmt = parser.node_at(9).expect("no node at `;`");
+    assert_eq!(parser.node_span(stmt), Some(0..10));
+    assert_eq!(parser.node_at(42), None);
+  }
+
+  #[test]
+  fn test_node_ids_are_unique_and_map_back_to_their_nodes() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("a + b * c;\nvar x = 1;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast_ids.len(), 2);
+    assert_ne!(parser.ast_ids[0], parser.ast_ids[1]);
+
+    let identifier = parser.node_at(4).expect("no node at `b`");
+    assert!(matches!(
+      parser.node(identifier),
+      Some(Node::Expr(Expr::Identifier(token))) if token.lexeme == "b"
+    ));
+    assert!(matches!(
+      parser.node(parser.ast_ids[1]),
+      Some(Node::Stmt(stmt)) if matches!(&**stmt, Stmt::VarDecl(name, _) if name.lexeme == "x")
+    ));
+    assert_eq!(parser.node_span(parser.ast_ids[1]), Some(11..21));
+  }
+
+  /// Function that returns the (line, column, length) of every label of the first diagnostic
+  fn label_spans(engine: &DiagnosticEngine) -> Vec<(usize, usize, usize)> {
+    engine.get_diagnostics()[0]
+      .labels
+      .iter()
+      .map(|label| (label.span.line, label.span.column, label.span.length))
+      .collect()
+  }
+
+  #[test]
+  fn test_var_without_identifier_at_start_of_file() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var ;", false, &mut engine);
+
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::ExpectedIdentifier
+    );
+    assert_eq!(label_spans(&engine), vec![(1, 5, 1), (1, 1, 3)]);
+  }
+
+  #[test]
+  fn test_var_with_number_instead_of_identifier() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var 123;", false, &mut engine);
+
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::ExpectedIdentifier
+    );
+    assert_eq!(label_spans(&engine), vec![(1, 5, 3), (1, 1, 3)]);
+  }
+
+  #[test]
+  fn test_var_at_end_of_line_does_not_underflow() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var\n", false, &mut engine);
+
+    assert_eq!(label_spans(&engine), vec![(2, 1, 1), (1, 1, 3)]);
+  }
+
+  #[test]
+  fn test_var_missing_equal_points_at_both_tokens() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var x 1;", false, &mut engine);
+
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::UnexpectedToken
+    );
+    assert_eq!(label_spans(&engine), vec![(1, 7, 1), (1, 5, 1)]);
+  }
+
+  #[test]
+  fn test_parse_only_eof_token() {
+    let mut engine = DiagnosticEngine::new();
+    let eof = scanner::token::Token::new(
+      scanner::token::types::TokenType::Eof,
+      String::new(),
+      scanner::token::types::Literal::Nil,
+      (0, 0),
+    );
+
+    let mut parser = Parser::new(vec![eof]);
+    parser.parse(&mut engine);
+
+    assert!(parser.ast.is_empty());
+    assert!(!engine.has_errors());
+  }
+
+  #[test]
+  fn test_parse_empty_token_stream() {
+    let mut engine = DiagnosticEngine::new();
+
+    let mut parser = Parser::new(Vec::new());
+    parser.parse(&mut engine);
+
+    assert!(parser.ast.is_empty());
+    assert!(!engine.has_errors());
+    assert_eq!(Parser::new(Vec::new()).into_ast().0.len(), 0);
+  }
+
+  #[test]
+  fn test_peek_looks_ahead_without_advancing() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("var a;".to_string());
+    scanner.scan(&mut engine);
+
+    let parser = Parser::new(scanner.tokens);
+
+    assert_eq!(parser.peek(0).lexeme, "var");
+    assert_eq!(parser.peek(1).lexeme, "a");
+    assert_eq!(parser.peek(0).lexeme, "var");
+  }
+
+  #[test]
+  fn test_peek_past_the_end_is_eof() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("a".to_string());
+    scanner.scan(&mut engine);
+
+    let parser = Parser::new(scanner.tokens);
+
+    assert_eq!(parser.peek(1).token_type, TokenType::Eof);
+    assert_eq!(parser.peek(100).token_type, TokenType::Eof);
+    assert_eq!(Parser::new(Vec::new()).peek(1).token_type, TokenType::Eof);
+  }
+
+  #[test]
+  fn test_var_initializer_at_end_of_input_does_not_panic() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var a = fun", false, &mut engine);
+    parse("var a = b", false, &mut engine);
+
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_trailing_comma_in_arguments_warns_once() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("foo(1, 2,);", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 1);
+    assert_eq!(engine.warning_count(), 1);
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::TrailingComma
+    );
+    assert_eq!(label_spans(&engine), vec![(1, 9, 1)]);
+  }
+
+  #[test]
+  fn test_arguments_without_trailing_comma_do_not_warn() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("foo(1, 2);", false, &mut engine);
+
+    assert!(engine.get_diagnostics().is_empty());
+  }
+
+  #[test]
+  fn test_trailing_comma_in_parameters_warns() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("fun foo(a, b,) { return a; }", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(engine.warning_count(), 1);
+  }
+
+  #[test]
+  fn test_trailing_comma_warning_can_be_turned_off() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("foo(1, 2,);".to_string());
+    scanner.scan(&mut engine);
+
+    let mut parser = Parser::new(scanner.tokens);
+    parser.warn_trailing_comma = false;
+    parser.parse(&mut engine);
+
+    assert_eq!(parser.ast.len(), 1);
+    assert!(engine.get_diagnostics().is_empty());
+  }
+
+  /// Function that returns `count` comma separated entries made with `entry`
+  fn list(count: usize, entry: fn(usize) -> String) -> String {
+    (0..count).map(entry).collect::<Vec<_>>().join(", ")
+  }
+
+  #[test]
+  fn test_call_with_256_arguments_is_an_error() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse(
+      &format!("foo({});", list(256, |i| i.to_string())),
+      false,
+      &mut engine,
+    );
+
+    assert_eq!(engine.error_count(), 1);
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::WrongNumberOfArguments);
+    // The label sits on the 256th argument, `255`
+    let span = &diagnostic.labels[0].span;
+    assert_eq!(span.length, 3);
+  }
+
+  #[test]
+  fn test_call_with_255_arguments_is_accepted() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      &format!("foo({});", list(255, |i| i.to_string())),
+      false,
+      &mut engine,
+    );
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 1);
+  }
+
+  #[test]
+  fn test_function_with_256_parameters_is_an_error() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse(
+      &format!("fun foo({}) {{}}", list(256, |i| format!("p{}", i))),
+      false,
+      &mut engine,
+    );
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::WrongNumberOfArguments
+    );
+
+    let mut engine = DiagnosticEngine::new();
+    parse(
+      &format!("fun foo({}) {{}}", list(255, |i| format!("p{}", i))),
+      false,
+      &mut engine,
+    );
+    assert!(!engine.has_errors());
+  }
+
+  #[test]
+  fn test_return_without_semicolon_suggests_inserting_it() {
+    let mut engine = DiagnosticEngine::new();
+    let source = "fun f() {\n  return 1\n}";
+
+    parse(source, false, &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::MissingSemicolon);
+    let output = diagnostic::formatter::DiagnosticFormatter::new(diagnostic, source).format_plain();
+    assert!(output.contains("= help: try this: `return 1;`"), "{output}");
+    // The caret sits on the value, not on the `}` on the following line
+    assert_eq!(label_spans(&engine), vec![(2, 10, 1)]);
+  }
+
+  #[test]
+  fn test_number_after_dot_is_expected_identifier() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("x.0;", false, &mut engine);
+
+    assert_eq!(engine.error_count(), 1);
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::ExpectedIdentifier);
+    assert!(diagnostic.help.is_some());
+    assert_eq!(label_spans(&engine), vec![(1, 3, 1), (1, 2, 1)]);
+  }
+
+  #[test]
+  fn test_dot_at_eof_is_expected_identifier() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("x.", false, &mut engine);
+
+    assert_eq!(engine.error_count(), 1);
+    assert_eq!(
+      engine.get_diagnostics()[0].code,
+      DiagnosticCode::ExpectedIdentifier
+    );
+  }
+
+  #[test]
+  fn test_labeled_for_keeps_the_label_on_the_loop() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse(
+      "outer: for (; i < 3; i = i + 1) { break outer; }",
+      false,
+      &mut engine,
+    );
+
+    assert!(!engine.has_errors());
+    let parser::stmt::Stmt::While(_, body, Some(_), Some(label)) = &parser.ast[0] else {
+      panic!("expected a labeled while loop, got {}", parser.ast[0]);
+    };
+    assert_eq!(label.lexeme, "outer");
+    assert!(body.to_string().contains("Break(break, outer)"), "{body}");
+  }
+
+  #[test]
+  fn test_nameless_fun_statement_is_an_anonymous_function_call() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("fun () { return 1; }();", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 1);
+    let parser::stmt::Stmt::Expr(parser::expr::Expr::Call { callee, .. }) = &parser.ast[0] else {
+      panic!("expected a call statement, got {}", parser.ast[0]);
+    };
+    assert!(matches!(**callee, parser::expr::Expr::Lambda { .. }));
+  }
+
+  #[test]
+  fn test_named_fun_in_var_initializer_is_a_single_declaration() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("var g = fun named() {}", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 1);
+    let Stmt::VarDecl(_, Some(Expr::Lambda { name, .. })) = &parser.ast[0] else {
+      panic!("expected a function value, got {}", parser.ast[0]);
+    };
+    assert_eq!(
+      name.as_ref().map(|name| name.lexeme.as_str()),
+      Some("named")
+    );
+  }
+
+  #[test]
+  fn test_nested_ternary_in_else_branch_is_right_associative() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("a ? b : c ? d : e;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let parser::stmt::Stmt::Expr(parser::expr::Expr::Ternary {
+      then_branch,
+      else_branch,
+      ..
+    }) = &parser.ast[0]
+    else {
+      panic!("expected a ternary, got {}", parser.ast[0]);
+    };
+    assert!(matches!(**then_branch, parser::expr::Expr::Identifier(_)));
+    assert!(matches!(**else_branch, parser::expr::Expr::Ternary { .. }));
+  }
+
+  #[test]
+  fn test_nested_ternary_in_then_branch() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("a ? b ? c : d : e;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let parser::stmt::Stmt::Expr(parser::expr::Expr::Ternary {
+      then_branch,
+      else_branch,
+      ..
+    }) = &parser.ast[0]
+    else {
+      panic!("expected a ternary, got {}", parser.ast[0]);
+    };
+    assert!(matches!(**then_branch, parser::expr::Expr::Ternary { .. }));
+    assert!(matches!(**else_branch, parser::expr::Expr::Identifier(_)));
+  }
+
+  #[test]
+  fn test_comma_in_ternary_then_branch_is_rejected() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("a ? b, c : d;", false, &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::UnexpectedToken);
+    assert!(diagnostic.message.contains("Comma operator"));
+    assert_eq!(label_spans(&engine), vec![(1, 6, 1), (1, 3, 1)]);
+  }
+
+  #[test]
+  fn test_parenthesized_comma_in_ternary_then_branch_is_allowed() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("a ? (b, c) : d;", false, &mut engine);
+
+    assert!(!engine.has_errors());
+  }
+
+  #[test]
+  fn test_recovery_reports_errors_on_consecutive_lines() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("var = 1;\nvar = 2;\nvar c = 1;", false, &mut engine);
+
+    let lines: Vec<usize> = engine
+      .get_diagnostics()
+      .iter()
+      .map(|diagnostic| diagnostic.labels[0].span.line)
+      .collect();
+    assert_eq!(lines, vec![1, 2]);
+  }
+
+  #[test]
+  fn test_recovery_resumes_at_statement_keyword_without_semicolon() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("var = 1\nvar = 2\nvar c = 1;", false, &mut engine);
+
+    assert_eq!(engine.get_diagnostics().len(), 2);
+    assert_eq!(engine.get_diagnostics()[1].labels[0].span.line, 2);
+    assert_eq!(parser.ast.len(), 1);
+  }
+
+  /// Function that prints the parsed program back to source and asserts that parsing the output
+  /// builds the same trees, returns the printed source
+  fn assert_round_trips(source: &str) -> String {
+    let mut engine = DiagnosticEngine::new();
+    let parser = parse(source, false, &mut engine);
+    assert!(!engine.has_errors(), "{:?}", engine.get_diagnostics());
+
+    let printed = parser
+      .ast
+      .iter()
+      .map(|stmt| stmt.to_source())
+      .collect::<Vec<_>>()
+      .join("\n");
+    let reparsed = parse(&printed, false, &mut engine);
+    assert!(!engine.has_errors(), "{printed}");
+
+    let dots =
+      |ast: &[parser::stmt::Stmt]| ast.iter().map(|stmt| stmt.to_dot()).collect::<Vec<_>>();
+    assert_eq!(dots(&parser.ast), dots(&reparsed.ast), "{printed}");
+    printed
+  }
+
+  #[test]
+  fn test_to_source_prints_if_else_with_indentation() {
+    let printed = assert_round_trips("if (a > 1) { print(a); } else if (b) x = 1; else { y = 2; }");
+
+    assert_eq!(
+      printed,
+      "if (a > 1) {\n  print(a);\n} else if (b) {\n  x = 1;\n} else {\n  y = 2;\n}"
+    );
+  }
+
+  #[test]
+  fn test_to_source_round_trips_nested_binary_expressions() {
+    let printed = assert_round_trips("var x = (1 + 2) * 3 - 4 / (5 - -6) ** 2 ** 3 % 7;");
+
+    assert_eq!(printed, "var x = (1 + 2) * 3 - 4 / (5 - -6) ** 2 ** 3 % 7;");
+  }
+
+  #[test]
+  fn test_to_source_adds_parentheses_the_tree_needs() {
+    let mut engine = DiagnosticEngine::new();
+    let parser = parse("1 + 2; a * b; 3;", false, &mut engine);
+    let expr = |stmt: &parser::stmt::Stmt| match stmt {
+      parser::stmt::Stmt::Expr(expr) => expr.clone(),
+      _ => panic!("expected an expression statement"),
+    };
+    let parser::expr::Expr::Binary { operator, .. } = expr(&parser.ast[1]) else {
+      panic!("expected a binary expression");
+    };
+
+    let product = parser::expr::Expr::Binary {
+      lhs: Box::new(expr(&parser.ast[0])),
+      operator,
+      rhs: Box::new(expr(&parser.ast[2])),
+    };
+
+    assert_eq!(product.to_source(), "(1 + 2) * 3");
+  }
+
+  #[test]
+  fn test_to_source_round_trips_every_statement() {
+    assert_round_trips(
+      r#"
+      class Point < Base {
+        init(x, y) { this.x = x; this.y = y; }
+        len() { return this.x ** 2 + this.y ** 2; }
+        static origin() { return Point(0, 0); }
+      }
+      fun add(a, b) { return a + b; }
+      var f = fun (n) { return n * 2; };
+      outer: for (var i = 0; i < 3; i = i + 1) {
+        while (true) { break outer; }
+        continue;
+      }
+      for (;;) break;
+      do x = x - 1; while (x > 0);
+      switch (x) { case 1: print("one"); default: print('say "hi"\n'); }
+      print("sum ${add(1, 2)} and ${[1, 2,]}", typeof x, !!x, - -x, x ? y : z ? 1 : 2);
+      a = b = c, d;
+      obj.field = super.method;
+      fun () { return; }();
+      "#,
+    );
+  }
+
+  #[test]
+  fn test_match_arms_keep_their_order() {
+    let mut engine = DiagnosticEngine::new();
+
+    let parser = parse("match (x) { 1 => a, -2 => b, _ => c };", false, &mut engine);
+
+    assert!(!engine.has_errors());
+    let parser::stmt::Stmt::Expr(parser::expr::Expr::Match { arms, .. }) = &parser.ast[0] else {
+      panic!("expected a match, got {}", parser.ast[0]);
+    };
+    assert_eq!(arms.len(), 3);
+    assert!(matches!(arms[0].0, Some(parser::expr::Expr::Literal(_))));
+    assert!(matches!(arms[1].0, Some(parser::expr::Expr::Unary { .. })));
+    assert!(arms[2].0.is_none());
+  }
+
+  #[test]
+  fn test_match_pattern_must_be_a_literal() {
+    let mut engine = DiagnosticEngine::new();
+
+    parse("match (x) { y => 1 };", false, &mut engine);
+
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::UnexpectedToken);
+    assert!(diagnostic.message.contains("literals"));
+    assert_eq!(label_spans(&engine), vec![(1, 13, 1)]);
+  }
+
+  #[test]
+  fn test_to_source_round_trips_match() {
+    let printed = assert_round_trips("var s = match (x) { 1 => \"one\", -1 => nil, _ => x * 2 };");
+
+    assert_eq!(
+      printed,
+      "var s = match (x) {\n  1 => \"one\",\n  -1 => nil,\n  _ => x * 2,\n};"
+    );
+  }
+
+  #[test]
+  fn test_and_or_parse_as_logical_nodes() {
+    let mut engine = DiagnosticEngine::new();
+    let parser = parse("a or b and c;", false, &mut engine);
+
+    let parser::stmt::Stmt::Expr(parser::expr::Expr::Logical { operator, rhs, .. }) =
+      &parser.ast[0]
+    else {
+      panic!("expected a logical expression");
+    };
+    assert_eq!(operator.token_type, TokenType::Or);
+    assert!(matches!(
+      rhs.as_ref(),
+      parser::expr::Expr::Logical { operator, .. } if operator.token_type == TokenType::And
+    ));
+    assert_eq!(assert_round_trips("a or b and c;"), "a or b and c;");
+  }
+
+  #[test]
+  fn test_template_keeps_statements_out_of_the_ast() {
+    let mut engine = DiagnosticEngine::new();
+    let parser = parse(r#"print("${fun () { return 1; }()}");"#, false, &mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(parser.ast.len(), 1);
+  }
+}
@@ -1,12 +1,19 @@
-use crate::token::Token;
+use crate::token::{Token, DEFAULT_FILE};
 use diagnostic::DiagnosticEngine;
+use std::rc::Rc;
 
 pub mod token;
 mod utils;
 
+pub use utils::unescape;
+
 pub struct Scanner {
   pub tokens: Vec<Token>,
   pub source: String,
+  /// File name every scanned token reports in its spans
+  pub file: Rc<str>,
+  /// Warn about lines indented with both tabs and spaces
+  pub warn_mixed_indent: bool,
   pub line: usize,
   pub column: usize,
   pub current: usize,
@@ -18,6 +25,8 @@ impl Scanner {
   pub fn new(source: String) -> Self {
     Self {
       source,
+      file: Rc::from(DEFAULT_FILE),
+      warn_mixed_indent: false,
       column: 0,
       line: 0,
       start: 0,
@@ -26,8 +35,20 @@ impl Scanner {
     }
   }
 
-  /// Funciton that scans the string buffer and returns tokens
-  pub fn scan(&mut self, engine: &mut DiagnosticEngine) {
+  /// Function that sets the file name the tokens report, `<repl>` for the interactive mode
+  pub fn with_file(mut self, file: &str) -> Self {
+    self.file = Rc::from(file);
+    self
+  }
+
+  /// Funciton that scans the string buffer and returns tokens, they stay in `tokens` as well
+  pub fn scan(&mut self, engine: &mut DiagnosticEngine) -> &[Token] {
     self.get_tokens(engine);
+
+    if self.warn_mixed_indent {
+      self.check_indentation(engine);
+    }
+
+    &self.tokens
   }
 }
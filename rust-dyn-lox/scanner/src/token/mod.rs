@@ -1,15 +1,24 @@
-use diagnostic::diagnostic::Span;
+use std::{ops::Range, rc::Rc};
+
+use diagnostic::{diagnostic::Span, json};
 
 use crate::token::types::{Literal, TokenType};
 
 pub mod types;
 
+/// File name of the tokens that weren't scanned from a named file
+pub const DEFAULT_FILE: &str = "input.duck";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Token {
   pub token_type: TokenType,
   pub lexeme: String,
   pub literal: Literal,
   pub position: (usize, usize),
+  /// File the token was scanned from, shared by every token of that file
+  pub file: Rc<str>,
+  /// Byte range of the token in the source, quotes and `_` separators included
+  pub range: Range<usize>,
 }
 
 impl Token {
@@ -24,25 +33,73 @@ impl Token {
       lexeme,
       literal,
       position,
+      file: Rc::from(DEFAULT_FILE),
+      range: 0..0,
     }
   }
 
+  /// Function that sets the file the token reports in its spans
+  pub fn with_file(mut self, file: Rc<str>) -> Self {
+    self.file = file;
+    self
+  }
+
+  /// Function that compares two tokens by type, lexeme and literal, unlike `==` it doesn't care
+  /// where they were scanned, which keeps tests from depending on exact positions
+  pub fn eq_ignoring_position(&self, other: &Token) -> bool {
+    self.token_type == other.token_type
+      && self.lexeme == other.lexeme
+      && self.literal == other.literal
+  }
+
   /// Function that takes a token and turn it to a span for the engine
   pub fn to_span(&self) -> Span {
     Span {
-      file: "input.duck".to_string(),
+      file: self.file.to_string(),
       line: self.position.0,
       column: self.position.1,
-      length: self.lexeme.len(),
+      length: self.lexeme.chars().count(),
+    }
+  }
+
+  /// Function that returns the span of the whole token, quotes included, on the 1-based line and
+  /// column the formatter reads, the length counts chars so multibyte text keeps its width
+  pub fn source_span(&self) -> Span {
+    let bytes = if self.range.is_empty() {
+      self.lexeme.len()
+    } else {
+      self.range.len()
+    };
+    let length = bytes.saturating_sub(self.lexeme.len() - self.lexeme.chars().count());
+
+    Span {
+      file: self.file.to_string(),
+      line: self.position.0 + 1,
+      column: (self.position.1 + 1).saturating_sub(length),
+      length,
     }
   }
 
   pub fn to_span_with_token(token: Token) -> Span {
     Span {
-      file: "input.duck".to_string(),
+      file: token.file.to_string(),
       line: token.position.0,
       column: token.position.1,
       length: token.lexeme.len(),
     }
   }
+
+  /// Function that renders the token as a JSON object for editors and test harnesses, the line
+  /// and column are 1-based and point at the token start, the length covers the source text
+  pub fn to_json(&self) -> String {
+    let span = self.source_span();
+    format!(
+      "{{\"kind\": {}, \"lexeme\": {}, \"line\": {}, \"column\": {}, \"length\": {}}}",
+      json::quote(&format!("{:?}", self.token_type)),
+      json::quote(&self.lexeme),
+      span.line,
+      span.column,
+      span.length
+    )
+  }
 }
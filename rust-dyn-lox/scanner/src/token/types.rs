@@ -24,22 +24,31 @@ pub enum TokenType {
   DivideEqual,
   Multiply,
   MultiplyEqual,
+  StarStar,
   SemiColon,
   Colon,
   Question,
   Modulus,
+  Underscore,
+  Ampersand,
+  Pipe,
+  Caret,
   // One or two character tokens.
   Bang,
   BangEqual,
   Equal,
   EqualEqual,
+  FatArrow,
   Greater,
   GreaterEqual,
   Less,
   LessEqual,
+  LessLess,
+  GreaterGreater,
   // Literals.
   Identifier,
   String,
+  Template,
   Number,
   // Keywords.
   And,
@@ -58,6 +67,12 @@ pub enum TokenType {
   True,
   Var,
   While,
+  Do,
+  Switch,
+  Case,
+  Default,
+  Match,
+  Typeof,
   Eof,
   Break,
   Continue,
@@ -86,24 +101,33 @@ impl fmt::Display for TokenType {
       TokenType::DivideEqual => "/=",
       TokenType::Multiply => "*",
       TokenType::MultiplyEqual => "*=",
+      TokenType::StarStar => "**",
       TokenType::SemiColon => ";",
       TokenType::Colon => ":",
       TokenType::Question => "?",
       TokenType::Modulus => "%",
+      TokenType::Underscore => "_",
+      TokenType::Ampersand => "&",
+      TokenType::Pipe => "|",
+      TokenType::Caret => "^",
 
       // One or two character tokens
       TokenType::Bang => "!",
       TokenType::BangEqual => "!=",
       TokenType::Equal => "=",
       TokenType::EqualEqual => "==",
+      TokenType::FatArrow => "=>",
       TokenType::Greater => ">",
       TokenType::GreaterEqual => ">=",
       TokenType::Less => "<",
       TokenType::LessEqual => "<=",
+      TokenType::LessLess => "<<",
+      TokenType::GreaterGreater => ">>",
 
       // Literals
       TokenType::Identifier => "identifier",
       TokenType::String => "string",
+      TokenType::Template => "template",
       TokenType::Number => "number",
 
       // Keywords
@@ -123,6 +147,12 @@ impl fmt::Display for TokenType {
       TokenType::True => "true",
       TokenType::Var => "var",
       TokenType::While => "while",
+      TokenType::Do => "do",
+      TokenType::Switch => "switch",
+      TokenType::Case => "case",
+      TokenType::Default => "default",
+      TokenType::Match => "match",
+      TokenType::Typeof => "typeof",
       TokenType::Eof => "eof",
       TokenType::Break => "break",
       TokenType::Continue => "continue",
@@ -14,7 +14,7 @@ use crate::{
 
 impl Scanner {
   /// Function that maps over the "lox" and returns a `Vec<Token>`.
-  pub fn get_tokens(&mut self, engine: &mut DiagnosticEngine) -> () {
+  pub fn get_tokens(&mut self, engine: &mut DiagnosticEngine) {
     while !self.is_at_end() {
       self.start = self.current;
       let c = self.advance();
@@ -65,6 +65,9 @@ impl Scanner {
           if self.match_char(&'=') {
             self.advance();
             Some(TokenType::MultiplyEqual)
+          } else if self.match_char(&'*') {
+            self.advance();
+            Some(TokenType::StarStar)
           } else {
             Some(TokenType::Multiply)
           }
@@ -78,26 +81,27 @@ impl Scanner {
         // Strings
         '"' | '\'' | '`' => Some(self.tokenize_strings(engine)),
 
-        // And condition check
+        // And condition check and the bitwise and
         '&' => {
           if self.match_char(&'&') {
             self.advance();
             Some(TokenType::And)
           } else {
-            None
+            Some(TokenType::Ampersand)
           }
         },
+        '^' => Some(TokenType::Caret),
 
         // Ternary operators
         '?' => Some(TokenType::Question),
         ':' => Some(TokenType::Colon),
-        // Or condition check
+        // Or condition check and the bitwise or
         '|' => {
           if self.match_char(&'|') {
             self.advance();
             Some(TokenType::Or)
           } else {
-            None
+            Some(TokenType::Pipe)
           }
         },
 
@@ -106,6 +110,9 @@ impl Scanner {
           if self.match_char(&'=') {
             self.advance();
             Some(TokenType::GreaterEqual)
+          } else if self.match_char(&'>') {
+            self.advance();
+            Some(TokenType::GreaterGreater)
           } else {
             Some(TokenType::Greater)
           }
@@ -114,16 +121,22 @@ impl Scanner {
           if self.match_char(&'=') {
             self.advance();
             Some(TokenType::LessEqual)
+          } else if self.match_char(&'<') {
+            self.advance();
+            Some(TokenType::LessLess)
           } else {
             Some(TokenType::Less)
           }
         },
 
-        // Equal and Strict Equal
+        // Equal, Strict Equal and the arrow of a match arm
         '=' => {
           if self.match_char(&'=') {
             self.advance();
             Some(TokenType::EqualEqual)
+          } else if self.match_char(&'>') {
+            self.advance();
+            Some(TokenType::FatArrow)
           } else {
             Some(TokenType::Equal)
           }
@@ -148,9 +161,9 @@ impl Scanner {
         // Ignore whitespace
         ' ' | '\r' | '\t' => None,
         // String
-        'a'..='z' | 'A'..='Z' | '_' => Some(self.tokenize_keywords()),
+        c if c.is_alphabetic() || c == '_' => Some(self.tokenize_keywords()),
         // Number
-        '0'..='9' => Some(self.tokenize_numbers()),
+        '0'..='9' => Some(self.tokenize_numbers(engine)),
 
         // New line
         '\n' => {
@@ -167,7 +180,7 @@ impl Scanner {
           )
           .with_label(Label::primary(
             Span {
-              file: "input".to_string(),
+              file: self.file.to_string(),
               line: self.line,
               column: self.column,
               length: 1,
@@ -190,12 +203,23 @@ impl Scanner {
       lexeme: String::from(""),
       literal: Literal::Nil,
       position: (self.line, self.column),
+      file: self.file.clone(),
+      range: self.current..self.current,
     });
-
-    ()
   }
 
   fn tokenize_dot(&mut self) -> Option<TokenType> {
+    // After an operand the dot is a property access, `x.0` is `x` `.` `0` and not `x` `0.0`
+    let after_operand = self.tokens.last().is_some_and(|token| {
+      matches!(
+        token.token_type,
+        TokenType::Identifier | TokenType::RightParen | TokenType::RightBracket | TokenType::This
+      )
+    });
+    if after_operand {
+      return Some(TokenType::Dot);
+    }
+
     if let Some(char) = self.peek() {
       if char.is_ascii_digit() {
         while let Some(char) = self.peek() {
@@ -212,25 +236,45 @@ impl Scanner {
 
   /// Function that tokenize all the string shapes
   fn tokenize_strings(&mut self, engine: &mut DiagnosticEngine) -> TokenType {
-    let current_char = self.get_current_lexeme().chars().collect::<Vec<_>>()[0];
+    let quote = self.get_current_lexeme().chars().collect::<Vec<_>>()[0];
+
+    if self.consume_string(quote, engine) {
+      return TokenType::Template;
+    }
+
+    TokenType::String
+  }
+
+  /// Function that consumes a string body up to its closing `quote` and returns whether it holds
+  /// a `${...}` interpolation
+  fn consume_string(&mut self, quote: char, engine: &mut DiagnosticEngine) -> bool {
+    let mut interpolated = false;
 
     while let Some(char) = self.peek() {
       self.advance();
-      if (current_char == '\'' && char == '\'')
-        || (current_char == '"' && char == '"')
-        || (current_char == '`' && char == '`')
-      {
+      if char == '\\' {
+        self.check_escape(engine);
+        continue;
+      }
+
+      if char == quote {
         break;
       }
 
-      if char == '\n' && current_char != '`' {
+      if char == '$' && self.peek() == Some('{') {
+        self.consume_interpolation(engine);
+        interpolated = true;
+        continue;
+      }
+
+      if char == '\n' && quote != '`' {
         let diagnostic = Diagnostic::new(
           DiagnosticCode::UnterminatedString,
           "wrong string syntax".to_string(),
         )
         .with_label(Label::primary(
           Span {
-            file: "input".to_string(),
+            file: self.file.to_string(),
             line: self.line,
             column: self.start,
             length: self.get_current_lexeme().len(),
@@ -242,7 +286,110 @@ impl Scanner {
       }
     }
 
-    TokenType::String
+    interpolated
+  }
+
+  /// Function that consumes a `${...}` up to its matching `}`, the `$` is already consumed, the
+  /// strings nested in the expression are skipped whole so their quotes don't end the outer one
+  fn consume_interpolation(&mut self, engine: &mut DiagnosticEngine) {
+    let (line, column) = (self.line, self.column);
+    self.advance(); // consume the {
+
+    let mut depth = 1;
+    while let Some(char) = self.peek() {
+      self.advance();
+      match char {
+        '{' => depth += 1,
+        '}' => {
+          depth -= 1;
+          if depth == 0 {
+            return;
+          }
+        },
+        '"' | '\'' | '`' => {
+          self.consume_string(char, engine);
+        },
+        _ => {},
+      }
+    }
+
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::UnterminatedInterpolation,
+      "unterminated string interpolation".to_string(),
+    )
+    .with_label(Label::primary(
+      Span {
+        file: self.file.to_string(),
+        line,
+        column,
+        length: 2,
+      },
+      Some("this `${` is never closed with `}`".to_string()),
+    ));
+
+    engine.emit(diagnostic);
+  }
+
+  /// Function that consumes the escape sequence after a `\` inside a string and reports the ones
+  /// that aren't supported, `add_token` decodes them with `unescape`.
+  fn check_escape(&mut self, engine: &mut DiagnosticEngine) {
+    let column = self.column;
+
+    let valid = match self.peek() {
+      Some('n' | 't' | 'r' | '0' | '\\' | '"' | '\'' | '$') => {
+        self.advance();
+        true
+      },
+      Some('u') => {
+        self.advance();
+        let mut digits = String::new();
+        let mut closed = false;
+
+        if self.peek() == Some('{') {
+          self.advance();
+          while let Some(char) = self.peek() {
+            if char == '}' {
+              self.advance();
+              closed = true;
+              break;
+            }
+            if !char.is_ascii_hexdigit() {
+              break;
+            }
+            digits.push(self.advance());
+          }
+        }
+
+        closed && unicode_escape(&digits).is_some()
+      },
+      // Leave the newline and the end of the source to the string loop
+      Some('\n') | None => false,
+      Some(_) => {
+        self.advance();
+        false
+      },
+    };
+
+    if !valid {
+      let diagnostic = Diagnostic::new(
+        DiagnosticCode::InvalidCharacter,
+        "unknown escape sequence in string".to_string(),
+      )
+      .with_label(Label::primary(
+        Span {
+          file: self.file.to_string(),
+          line: self.line,
+          column,
+          length: 1,
+        },
+        Some("invalid escape".to_string()),
+      ))
+      .with_help(
+        "supported escapes are \\n, \\t, \\r, \\0, \\\\, \\\", \\', \\$ and \\u{...}".to_string(),
+      );
+
+      engine.emit(diagnostic);
+    }
   }
 
   /// Function that tokenize lox comments and if it's not a comment it might a "division" or `None`
@@ -252,13 +399,10 @@ impl Scanner {
       TokenType::DivideEqual
     } else if self.match_char(&'/') {
       loop {
-        match self.advance() {
-          char => {
-            if char == '\n' {
-              break;
-            };
-          },
-        }
+        let char = self.advance();
+        if char == '\n' {
+          break;
+        };
       }
 
       TokenType::Comment
@@ -286,7 +430,7 @@ impl Scanner {
         )
         .with_label(Label::primary(
           Span {
-            file: "input".to_string(),
+            file: self.file.to_string(),
             line: self.line,
             column: self.column,
             length: self.get_current_lexeme().len(),
@@ -302,38 +446,62 @@ impl Scanner {
     }
   }
 
-  /// Function that tokenize lox numbers and return `TokenType`.
-  fn tokenize_numbers(&mut self) -> TokenType {
+  /// Function that tokenize lox numbers and return `TokenType`, digits can be grouped with single
+  /// `_` separators like `1_000`.
+  fn tokenize_numbers(&mut self, engine: &mut DiagnosticEngine) -> TokenType {
+    let mut after_underscore = false;
+
     while let Some(char) = self.peek() {
       if char.is_ascii_digit() {
         self.advance();
-      } else {
-        if self.match_char(&'.') {
-          match self.peek_next() {
-            Some(char) => {
-              if char.is_ascii_digit() {
-                self.advance();
-              } else {
-                break;
-              }
-            },
-            None => {
-              break;
-            },
-          }
-        } else {
-          break;
+        after_underscore = false;
+      } else if char == '_' {
+        self.advance();
+
+        if after_underscore {
+          self.invalid_underscore("doubled `_` in a number literal", engine);
+        } else if !matches!(self.peek(), Some(next) if next.is_ascii_digit() || next == '_') {
+          self.invalid_underscore("trailing `_` in a number literal", engine);
         }
+        after_underscore = true;
+      } else if self.match_char(&'.') {
+        match self.peek_next() {
+          Some(char) if char.is_ascii_digit() => {
+            self.advance();
+          },
+          _ => {
+            break;
+          },
+        }
+      } else {
+        break;
       }
     }
 
     TokenType::Number
   }
 
+  /// Function that reports the `_` that was just consumed as a misplaced digit separator.
+  fn invalid_underscore(&self, message: &str, engine: &mut DiagnosticEngine) {
+    let diagnostic = Diagnostic::new(DiagnosticCode::InvalidNumber, message.to_string())
+      .with_label(Label::primary(
+        Span {
+          file: self.file.to_string(),
+          line: self.line,
+          column: self.column,
+          length: 1,
+        },
+        Some("misplaced separator".to_string()),
+      ))
+      .with_help("`_` can only appear between two digits, like `1_000`".to_string());
+
+    engine.emit(diagnostic);
+  }
+
   /// Function that tokenize lox keywords and return `TokenType`.
   fn tokenize_keywords(&mut self) -> TokenType {
     while let Some(char) = self.peek() {
-      if char.is_ascii_alphanumeric() || char == '_' {
+      if char.is_alphanumeric() || char == '_' {
         self.advance();
       } else {
         break;
@@ -348,6 +516,13 @@ impl Scanner {
       "else" => TokenType::Else,
       "for" => TokenType::For,
       "while" => TokenType::While,
+      "do" => TokenType::Do,
+      "switch" => TokenType::Switch,
+      "case" => TokenType::Case,
+      "default" => TokenType::Default,
+      "match" => TokenType::Match,
+      "_" => TokenType::Underscore,
+      "typeof" => TokenType::Typeof,
       "break" => TokenType::Break,
       "continue" => TokenType::Continue,
       "class" => TokenType::Class,
@@ -373,14 +548,21 @@ impl Scanner {
         return; // don't add comment tokens
       },
 
-      TokenType::String => {
-        // Remove the quotes from the string literal
-        if lexeme.len() >= 2 {
-          lexeme = lexeme[1..lexeme.len() - 1].to_string();
-        }
+      // Remove the quotes from the string literal and decode the escapes
+      TokenType::String if lexeme.len() >= 2 => {
+        lexeme = unescape(&lexeme[1..lexeme.len() - 1]);
+      },
+
+      // A template keeps its escapes, the parser decodes every literal part once the `${...}`
+      // are split off so an escaped `\${` never starts one
+      TokenType::Template if lexeme.len() >= 2 => {
+        lexeme = lexeme[1..lexeme.len() - 1].to_string();
       },
 
       TokenType::Number => {
+        // Strip the digit separators so the lexeme parses as a plain number
+        lexeme.retain(|char| char != '_');
+
         // Normalize numbers like `.5` → `0.5` and `5.` → `5`
         if lexeme.ends_with('.') {
           lexeme = lexeme.trim_end_matches('.').to_string();
@@ -397,13 +579,15 @@ impl Scanner {
       lexeme,
       literal,
       position: (self.line, self.column),
+      file: self.file.clone(),
+      range: self.start..self.current,
     });
   }
 
   /// Function that gets the literal type of the token.
   fn get_literal(&self, token_type: &TokenType) -> Literal {
     match token_type {
-      TokenType::String => Literal::String,
+      TokenType::String | TokenType::Template => Literal::String,
       TokenType::Number => Literal::Number,
       TokenType::True => Literal::Boolean,
       TokenType::False => Literal::Boolean,
@@ -413,17 +597,18 @@ impl Scanner {
 
   /// Function that returns `bool` which indicate the state at the "EOF".
   fn is_at_end(&self) -> bool {
-    (self.current as usize) == self.source.len()
+    self.current == self.source.len()
   }
 
-  /// Function that return the next char and shift the current and column count to this char.
+  /// Function that return the next char and shift the current and column count to this char,
+  /// `current` is a byte offset so it moves by the UTF-8 width of the char.
   fn advance(&mut self) -> char {
-    let char = self.peek();
+    let char = self.peek().unwrap();
 
-    self.current += 1;
+    self.current += char.len_utf8();
     self.column += 1;
 
-    char.unwrap()
+    char
   }
 
   /// Function that returns the next char without advancing the pointer.
@@ -432,10 +617,7 @@ impl Scanner {
       return None;
     };
 
-    let char = self.source[(self.current as usize)..]
-      .chars()
-      .next()
-      .unwrap();
+    let char = self.source[self.current..].chars().next().unwrap();
 
     Some(char)
   }
@@ -444,17 +626,47 @@ impl Scanner {
       return None;
     };
 
-    let char = self.source[((self.current + 1) as usize)..]
-      .chars()
-      .next()
-      .unwrap();
+    self.source[self.current..].chars().nth(1)
+  }
 
-    Some(char)
+  /// Function that warns about every line indented with both tabs and spaces, lines holding only
+  /// whitespace are skipped.
+  pub(crate) fn check_indentation(&self, engine: &mut DiagnosticEngine) {
+    for (line, text) in self.source.lines().enumerate() {
+      let indent = text
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+
+      if indent.len() == text.len() || !(indent.contains(' ') && indent.contains('\t')) {
+        continue;
+      }
+
+      let diagnostic = Diagnostic::new(
+        DiagnosticCode::MixedIndentation,
+        "Indentation mixes tabs and spaces".to_string(),
+      )
+      .with_label(Label::primary(
+        Span {
+          file: self.file.to_string(),
+          // The formatter counts lines from 1
+          line: line + 1,
+          column: 0,
+          length: indent.len(),
+        },
+        Some("tabs and spaces here".to_string()),
+      ))
+      .with_help(
+        "Indent with either tabs or spaces, columns shift between editors otherwise".to_string(),
+      );
+
+      engine.emit(diagnostic);
+    }
   }
 
   /// Function that returns the current lexelme.
   fn get_current_lexeme(&self) -> &str {
-    return &self.source[(self.start as usize)..(self.current as usize)];
+    &self.source[self.start..self.current]
   }
 
   /// Function that matches the next char to an argument and returns true.
@@ -463,15 +675,69 @@ impl Scanner {
       return false;
     }
 
-    if &self.source[(self.current as usize)..]
-      .chars()
-      .next()
-      .unwrap()
-      != expected
-    {
+    if &self.source[self.current..].chars().next().unwrap() != expected {
       return false;
     }
 
     true
   }
 }
+
+/// Function that decodes the escape sequences of a string literal, the ones `check_escape`
+/// rejected are kept as written.
+pub fn unescape(raw: &str) -> String {
+  let mut decoded = String::with_capacity(raw.len());
+  let mut chars = raw.chars().peekable();
+
+  while let Some(char) = chars.next() {
+    if char != '\\' {
+      decoded.push(char);
+      continue;
+    }
+
+    match chars.peek() {
+      Some('n') => decoded.push('\n'),
+      Some('t') => decoded.push('\t'),
+      Some('r') => decoded.push('\r'),
+      Some('0') => decoded.push('\0'),
+      Some(escaped @ ('\\' | '"' | '\'' | '$')) => decoded.push(*escaped),
+      Some('u') => {
+        let rest = chars.clone().skip(1).collect::<String>();
+        let decoded_char = rest
+          .strip_prefix('{')
+          .and_then(|rest| rest.split_once('}'))
+          .and_then(|(digits, _)| unicode_escape(digits).map(|char| (char, digits.len())));
+
+        match decoded_char {
+          Some((unicode, len)) => {
+            decoded.push(unicode);
+            // Skip `u{`, the digits and `}`
+            for _ in 0..len + 3 {
+              chars.next();
+            }
+          },
+          None => decoded.push('\\'),
+        }
+        continue;
+      },
+      _ => {
+        decoded.push('\\');
+        continue;
+      },
+    }
+    chars.next();
+  }
+
+  decoded
+}
+
+/// Function that turns the hex digits of a `\u{...}` escape into the char they name.
+fn unicode_escape(digits: &str) -> Option<char> {
+  if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|char| char.is_ascii_hexdigit()) {
+    return None;
+  }
+
+  u32::from_str_radix(digits, 16)
+    .ok()
+    .and_then(char::from_u32)
+}
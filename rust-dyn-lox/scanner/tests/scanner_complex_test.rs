@@ -1,11 +1,13 @@
 #[cfg(test)]
 mod tests {
-  use scanner::{lox::Lox, token::types::TokenType, Scanner};
+  use diagnostic::DiagnosticEngine;
+  use scanner::{token::types::TokenType, Scanner};
   #[test]
   fn test_complex_scanner() {
-    let mut lox = Lox::new();
-    let mut scanner = Scanner::new();
-    scanner.run_file(String::from("tests/files/test_complex.duck"), &mut lox);
+    let source = std::fs::read_to_string("tests/files/test_complex.duck").unwrap();
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source);
+    scanner.scan(&mut engine);
 
     let tokens = scanner.tokens;
 
@@ -42,7 +44,6 @@ mod tests {
       TokenType::Return,
       TokenType::True,
       TokenType::Nil,
-      TokenType::Print,
     ] {
       assert!(
         tokens.iter().any(|t| t.token_type == keyword),
@@ -52,7 +53,8 @@ mod tests {
     }
 
     // --- identifiers ---
-    for name in ["result", "add", "flag"] {
+    // `print` is a native function, so it scans as an identifier
+    for name in ["result", "add", "flag", "print"] {
       assert!(
         tokens.iter().any(|t| t.lexeme == name),
         "Missing identifier {:?}",
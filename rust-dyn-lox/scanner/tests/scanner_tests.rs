@@ -1,20 +1,355 @@
 #[cfg(test)]
 mod tests {
-  use scanner::{lox::Lox, token::types::TokenType, Scanner};
-
-  use super::*;
+  use diagnostic::{diagnostic_code::DiagnosticCode, DiagnosticEngine};
+  use scanner::{
+    token::{
+      types::{Literal, TokenType},
+      Token,
+    },
+    Scanner,
+  };
 
   #[test]
   fn test_identifier_and_equal() {
-    let mut lox = Lox::new();
-    let mut scanner = Scanner::new();
-    scanner.run_file(
-      String::from("tests/files/test_identifier_and_equal.duck"),
-      &mut lox,
-    );
+    let source = std::fs::read_to_string("tests/files/test_identifier_and_equal.duck").unwrap();
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source);
+    scanner.scan(&mut engine);
     let tokens = scanner.tokens;
 
     assert_eq!(tokens[0].token_type, TokenType::Var);
     assert_eq!(tokens.last().unwrap().token_type, TokenType::Eof);
   }
+
+  #[test]
+  fn test_template_string() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(r#""x=${1+2}""#.to_string());
+    scanner.scan(&mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(scanner.tokens[0].token_type, TokenType::Template);
+    assert_eq!(scanner.tokens[0].lexeme, "x=${1+2}");
+  }
+
+  #[test]
+  fn test_unterminated_interpolation() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(r#""x=${1+2""#.to_string());
+    scanner.scan(&mut engine);
+
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_token_eq_ignoring_position() {
+    let first = Token::new(TokenType::Identifier, "x".to_string(), Literal::Nil, (0, 5));
+    let moved = Token::new(TokenType::Identifier, "x".to_string(), Literal::Nil, (3, 1));
+
+    assert!(first.eq_ignoring_position(&moved));
+    assert_ne!(first, moved);
+
+    let other = Token::new(TokenType::Identifier, "y".to_string(), Literal::Nil, (0, 5));
+    assert!(!first.eq_ignoring_position(&other));
+  }
+
+  #[test]
+  fn test_number_separators() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("1_000_000 1_0.2_5".to_string());
+    scanner.scan(&mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(scanner.tokens[0].token_type, TokenType::Number);
+    assert_eq!(scanner.tokens[0].lexeme, "1000000");
+    assert_eq!(scanner.tokens[1].lexeme, "10.25");
+  }
+
+  #[test]
+  fn test_invalid_number_separators() {
+    for (source, column) in [("1__0", 3), ("1_", 2), ("1_;", 2)] {
+      let mut engine = DiagnosticEngine::new();
+      let mut scanner = Scanner::new(source.to_string());
+      scanner.scan(&mut engine);
+
+      let diagnostics = engine.get_diagnostics();
+      assert_eq!(diagnostics.len(), 1, "{}", source);
+      assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidNumber);
+      assert_eq!(diagnostics[0].labels[0].span.column, column, "{}", source);
+    }
+  }
+
+  fn scan_string(source: &str) -> (String, DiagnosticEngine) {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.scan(&mut engine);
+    (scanner.tokens[0].lexeme.clone(), engine)
+  }
+
+  #[test]
+  fn test_string_escapes() {
+    let cases = [
+      (r#""a\nb""#, "a\nb"),
+      (r#""a\tb""#, "a\tb"),
+      (r#""a\rb""#, "a\rb"),
+      (r#""a\0b""#, "a\0b"),
+      (r#""a\\b""#, "a\\b"),
+      (r#""a\"b""#, "a\"b"),
+      (r#""\u{1F986} \u{41}""#, "\u{1F986} A"),
+    ];
+
+    for (source, expected) in cases {
+      let (lexeme, engine) = scan_string(source);
+      assert!(!engine.has_errors(), "{}", source);
+      assert_eq!(lexeme, expected, "{}", source);
+    }
+  }
+
+  #[test]
+  fn test_invalid_string_escape() {
+    let (_, engine) = scan_string(r#""ab\q""#);
+
+    let diagnostics = engine.get_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidCharacter);
+    assert_eq!(diagnostics[0].labels[0].span.column, 4);
+
+    let (_, engine) = scan_string(r#""\u{110000}""#);
+    assert!(engine.has_errors());
+  }
+
+  #[test]
+  fn test_tokens_carry_the_scanned_file_name() {
+    let path = "tests/files/test_identifier_and_equal.duck";
+    let source = std::fs::read_to_string(path).unwrap();
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source).with_file(path);
+    scanner.scan(&mut engine);
+
+    assert_eq!(scanner.tokens[0].to_span().file, path);
+    assert_eq!(scanner.tokens.last().unwrap().to_span().file, path);
+  }
+
+  #[test]
+  fn test_scanner_diagnostics_carry_the_scanned_file_name() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("var n = 1__0;".to_string()).with_file("broken.duck");
+    scanner.scan(&mut engine);
+
+    assert!(engine.has_errors());
+    assert_eq!(
+      engine.get_diagnostics()[0].labels[0].span.file,
+      "broken.duck"
+    );
+  }
+
+  fn scan_indentation(path: &str, warn_mixed_indent: bool) -> DiagnosticEngine {
+    let source = std::fs::read_to_string(path).unwrap();
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source);
+    scanner.warn_mixed_indent = warn_mixed_indent;
+    scanner.scan(&mut engine);
+    engine
+  }
+
+  #[test]
+  fn test_mixed_indentation_warns() {
+    let engine = scan_indentation("tests/files/test_mixed_indent.duck", true);
+
+    assert!(!engine.has_errors());
+    assert_eq!(engine.warning_count(), 1);
+    let diagnostic = &engine.get_diagnostics()[0];
+    assert_eq!(diagnostic.code, DiagnosticCode::MixedIndentation);
+    assert_eq!(diagnostic.labels[0].span.line, 2);
+    assert_eq!(diagnostic.labels[0].span.length, 3);
+  }
+
+  #[test]
+  fn test_consistent_indentation_does_not_warn() {
+    let engine = scan_indentation("tests/files/test_space_indent.duck", true);
+
+    assert_eq!(engine.warning_count(), 0);
+  }
+
+  #[test]
+  fn test_mixed_indentation_is_opt_in() {
+    let engine = scan_indentation("tests/files/test_mixed_indent.duck", false);
+
+    assert_eq!(engine.warning_count(), 0);
+  }
+
+  #[test]
+  fn test_dot_after_operand_is_not_a_number() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("x.0 + .5".to_string());
+    scanner.scan(&mut engine);
+
+    let types = scanner
+      .tokens
+      .iter()
+      .map(|token| token.token_type.clone())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      types,
+      vec![
+        TokenType::Identifier,
+        TokenType::Dot,
+        TokenType::Number,
+        TokenType::Plus,
+        TokenType::Number,
+        TokenType::Eof,
+      ]
+    );
+    assert_eq!(scanner.tokens[4].lexeme, "0.5");
+  }
+
+  #[test]
+  fn test_scan_returns_the_tokens() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("var a = 1;".to_string());
+
+    let types = scanner
+      .scan(&mut engine)
+      .iter()
+      .map(|token| token.token_type.clone())
+      .collect::<Vec<_>>();
+
+    assert!(!engine.has_errors());
+    assert_eq!(
+      types,
+      vec![
+        TokenType::Var,
+        TokenType::Identifier,
+        TokenType::Equal,
+        TokenType::Number,
+        TokenType::SemiColon,
+        TokenType::Eof,
+      ]
+    );
+    assert_eq!(scanner.tokens.len(), types.len());
+  }
+
+  #[test]
+  fn test_bitwise_operators() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("a & b | c ^ d << e >> f && g || h".to_string());
+    scanner.scan(&mut engine);
+
+    let operators: Vec<TokenType> = scanner
+      .tokens
+      .iter()
+      .skip(1)
+      .step_by(2)
+      .map(|token| token.token_type.clone())
+      .collect();
+    assert!(!engine.has_errors());
+    assert_eq!(
+      operators,
+      vec![
+        TokenType::Ampersand,
+        TokenType::Pipe,
+        TokenType::Caret,
+        TokenType::LessLess,
+        TokenType::GreaterGreater,
+        TokenType::And,
+        TokenType::Or,
+        TokenType::Eof,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_string_keeps_its_raw_source_range() {
+    let source = r#"x = "a\"b";"#;
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.scan(&mut engine);
+
+    let string = &scanner.tokens[2];
+    assert!(!engine.has_errors());
+    assert_eq!(string.token_type, TokenType::String);
+    assert_eq!(string.lexeme, "a\"b");
+    assert_eq!(string.range, 4..10);
+    assert_eq!(&source[string.range.clone()], r#""a\"b""#);
+    assert_eq!(string.source_span().column, 5);
+    assert_eq!(string.source_span().length, 6);
+  }
+
+  #[test]
+  fn test_match_tokens() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new("match _ => _x == >=".to_string());
+    scanner.scan(&mut engine);
+
+    let kinds: Vec<TokenType> = scanner
+      .tokens
+      .iter()
+      .map(|token| token.token_type.clone())
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        TokenType::Match,
+        TokenType::Underscore,
+        TokenType::FatArrow,
+        TokenType::Identifier,
+        TokenType::EqualEqual,
+        TokenType::GreaterEqual,
+        TokenType::Eof,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_unicode_identifiers() {
+    let mut engine = DiagnosticEngine::new();
+    let source = "var café = 你好_1;";
+    let mut scanner = Scanner::new(source.to_string());
+    scanner.scan(&mut engine);
+
+    assert!(!engine.has_errors());
+    let kinds: Vec<TokenType> = scanner
+      .tokens
+      .iter()
+      .map(|token| token.token_type.clone())
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        TokenType::Var,
+        TokenType::Identifier,
+        TokenType::Equal,
+        TokenType::Identifier,
+        TokenType::SemiColon,
+        TokenType::Eof,
+      ]
+    );
+
+    let cafe = &scanner.tokens[1];
+    assert_eq!(cafe.lexeme, "café");
+    assert_eq!(cafe.range, 4..9);
+    assert_eq!(&source[cafe.range.clone()], "café");
+    assert_eq!(
+      (cafe.source_span().column, cafe.source_span().length),
+      (5, 4)
+    );
+
+    let nihao = &scanner.tokens[3];
+    assert_eq!(nihao.lexeme, "你好_1");
+    assert_eq!(nihao.range.len(), 8);
+    assert_eq!(nihao.source_span().column, 12);
+  }
+
+  #[test]
+  fn test_template_with_nested_string() {
+    let mut engine = DiagnosticEngine::new();
+    let mut scanner = Scanner::new(r#""q ${"s}"}" "\${x}""#.to_string());
+    scanner.scan(&mut engine);
+
+    assert!(!engine.has_errors());
+    assert_eq!(scanner.tokens[0].token_type, TokenType::Template);
+    assert_eq!(scanner.tokens[0].lexeme, r#"q ${"s}"}"#);
+    assert_eq!(scanner.tokens[1].token_type, TokenType::String);
+    assert_eq!(scanner.tokens[1].lexeme, "${x}");
+  }
 }
@@ -4,14 +4,50 @@ use diagnostic::{
   DiagnosticEngine,
 };
 use parser::{expr::Expr, stmt::Stmt};
-use scanner::token::Token;
-use std::collections::HashMap;
+use scanner::token::{types::TokenType, Token};
+use std::{collections::HashMap, rc::Rc};
+
+/// Where a variable is read or assigned: the file the token was scanned from, compared by
+/// identity, and the token position. Every scan gets a file of its own, so the sites of two REPL
+/// inputs never collide while the tree of either one is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UseSite {
+  file: *const u8,
+  position: (usize, usize),
+}
+
+impl UseSite {
+  pub fn of(token: &Token) -> Self {
+    Self {
+      file: Rc::as_ptr(&token.file) as *const u8,
+      position: token.position,
+    }
+  }
+}
 
 pub struct Resolver {
   scopes: Vec<HashMap<String, VariableState>>,
-  locals: HashMap<String, usize>,
+  /// Scope distance of every use of a local variable, globals are left out
+  locals: HashMap<UseSite, usize>,
   current_class: ClassType,
   current_superclass: ClassType,
+  current_function: FunctionType,
+  /// How many loops enclose the current statement inside the current function
+  loop_depth: usize,
+  /// Labels of the loops enclosing the current statement inside the current function
+  loop_labels: Vec<String>,
+  /// Tailor the help of the diagnostics to input typed at the REPL prompt
+  repl_mode: bool,
+  /// Warn when a `var` declared without an initializer is read before it's assigned
+  warn_uninit: bool,
+  /// Variables declared without an initializer that have not been assigned yet, with the scope
+  /// depth of the declaration
+  uninitialized: HashMap<String, usize>,
+  /// Scope depth where the body of the current function starts, 0 outside of functions
+  function_scope: usize,
+  /// Variables whose latest declaration was initialized with a literal, with the scope depth of
+  /// the declaration and the name of the literal's type
+  literal_vars: HashMap<String, (usize, &'static str)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -23,11 +59,26 @@ enum ClassType {
   StaticMethod,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+  None,
+  Function,
+}
+
 #[derive(Debug, Clone)]
 struct VariableState {
   defined: bool,
   used: bool,
   line: usize,
+  file: String,
+  /// Where the variable was declared, for pointing at it from later diagnostics
+  span: Span,
+}
+
+impl Default for Resolver {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Resolver {
@@ -37,9 +88,29 @@ impl Resolver {
       locals: HashMap::new(),
       current_class: ClassType::None,
       current_superclass: ClassType::None,
+      current_function: FunctionType::None,
+      loop_depth: 0,
+      loop_labels: Vec::new(),
+      repl_mode: false,
+      warn_uninit: false,
+      uninitialized: HashMap::new(),
+      function_scope: 0,
+      literal_vars: HashMap::new(),
     }
   }
 
+  /// Function that marks the resolver as running over REPL input
+  pub fn with_repl_mode(mut self, repl_mode: bool) -> Self {
+    self.repl_mode = repl_mode;
+    self
+  }
+
+  /// Function that enables the uninitialized variable warning
+  pub fn with_warn_uninit(mut self, warn_uninit: bool) -> Self {
+    self.warn_uninit = warn_uninit;
+    self
+  }
+
   pub fn run(&mut self, ast: &Vec<Stmt>, engine: &mut DiagnosticEngine) {
     self.resolve_statements(ast, engine);
   }
@@ -47,7 +118,7 @@ impl Resolver {
   /// Entry points
   pub fn resolve_statements(&mut self, stmts: &Vec<Stmt>, engine: &mut DiagnosticEngine) {
     for s in stmts {
-      self.resolve_stmt(&s, engine);
+      self.resolve_stmt(s, engine);
     }
   }
 
@@ -59,6 +130,28 @@ impl Resolver {
         self.end_scope(engine);
       },
       Stmt::VarDecl(token, value) => {
+        if self.warn_uninit {
+          if value.is_none() {
+            self
+              .uninitialized
+              .insert(token.lexeme.clone(), self.scopes.len());
+          } else {
+            self.uninitialized.remove(&token.lexeme);
+          }
+        }
+
+        match value.as_ref().and_then(literal_type_name) {
+          Some(type_name) => {
+            let depth = self.scopes.len();
+            self
+              .literal_vars
+              .insert(token.lexeme.clone(), (depth, type_name));
+          },
+          None => {
+            self.literal_vars.remove(&token.lexeme);
+          },
+        }
+
         if self.scopes.is_empty() {
           if let Some(value) = value {
             self.resolve_expr(value, engine);
@@ -79,12 +172,32 @@ impl Resolver {
           self.resolve_stmt(else_branch, engine);
         }
       },
-      Stmt::While(condition, body) => {
+      Stmt::While(condition, body, increment, label) => {
+        self.resolve_expr(condition, engine);
+        Self::check_constant_condition(condition, body, engine);
+        self.resolve_loop_body(body, label, engine);
+        if let Some(increment) = increment {
+          self.resolve_expr(increment, engine);
+        }
+      },
+      Stmt::Switch(discriminant, cases) => {
+        self.resolve_expr(discriminant, engine);
+        for (value, stmts) in cases {
+          if let Some(value) = value {
+            self.resolve_expr(value, engine);
+          }
+          self.begin_scope();
+          self.resolve_statements(stmts, engine);
+          self.end_scope(engine);
+        }
+      },
+      Stmt::DoWhile(body, condition, label) => {
+        self.resolve_loop_body(body, label, engine);
         self.resolve_expr(condition, engine);
-        self.resolve_stmt(body, engine);
       },
       Stmt::Fun(name, params, body) => {
         if let Expr::Identifier(name) = name {
+          self.literal_vars.remove(&name.lexeme);
           if !self.scopes.is_empty() {
             self.declare(name, engine);
             self.define(name);
@@ -93,12 +206,39 @@ impl Resolver {
 
         self.resolve_function(params, body, engine);
       },
-      Stmt::Return(_, value) => {
+      Stmt::Return(keyword, value) => {
+        if self.current_function == FunctionType::None {
+          let mut diagnostic = Diagnostic::new(
+            DiagnosticCode::ReturnNotInFunction,
+            "Can't return from top-level code".to_string(),
+          )
+          .with_label(Label::primary(
+            keyword.source_span(),
+            Some("'return' outside of a function".to_string()),
+          ));
+
+          if self.repl_mode {
+            diagnostic = diagnostic.with_help(match value {
+              Some(value) => format!(
+                "cannot return from the top level; did you mean to print `{}`?",
+                value
+              ),
+              None => "cannot return from the top level of the prompt".to_string(),
+            });
+          }
+
+          engine.emit(diagnostic);
+        }
+
         if let Some(value) = value {
           self.resolve_expr(value, engine);
         }
       },
       Stmt::Class(name, superclass_expr, methods, static_methods) => {
+        if let Expr::Identifier(name) = name {
+          self.literal_vars.remove(&name.lexeme);
+        }
+
         let enclosing_class = self.current_class;
         let enclosing_superclass = self.current_superclass; // Store previous state
 
@@ -155,6 +295,8 @@ impl Resolver {
               defined: true,
               used: false,
               line: name_token.position.0,
+              file: name_token.file.to_string(),
+              span: name_token.source_span(),
             },
           );
         }
@@ -167,6 +309,8 @@ impl Resolver {
             defined: true,
             used: false,
             line: name_token.position.0,
+            file: name_token.file.to_string(),
+            span: name_token.source_span(),
           },
         );
 
@@ -184,7 +328,7 @@ impl Resolver {
         }
 
         // 5. Resolve STATIC methods (without 'this' in scope)
-        let prev_class = self.current_class;
+        let _prev_class = self.current_class;
         self.current_class = ClassType::StaticMethod;
 
         for method in static_methods.iter() {
@@ -196,7 +340,107 @@ impl Resolver {
         self.current_superclass = enclosing_superclass;
       },
 
-      Stmt::Break(_) | Stmt::Continue(_) => {},
+      Stmt::Break(keyword, _) if self.loop_depth == 0 => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::BreakOutsideLoop,
+          "Can't break outside of a loop".to_string(),
+        )
+        .with_label(Label::primary(
+          keyword.source_span(),
+          Some("'break' outside of a loop".to_string()),
+        ))
+        .with_help("break can only be used inside while, do-while and for loops".to_string());
+
+        engine.emit(diagnostic);
+      },
+      Stmt::Continue(keyword, _) if self.loop_depth == 0 => {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::ContinueOutsideLoop,
+          "Can't continue outside of a loop".to_string(),
+        )
+        .with_label(Label::primary(
+          keyword.source_span(),
+          Some("'continue' outside of a loop".to_string()),
+        ))
+        .with_help("continue can only be used inside while, do-while and for loops".to_string());
+
+        engine.emit(diagnostic);
+      },
+      Stmt::Break(_, Some(label)) | Stmt::Continue(_, Some(label))
+        if !self.loop_labels.contains(&label.lexeme) =>
+      {
+        let diagnostic = Diagnostic::new(
+          DiagnosticCode::UnknownLabel,
+          format!("Use of undeclared label '{}'", label.lexeme),
+        )
+        .with_label(Label::primary(
+          label.source_span(),
+          Some("no enclosing loop has this label".to_string()),
+        ))
+        .with_help(format!(
+          "label the loop to target with `{}: while (...)`",
+          label.lexeme
+        ));
+
+        engine.emit(diagnostic);
+      },
+      Stmt::Break(..) | Stmt::Continue(..) => {},
+    }
+  }
+
+  /// Function that resolves the body of a loop with the loop and its label in scope
+  fn resolve_loop_body(
+    &mut self,
+    body: &Stmt,
+    label: &Option<Token>,
+    engine: &mut DiagnosticEngine,
+  ) {
+    if let Some(label) = label {
+      self.loop_labels.push(label.lexeme.clone());
+    }
+    self.loop_depth += 1;
+    self.resolve_stmt(body, engine);
+    self.loop_depth -= 1;
+    if label.is_some() {
+      self.loop_labels.pop();
+    }
+  }
+
+  /// Function that lints a `while` on a literal condition, the body of `while (false)` never runs
+  /// and `while (true)` only ends through a `break` or `return` in its body
+  fn check_constant_condition(condition: &Expr, body: &Stmt, engine: &mut DiagnosticEngine) {
+    let Expr::Literal(token) = condition else {
+      return;
+    };
+    // The `true` a `for (;;)` is desugared with isn't in the source
+    if token.range.is_empty() {
+      return;
+    }
+
+    match token.token_type {
+      TokenType::False => engine.emit(
+        Diagnostic::new(
+          DiagnosticCode::UnreachableCode,
+          "Loop body never runs".to_string(),
+        )
+        .with_label(Label::primary(
+          token.source_span(),
+          Some("this condition is always false".to_string()),
+        ))
+        .with_help("remove the loop, or change its condition".to_string()),
+      ),
+      TokenType::True if !escapes_loop(body, false) => engine.emit(
+        Diagnostic::note(
+          DiagnosticCode::Info,
+          "Loop may never end, its body has no 'break'".to_string(),
+        )
+        .with_label(Label::primary(
+          token.source_span(),
+          Some("this condition is always true".to_string()),
+        ))
+        .with_help("add a `break` or a `return` where the loop should stop".to_string()),
+      ),
+      _ => {},
     }
   }
 
@@ -213,22 +457,55 @@ impl Resolver {
             }
           }
         }
-        self.resolve_local(&token.lexeme);
+
+        // Warn once per variable, the following reads are the same mistake. A function body
+        // reading a variable declared outside of it may only run after the assignment
+        let declared_here = self
+          .uninitialized
+          .get(&token.lexeme)
+          .is_some_and(|depth| *depth >= self.function_scope);
+        if declared_here && self.uninitialized.remove(&token.lexeme).is_some() {
+          let diagnostic = Diagnostic::new(
+            DiagnosticCode::UninitializedVariable,
+            format!("Variable '{}' is read before it's assigned", token.lexeme),
+          )
+          .with_label(Label::primary(
+            token.source_span(),
+            Some("this is always nil here".to_string()),
+          ))
+          .with_help(format!(
+            "give '{}' an initial value where it's declared",
+            token.lexeme
+          ));
+          engine.emit(diagnostic);
+        }
+
+        self.resolve_local(token);
       },
       Expr::Call {
         callee,
-        paren,
+        paren: _,
         arguments,
       } => {
+        self.check_literal_call(callee, engine);
         self.resolve_expr(callee, engine);
         for argument in arguments {
           self.resolve_expr(argument, engine);
         }
       },
-      Expr::Unary { operator, rhs } => {
+      Expr::Unary { operator: _, rhs } => {
         self.resolve_expr(rhs, engine);
       },
-      Expr::Binary { lhs, operator, rhs } => {
+      Expr::Binary {
+        lhs,
+        operator: _,
+        rhs,
+      }
+      | Expr::Logical {
+        lhs,
+        operator: _,
+        rhs,
+      } => {
         self.resolve_expr(lhs, engine);
         self.resolve_expr(rhs, engine);
       },
@@ -246,7 +523,9 @@ impl Resolver {
       },
       Expr::Assign { name, value } => {
         self.resolve_expr(value, engine);
-        self.resolve_local(&name.lexeme);
+        self.uninitialized.remove(&name.lexeme);
+        self.literal_vars.remove(&name.lexeme);
+        self.resolve_local(name);
       },
       Expr::Literal(_) => {},
 
@@ -270,7 +549,7 @@ impl Resolver {
           let diagnostic = Diagnostic::new(
             DiagnosticCode::InvalidThis,
             "Can't use 'this' in static methods".to_string(),
-          )  
+          )
             .with_label(Label::primary(
             keyword.to_span(),
             Some("'this' not allowed in static context".to_string()),
@@ -295,10 +574,10 @@ impl Resolver {
           return;
         }
 
-        self.resolve_local(&keyword.lexeme);
+        self.resolve_local(keyword);
       },
 
-      Expr::Super(keyword, method_name) => {
+      Expr::Super(keyword, _method_name) => {
         // Check 1: Must be inside a class
         if self.current_class == ClassType::None {
           let diagnostic = Diagnostic::new(
@@ -344,18 +623,79 @@ impl Resolver {
 
         // Resolve 'super' keyword. This finds the environment where the superclass
         // reference is stored, and records the depth in `self.locals`.
-        self.resolve_local(&keyword.lexeme);
+        self.resolve_local(keyword);
+      },
+      Expr::Array { elements, .. } => {
+        for element in elements {
+          self.resolve_expr(element, engine);
+        }
+      },
+      Expr::Template { expressions, .. } => {
+        for expr in expressions {
+          self.resolve_expr(expr, engine);
+        }
+      },
+      Expr::Lambda {
+        name: None,
+        params,
+        body,
+        ..
+      } => self.resolve_function(params, body, engine),
+      Expr::Lambda {
+        name: Some(name),
+        params,
+        body,
+        ..
+      } => {
+        // The name lives in a scope of its own around the function, so only the body sees it
+        self.begin_scope();
+        self.literal_vars.remove(&name.lexeme);
+        self.scopes.last_mut().unwrap().insert(
+          name.lexeme.clone(),
+          VariableState {
+            defined: true,
+            // Not calling itself is no reason to warn about the name
+            used: true,
+            line: name.position.0,
+            file: name.file.to_string(),
+            span: name.source_span(),
+          },
+        );
+
+        self.resolve_function(params, body, engine);
+        self.end_scope(engine);
+      },
+      Expr::Match {
+        scrutinee, arms, ..
+      } => {
+        self.resolve_expr(scrutinee, engine);
+        for (pattern, value) in arms {
+          if let Some(pattern) = pattern {
+            self.resolve_expr(pattern, engine);
+          }
+          self.resolve_expr(value, engine);
+        }
       },
     }
   }
 
   fn resolve_function(&mut self, params: &[Expr], body: &Stmt, engine: &mut DiagnosticEngine) {
+    let enclosing_function = self.current_function;
+    self.current_function = FunctionType::Function;
+    // A loop around the declaration doesn't make `break` valid inside the body
+    let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+    let enclosing_loop_labels = std::mem::take(&mut self.loop_labels);
     self.begin_scope();
+    let enclosing_function_scope = std::mem::replace(&mut self.function_scope, self.scopes.len());
 
     for param in params {
       if let Expr::Identifier(param) = param {
         if !self.scopes.is_empty() {
-          self.declare(param, engine);
+          self.declare_with_help(
+            param,
+            "Each parameter needs its own name. Rename one of them.",
+            engine,
+          );
           self.define(param);
         }
       }
@@ -363,14 +703,18 @@ impl Resolver {
 
     self.resolve_stmt(body, engine);
     self.end_scope(engine);
+    self.current_function = enclosing_function;
+    self.function_scope = enclosing_function_scope;
+    self.loop_depth = enclosing_loop_depth;
+    self.loop_labels = enclosing_loop_labels;
   }
 
-  fn resolve_local(&mut self, name: &str) {
+  fn resolve_local(&mut self, name: &Token) {
     // Iterate from INNERMOST (last) to OUTERMOST (first)
     for (i, scope) in self.scopes.iter_mut().rev().enumerate() {
-      if let Some(local) = scope.get_mut(name) {
+      if let Some(local) = scope.get_mut(&name.lexeme) {
         local.used = true;
-        self.locals.insert(name.to_string(), i);
+        self.locals.insert(UseSite::of(name), i);
         return;
       }
     }
@@ -383,6 +727,12 @@ impl Resolver {
   }
 
   fn end_scope(&mut self, engine: &mut DiagnosticEngine) {
+    // The literals declared in the closing scope are out of sight for the sibling scopes
+    let depth = self.scopes.len();
+    self
+      .literal_vars
+      .retain(|_, (declared, _)| *declared != depth);
+
     if let Some(scope) = self.scopes.pop() {
       for (name, state) in scope {
         if state.defined && !state.used {
@@ -395,7 +745,7 @@ impl Resolver {
               line: state.line + 1,
               column: 0,
               length: 25,
-              file: "".to_string(),
+              file: state.file,
             },
             Some("never used".to_string()),
           ))
@@ -408,30 +758,70 @@ impl Resolver {
     }
   }
 
+  /// Function that reports calling a variable that can only hold a literal here. It only looks at
+  /// calls in the same scope as the declaration, so a function body or a loop that could see an
+  /// assignment made later in the source is never flagged.
+  fn check_literal_call(&self, callee: &Expr, engine: &mut DiagnosticEngine) {
+    let Expr::Identifier(token) = callee else {
+      return;
+    };
+
+    let Some((depth, type_name)) = self.literal_vars.get(&token.lexeme) else {
+      return;
+    };
+
+    if *depth != self.scopes.len() {
+      return;
+    }
+
+    let diagnostic = Diagnostic::new(
+      DiagnosticCode::InvalidFunctionCall,
+      format!("'{}' is not callable", token.lexeme),
+    )
+    .with_label(Label::primary(
+      token.source_span(),
+      Some(format!("'{}' holds a {} here", token.lexeme, type_name)),
+    ))
+    .with_help("only functions and classes can be called".to_string());
+
+    engine.emit(diagnostic);
+  }
+
   /// Returns true if successful, false if already declared
   fn declare(&mut self, name: &Token, engine: &mut DiagnosticEngine) -> bool {
+    self.declare_with_help(
+      name,
+      "Did you mean to assign to the existing variable? Remove 'var' to assign.",
+      engine,
+    )
+  }
+
+  /// Function that declares `name` like `declare`, with `help` attached if it's a duplicate
+  fn declare_with_help(&mut self, name: &Token, help: &str, engine: &mut DiagnosticEngine) -> bool {
     if self.scopes.is_empty() {
       return true; // global scope, always allow
     }
 
     let scope = self.scopes.last_mut().unwrap();
 
-    // Check for duplicate declaration in same scope
-    if scope.contains_key(&name.lexeme) {
+    // Check for duplicate declaration in same scope, a nested scope may still shadow it
+    if let Some(original) = scope.get(&name.lexeme) {
       let diagnostic = Diagnostic::new(
-        DiagnosticCode::VariableAlreadyDeclared,
+        DiagnosticCode::DuplicateDeclaration,
         format!(
           "Variable '{}' is already declared in this scope",
           name.lexeme
         ),
       )
       .with_label(Label::primary(
-        name.to_span(),
-        Some("already declared here".to_string()),
+        name.source_span(),
+        Some("declared again here".to_string()),
+      ))
+      .with_label(Label::secondary(
+        original.span.clone(),
+        Some(format!("'{}' first declared here", name.lexeme)),
       ))
-      .with_help(
-        "Did you mean to assign to the existing variable? Remove 'var' to assign.".to_string(),
-      );
+      .with_help(help.to_string());
 
       engine.emit(diagnostic);
       return false;
@@ -444,6 +834,8 @@ impl Resolver {
         defined: false,
         used: false,
         line: name.position.0,
+        file: name.file.to_string(),
+        span: name.source_span(),
       },
     );
     true
@@ -460,7 +852,49 @@ impl Resolver {
     }
   }
 
-  pub fn get_locals(&self) -> &HashMap<String, usize> {
+  pub fn get_locals(&self) -> &HashMap<UseSite, usize> {
     &self.locals
   }
 }
+
+/// Function that tells whether a statement in a loop body can leave the loop, through a `return`,
+/// a labeled `break`, or a plain `break` that isn't inside a nested loop. Functions declared in
+/// the body don't count since their `break` and `return` stay inside them.
+fn escapes_loop(stmt: &Stmt, in_nested_loop: bool) -> bool {
+  match stmt {
+    Stmt::Break(_, None) => !in_nested_loop,
+    Stmt::Break(_, Some(_)) | Stmt::Return(..) => true,
+    Stmt::Block(stmts) => stmts.iter().any(|stmt| escapes_loop(stmt, in_nested_loop)),
+    Stmt::If(_, then_branch, else_branch) => {
+      escapes_loop(then_branch, in_nested_loop)
+        || else_branch
+          .as_ref()
+          .is_some_and(|else_branch| escapes_loop(else_branch, in_nested_loop))
+    },
+    Stmt::While(_, body, _, _) | Stmt::DoWhile(body, _, _) => escapes_loop(body, true),
+    // A `break` in a case leaves the loop around the switch
+    Stmt::Switch(_, cases) => cases
+      .iter()
+      .flat_map(|(_, stmts)| stmts)
+      .any(|stmt| escapes_loop(stmt, in_nested_loop)),
+    Stmt::Expr(_) | Stmt::VarDecl(..) | Stmt::Fun(..) | Stmt::Class(..) | Stmt::Continue(..) => {
+      false
+    },
+  }
+}
+
+/// Function that names the type of an initializer that is a plain literal, `None` for anything
+/// whose type is only known at runtime.
+fn literal_type_name(expr: &Expr) -> Option<&'static str> {
+  let Expr::Literal(token) = expr else {
+    return None;
+  };
+
+  match token.token_type {
+    TokenType::Number => Some("number"),
+    TokenType::String | TokenType::Template => Some("string"),
+    TokenType::True | TokenType::False => Some("boolean"),
+    TokenType::Nil => Some("nil"),
+    _ => None,
+  }
+}
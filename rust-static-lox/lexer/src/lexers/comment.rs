@@ -71,11 +71,6 @@ impl Lexer {
       let current = self.peek();
       let next = self.peek_next(1);
 
-      // Handle newlines
-      if current == Some('\n') {
-        self.line += 1;
-      }
-
       // Detect nested comment start "/*"
       if current == Some('/') && next == Some('*') {
         self.advance(); // consume '/'
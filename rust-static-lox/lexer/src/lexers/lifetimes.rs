@@ -36,12 +36,13 @@ impl Lexer {
     // `'a: 'b + 'c`) may require additional handling to ensure full correctness.
 
     let len = chars.len() - 1;
-    for (i, c) in chars.into_iter().enumerate() {
+    for (i, c) in chars.iter().enumerate() {
       if c.is_ascii_alphabetic()
         || c.is_ascii_whitespace()
         || (c.is_ascii_punctuation() && matches!(c, '\'' | ','))
       {
-        self.current -= len - i;
+        // `current` is a byte offset, the trimmed chars may be wider than one byte
+        self.current -= chars[i + 1..].iter().map(|c| c.len_utf8()).sum::<usize>();
         self.column -= len - i;
         break;
       }
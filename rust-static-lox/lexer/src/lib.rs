@@ -28,17 +28,19 @@ pub mod token;
 pub struct Lexer {
   pub source: SourceFile,
   pub tokens: Vec<Token>,
-  pub start: usize,   // Start byte offset of current token
-  pub current: usize, // Current byte offset in source
-  pub line: usize,    // Current line (1-indexed)
-  pub column: usize,  // Current column (1-indexed)
+  pub start: usize,    // Start byte offset of current token
+  pub current: usize,  // Current byte offset in source
+  pub line: usize,     // Current line (1-indexed)
+  pub column: usize,   // Current column (1-indexed)
+  start_line: usize,   // Line of the first character of the current token
+  start_column: usize, // Column of the first character of the current token
 }
 
 impl Lexer {
   /// Creates a new lexer for the given source file.
   ///
-  /// Initializes all position counters to zero. The lexer is ready
-  /// to tokenize the source after construction.
+  /// Initializes the byte offsets to zero and the position to line 1.
+  /// The lexer is ready to tokenize the source after construction.
   ///
   /// # Arguments
   ///
@@ -56,8 +58,10 @@ impl Lexer {
       tokens: Vec::new(),
       start: 0,
       current: 0,
-      line: 0,
+      line: 1,
       column: 0,
+      start_line: 1,
+      start_column: 1,
     }
   }
 
@@ -81,6 +85,7 @@ impl Lexer {
   pub fn scan_tokens(&mut self, engine: &mut DiagnosticEngine) {
     while !self.is_eof() {
       self.start = self.current;
+      self.mark_start();
       let c = self.advance();
 
       let token = self.lex_tokens(c, engine);
@@ -90,9 +95,16 @@ impl Lexer {
       };
     }
 
+    self.mark_start();
     self.emit(TokenKind::Eof);
   }
 
+  /// Records the current position as the start of the next token.
+  fn mark_start(&mut self) {
+    self.start_line = self.line;
+    self.start_column = self.column + 1;
+  }
+
   /// Conditionally consumes the next character if it matches the expected character.
   ///
   /// This is a lookahead operation: if the next character matches, it is consumed
@@ -152,6 +164,8 @@ impl Lexer {
         start: self.start,
         end: self.current,
       },
+      line: self.start_line,
+      column: self.start_column,
     });
     self.start = self.current;
   }
@@ -189,7 +203,7 @@ impl Lexer {
   ///
   /// # Arguments
   ///
-  /// * `offset` - Byte offset from current position (0 = current char, 1 = next char)
+  /// * `offset` - Character offset from current position (0 = current char, 1 = next char)
   ///
   /// # Returns
   ///
@@ -208,13 +222,14 @@ impl Lexer {
       return None;
     }
 
-    self.source.src[(self.current + offset)..].chars().next()
+    self.source.src[self.current..].chars().nth(offset)
   }
 
   /// Advances the cursor by one character and returns it.
   ///
-  /// Handles UTF-8 correctly by computing the byte offset of the next character.
-  /// Updates `current` (byte offset) and `column` (character column) accordingly.
+  /// Handles UTF-8 correctly by moving `current` by the byte width of the character.
+  /// Updates `current` (byte offset) and `column` (character column) accordingly,
+  /// a consumed `\n` moves to the next line and resets `column` to 0.
   ///
   /// # Returns
   ///
@@ -226,29 +241,26 @@ impl Lexer {
   /// // If source is "abc" and current=0:
   /// lexer.advance() // 'a', current=1, column=1
   /// lexer.advance() // 'b', current=2, column=2
+  ///
+  /// // If source is "a\nb" and current=1:
+  /// lexer.advance() // '\n', current=2, line=2, column=0
   /// ```
   fn advance(&mut self) -> char {
-    if self.is_eof() {
+    let Some(ch) = self.peek() else {
       return '\0';
-    }
-
-    // get remaining string slice
-    let remaining = &self.source.src[self.current..];
-    let mut iter = remaining.char_indices();
+    };
 
-    // the first character and its byte offset (always 0)
-    let (_, ch) = iter.next().unwrap();
+    // the char is decoded once, its UTF-8 width moves the byte offset past it
+    self.current += ch.len_utf8();
 
-    // compute byte offset of next character (to move current forward)
-    if let Some((next_byte_idx, _)) = iter.next() {
-      self.current += next_byte_idx;
+    // update line and column count
+    if ch == '\n' {
+      self.line += 1;
+      self.column = 0;
     } else {
-      self.current = self.source.src.len();
+      self.column += 1;
     }
 
-    // update column count
-    self.column += 1;
-
     ch
   }
 
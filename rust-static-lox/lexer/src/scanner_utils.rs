@@ -71,11 +71,8 @@ impl Lexer {
       '^' => self.lex_caret(),
 
       // handle whitespace
-      '\n' => {
-        self.line += 1;
-        self.column = 0;
-        Some(TokenKind::Whitespace)
-      }
+      // `advance` already moved to the next line
+      '\n' => Some(TokenKind::Whitespace),
       '\r' | '\t' | ' ' => self.lex_whitespace(),
 
       // String and character literals
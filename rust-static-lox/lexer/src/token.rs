@@ -6,6 +6,8 @@ use TokenKind::*;
 pub struct Token {
   pub kind: TokenKind,
   pub span: Span,
+  pub line: usize,   // Line of the first character (1-indexed)
+  pub column: usize, // Column of the first character (1-indexed)
 }
 
 // ============================================================================
@@ -421,10 +421,8 @@ mod lexer_tests {
       TokenKind::SlashEq,
       TokenKind::And,
       TokenKind::AndEq,
-      TokenKind::AndAnd,
       TokenKind::Or,
       TokenKind::OrEq,
-      TokenKind::OrOr,
       TokenKind::Caret,
       TokenKind::CaretEq,
       TokenKind::ColonColon,
@@ -484,10 +482,8 @@ mod lexer_tests {
       TokenKind::SlashEq,
       TokenKind::And,
       TokenKind::AndEq,
-      TokenKind::AndAnd,
       TokenKind::Or,
       TokenKind::OrEq,
-      TokenKind::OrOr,
       TokenKind::Caret,
       TokenKind::CaretEq,
       TokenKind::ColonColon,
@@ -625,6 +621,29 @@ mod lexer_tests {
     );
   }
 
+  #[test]
+  fn test_large_multibyte_source_token_count() {
+    let line = "let s = \"héllo → wörld\";\n";
+    let mut builder = String::new();
+    while builder.len() < 100_000 {
+      builder.push_str(line);
+    }
+    let lines = builder.len() / line.len();
+
+    let (lexer, engine) = lex_inline("large-utf8", &builder);
+
+    assert!(!engine.has_errors(), "multibyte source should lex cleanly");
+    assert_eq!(lexer.tokens.len(), lines * 5 + 1);
+    assert_eq!(
+      token_text(&lexer, &lexer.tokens[lexer.tokens.len() - 3]),
+      "\"héllo → wörld\""
+    );
+    assert!(lexer.tokens.iter().all(|tok| {
+      lexer.source.src.is_char_boundary(tok.span.start)
+        && lexer.source.src.is_char_boundary(tok.span.end)
+    }));
+  }
+
   #[test]
   fn test_large_file_performance() {
     let mut builder = String::new();
@@ -666,4 +685,28 @@ mod lexer_tests {
       let (_lexer, _engine) = lex_inline(&format!("fuzz-semi-{i}"), &src);
     }
   }
+
+  #[test]
+  fn test_token_line_and_column_tracking() {
+    let (lexer, engine) = lex_inline("lines.lox", "let a = 1;\nlet b = 2;\n  let c = 3;");
+
+    assert!(!engine.has_errors(), "three line source should be valid");
+
+    let first = &lexer.tokens[0];
+    assert_eq!((first.line, first.column), (1, 1));
+
+    let idents = lexer
+      .tokens
+      .iter()
+      .filter(|t| matches!(t.kind, TokenKind::Ident))
+      .collect::<Vec<_>>();
+    assert_eq!(idents.len(), 3);
+    assert_eq!(token_text(&lexer, idents[1]), "b");
+    assert_eq!((idents[1].line, idents[1].column), (2, 5));
+
+    let c = idents[2];
+    assert_eq!(token_text(&lexer, c), "c");
+    assert_eq!((c.line, c.column), (3, 7));
+    assert_eq!(lexer.line, 3);
+  }
 }